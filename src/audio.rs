@@ -0,0 +1,557 @@
+//! Helpers for working with headerless s32le sample files.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+use crate::rng::Rng;
+
+/// The sample format `convert` reads and writes, and so the one every raw
+/// file here is in.
+pub const SAMPLE_FORMAT: &str = "s32le";
+pub const BYTES_PER_SAMPLE: usize = pcm_width(SAMPLE_FORMAT);
+
+/// Raw PCM formats ffmpeg can read and write (`ffmpeg -formats`).
+const PCM_FORMATS: &[&str] = &[
+    "s8", "u8", "s16le", "s16be", "u16le", "u16be", "s24le", "s24be", "u24le", "u24be", "s32le", "s32be", "u32le",
+    "u32be", "f32le", "f32be", "f64le", "f64be", "alaw", "mulaw",
+];
+
+/// Bytes per sample of a name in `PCM_FORMATS`, from its bit count.
+const fn pcm_width(name: &str) -> usize {
+    match name.as_bytes() {
+        [_, b'1', b'6', ..] => 2,
+        [_, b'2', b'4', ..] => 3,
+        [_, b'3', b'2', ..] => 4,
+        [_, b'6', b'4', ..] => 8,
+        _ => 1,
+    }
+}
+
+/// Sample rate and sample format of the raw files passed between ffmpeg,
+/// `convert` and ffplay.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioFormat {
+    pub rate: u32,
+    pub format: &'static str,
+}
+
+impl Default for AudioFormat {
+    fn default() -> Self {
+        Self { rate: 48000, format: SAMPLE_FORMAT }
+    }
+}
+
+impl AudioFormat {
+    /// Checks a `--format` name. ffmpeg knows every raw PCM format, but
+    /// `convert` and the fades, mixing, metering and exports here read
+    /// `SAMPLE_FORMAT`, so any other is rejected for now.
+    pub fn parse_format(name: &str) -> Result<&'static str, String> {
+        match PCM_FORMATS.iter().find(|&&f| f == name) {
+            Some(&f) if f == SAMPLE_FORMAT => Ok(f),
+            Some(&f) => Err(format!(
+                "format '{}' ({}-bit) isn't supported yet - the render pipeline works on {}-bit {}",
+                f,
+                pcm_width(f) * 8,
+                BYTES_PER_SAMPLE * 8,
+                SAMPLE_FORMAT
+            )),
+            None => Err(format!("unknown format '{}' (ffmpeg raw PCM formats: {})", name, PCM_FORMATS.join(", "))),
+        }
+    }
+}
+
+/// A named ffmpeg channel layout and its channel count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelLayout {
+    pub name: &'static str,
+    pub channels: usize,
+}
+
+/// The standard layouts ffmpeg knows by name (`ffmpeg -layouts`).
+pub const LAYOUTS: &[ChannelLayout] = &[
+    ChannelLayout { name: "mono", channels: 1 },
+    ChannelLayout { name: "stereo", channels: 2 },
+    ChannelLayout { name: "2.1", channels: 3 },
+    ChannelLayout { name: "3.0", channels: 3 },
+    ChannelLayout { name: "3.0(back)", channels: 3 },
+    ChannelLayout { name: "4.0", channels: 4 },
+    ChannelLayout { name: "quad", channels: 4 },
+    ChannelLayout { name: "quad(side)", channels: 4 },
+    ChannelLayout { name: "3.1", channels: 4 },
+    ChannelLayout { name: "5.0", channels: 5 },
+    ChannelLayout { name: "5.0(side)", channels: 5 },
+    ChannelLayout { name: "4.1", channels: 5 },
+    ChannelLayout { name: "5.1", channels: 6 },
+    ChannelLayout { name: "5.1(side)", channels: 6 },
+    ChannelLayout { name: "6.0", channels: 6 },
+    ChannelLayout { name: "hexagonal", channels: 6 },
+    ChannelLayout { name: "6.1", channels: 7 },
+    ChannelLayout { name: "7.0", channels: 7 },
+    ChannelLayout { name: "7.1", channels: 8 },
+    ChannelLayout { name: "7.1(wide)", channels: 8 },
+    ChannelLayout { name: "octagonal", channels: 8 },
+];
+
+impl ChannelLayout {
+    pub const MONO: Self = LAYOUTS[0];
+    pub const STEREO: Self = LAYOUTS[1];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        LAYOUTS.iter().copied().find(|l| l.name == name)
+    }
+
+    pub fn names() -> String {
+        LAYOUTS.iter().map(|l| l.name).collect::<Vec<_>>().join(", ")
+    }
+
+    pub fn bytes_per_frame(self) -> usize {
+        self.channels * BYTES_PER_SAMPLE
+    }
+}
+
+/// Samples per chunk when streaming through files, so memory use stays
+/// constant however long the audio is.
+pub const CHUNK_SAMPLES: usize = 1 << 16;
+
+/// Buffered chunk-at-a-time reader of an s32le file.
+pub struct SampleReader {
+    inner: BufReader<File>,
+    bytes: Vec<u8>,
+}
+
+impl SampleReader {
+    pub fn open(path: &str) -> io::Result<Self> {
+        Ok(Self { inner: BufReader::new(File::open(path)?), bytes: Vec::new() })
+    }
+
+    /// Replaces `out` with up to `max` samples, returning how many were
+    /// read; 0 means end of file. Only the last chunk is ever short.
+    pub fn read_chunk(&mut self, out: &mut Vec<i32>, max: usize) -> io::Result<usize> {
+        self.bytes.resize(max * BYTES_PER_SAMPLE, 0);
+        let mut filled = 0;
+        while filled < self.bytes.len() {
+            match self.inner.read(&mut self.bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        out.clear();
+        out.extend(
+            self.bytes[..filled]
+                .chunks_exact(BYTES_PER_SAMPLE)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        );
+        Ok(out.len())
+    }
+
+    /// Moves the read cursor to sample index `sample`.
+    pub fn seek(&mut self, sample: u64) -> io::Result<()> {
+        self.inner.seek(SeekFrom::Start(sample * BYTES_PER_SAMPLE as u64)).map(|_| ())
+    }
+}
+
+pub struct SampleWriter {
+    inner: BufWriter<File>,
+}
+
+impl SampleWriter {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Self { inner: BufWriter::new(File::create(path)?) })
+    }
+
+    pub fn write(&mut self, samples: &[i32]) -> io::Result<()> {
+        for s in samples {
+            self.inner.write_all(&s.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Number of whole samples in an s32le file.
+pub fn sample_count(path: &str) -> io::Result<u64> {
+    Ok(std::fs::metadata(path)?.len() / BYTES_PER_SAMPLE as u64)
+}
+
+/// Reads up to `len` samples starting at sample index `start`.
+pub fn read_window(path: &str, start: u64, len: usize) -> io::Result<Vec<i32>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start * BYTES_PER_SAMPLE as u64))?;
+    let mut bytes = Vec::with_capacity(len * BYTES_PER_SAMPLE);
+    file.take((len * BYTES_PER_SAMPLE) as u64).read_to_end(&mut bytes)?;
+    Ok(bytes
+        .chunks_exact(BYTES_PER_SAMPLE)
+        .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// Rewrites `path` chunk by chunk through `f`, which gets each chunk's
+/// first frame index. Chunks hold whole frames of `channels` samples. The
+/// result goes to a temporary file that then replaces the original, and
+/// is removed if anything fails.
+pub fn map_file(path: &str, channels: usize, f: impl FnMut(u64, &[i32]) -> Vec<i32>) -> io::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    let written = write_mapped(path, &tmp_path, channels, f).and_then(|()| std::fs::rename(&tmp_path, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    written
+}
+
+/// The body of `map_file`, writing the mapped samples to `out_path`.
+fn write_mapped(
+    path: &str,
+    out_path: &str,
+    channels: usize,
+    mut f: impl FnMut(u64, &[i32]) -> Vec<i32>,
+) -> io::Result<()> {
+    let channels = channels.max(1);
+    let chunk_len = CHUNK_SAMPLES / channels * channels;
+    let mut reader = SampleReader::open(path)?;
+    let mut writer = SampleWriter::create(out_path)?;
+    let mut chunk = Vec::new();
+    let mut frame = 0u64;
+    while reader.read_chunk(&mut chunk, chunk_len)? > 0 {
+        writer.write(&f(frame, &chunk))?;
+        frame += (chunk.len() / channels) as u64;
+    }
+    writer.finish()
+}
+
+/// Streams two files side by side through `f` into `out_path`. Once the
+/// shorter file runs out, `f` sees an empty slice for it.
+fn zip_files(
+    a_path: &str,
+    b_path: &str,
+    out_path: &str,
+    f: impl Fn(&[i32], &[i32]) -> Vec<i32>,
+) -> io::Result<()> {
+    let mut a = SampleReader::open(a_path)?;
+    let mut b = SampleReader::open(b_path)?;
+    let mut writer = SampleWriter::create(out_path)?;
+    let (mut a_chunk, mut b_chunk) = (Vec::new(), Vec::new());
+    loop {
+        let a_len = a.read_chunk(&mut a_chunk, CHUNK_SAMPLES)?;
+        let b_len = b.read_chunk(&mut b_chunk, CHUNK_SAMPLES)?;
+        if a_len == 0 && b_len == 0 {
+            break;
+        }
+        writer.write(&f(&a_chunk, &b_chunk))?;
+    }
+    writer.finish()
+}
+
+/// Interleaves two mono streams into one stereo stream, padding the shorter
+/// side with silence.
+pub fn interleave_stereo(left: &[i32], right: &[i32]) -> Vec<i32> {
+    let frames = left.len().max(right.len());
+    let mut out = Vec::with_capacity(frames * 2);
+    for i in 0..frames {
+        out.push(left.get(i).copied().unwrap_or(0));
+        out.push(right.get(i).copied().unwrap_or(0));
+    }
+    out
+}
+
+pub fn write_interleaved_stereo(left_path: &str, right_path: &str, out_path: &str) -> io::Result<()> {
+    zip_files(left_path, right_path, out_path, interleave_stereo)
+}
+
+/// The residual `wet - dry`, sample by sample. Where one stream is longer
+/// the other counts as silence, so an echo tail survives intact.
+pub fn difference(wet: &[i32], dry: &[i32]) -> Vec<i32> {
+    let len = wet.len().max(dry.len());
+    (0..len)
+        .map(|i| {
+            let w = wet.get(i).copied().unwrap_or(0);
+            let d = dry.get(i).copied().unwrap_or(0);
+            w.saturating_sub(d)
+        })
+        .collect()
+}
+
+pub fn write_difference(wet_path: &str, dry_path: &str, out_path: &str) -> io::Result<()> {
+    zip_files(wet_path, dry_path, out_path, difference)
+}
+
+/// Crossfades `dry` into `wet`: `mix` 1.0 is all wet, 0.0 all dry. The
+/// shorter buffer is padded with silence.
+pub fn blend_raw(dry: &[i32], wet: &[i32], mix: f32) -> Vec<i32> {
+    let mix = mix.clamp(0.0, 1.0) as f64;
+    let len = dry.len().max(wet.len());
+    (0..len)
+        .map(|i| {
+            let d = dry.get(i).copied().unwrap_or(0) as f64;
+            let w = wet.get(i).copied().unwrap_or(0) as f64;
+            (d * (1.0 - mix) + w * mix).round() as i32
+        })
+        .collect()
+}
+
+pub fn write_blend(dry_path: &str, wet_path: &str, out_path: &str, mix: f32) -> io::Result<()> {
+    zip_files(dry_path, wet_path, out_path, |dry, wet| blend_raw(dry, wet, mix))
+}
+
+/// Sums several files with per-file gain into `out_path`. Shorter files are
+/// padded with silence, and if the sum would clip, the whole mix is scaled
+/// down so its peak lands exactly at full scale. This takes two streaming
+/// passes: one to find the peak, one to write.
+pub fn write_mix(parts: &[(&str, f32)], out_path: &str) -> io::Result<()> {
+    let peak = mix_pass(parts, None)?;
+    let scale = if peak > i32::MAX as f64 { i32::MAX as f64 / peak } else { 1.0 };
+    mix_pass(parts, Some((SampleWriter::create(out_path)?, scale)))?;
+    Ok(())
+}
+
+/// One pass over the mix, writing it scaled if `out` is given. Returns the
+/// peak of the unscaled sum.
+fn mix_pass(parts: &[(&str, f32)], mut out: Option<(SampleWriter, f64)>) -> io::Result<f64> {
+    let mut readers = parts
+        .iter()
+        .map(|(path, _)| SampleReader::open(path))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut chunk = Vec::new();
+    let mut sum: Vec<f64> = Vec::new();
+    let mut peak = 0f64;
+    loop {
+        sum.clear();
+        for (reader, (_, gain)) in readers.iter_mut().zip(parts) {
+            reader.read_chunk(&mut chunk, CHUNK_SAMPLES)?;
+            if sum.len() < chunk.len() {
+                sum.resize(chunk.len(), 0.0);
+            }
+            for (acc, &s) in sum.iter_mut().zip(&chunk) {
+                *acc += s as f64 * *gain as f64;
+            }
+        }
+        if sum.is_empty() {
+            break;
+        }
+        peak = sum.iter().fold(peak, |m, s| m.max(s.abs()));
+        if let Some((writer, scale)) = out.as_mut() {
+            let scaled: Vec<i32> = sum.iter().map(|s| (s * *scale) as i32).collect();
+            writer.write(&scaled)?;
+        }
+    }
+    if let Some((writer, _)) = out {
+        writer.finish()?;
+    }
+    Ok(peak)
+}
+
+/// Cuts a file into `out_paths.len()` consecutive pieces of whole frames,
+/// as equal as possible; the last piece also takes any remainder.
+pub fn split_file(path: &str, channels: usize, out_paths: &[String]) -> io::Result<()> {
+    let frame_bytes = (BYTES_PER_SAMPLE * channels.max(1)) as u64;
+    let frames = std::fs::metadata(path)?.len() / frame_bytes;
+    let per_piece = frames / out_paths.len().max(1) as u64;
+    let mut input = BufReader::new(File::open(path)?);
+    for (i, out_path) in out_paths.iter().enumerate() {
+        let mut out = BufWriter::new(File::create(out_path)?);
+        if i + 1 == out_paths.len() {
+            io::copy(&mut input, &mut out)?;
+        } else {
+            io::copy(&mut (&mut input).take(per_piece * frame_bytes), &mut out)?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Joins files end to end into `out_path`.
+pub fn concat_files(paths: &[String], out_path: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(out_path)?);
+    for path in paths {
+        io::copy(&mut File::open(path)?, &mut out)?;
+    }
+    out.flush()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FadeCurve {
+    Linear,
+    EqualPower,
+}
+
+impl FadeCurve {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Self::Linear),
+            "equal-power" | "equal_power" => Some(Self::EqualPower),
+            _ => None,
+        }
+    }
+
+    /// Gain at position `t` in 0.0..=1.0 of a fade-in.
+    fn gain(self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::EqualPower => (t * std::f32::consts::FRAC_PI_2).sin(),
+        }
+    }
+}
+
+/// Ramps the first and last `len` frames of a file's interleaved audio in
+/// and out to avoid clicks at the file boundaries. `samples` is a chunk of
+/// whole frames starting at `first_frame` of `total_frames`, so long files
+/// can be faded a chunk at a time. Short files get proportionally shorter
+/// fades.
+pub fn apply_fades(
+    samples: &mut [f32],
+    channels: usize,
+    first_frame: u64,
+    total_frames: u64,
+    len: usize,
+    curve: FadeCurve,
+) {
+    let channels = channels.max(1);
+    let len = (len as u64).min(total_frames / 2);
+    if len == 0 {
+        return;
+    }
+    for (i, frame) in samples.chunks_mut(channels).enumerate() {
+        let pos = first_frame + i as u64;
+        let from_edge = pos.min(total_frames.saturating_sub(1 + pos));
+        if from_edge >= len {
+            continue;
+        }
+        let gain = curve.gain(from_edge as f32 / len as f32);
+        for s in frame {
+            *s *= gain;
+        }
+    }
+}
+
+/// Converts float samples (full scale = 1.0) to `bits`-bit integers, e.g.
+/// -32768..=32767 at 16 bits. Below 32 bits, `dither` adds triangular
+/// (TPDF) noise of +/-1 LSB before rounding so quiet passages don't
+/// collapse into quantization distortion; at 32 bits no depth is lost, so
+/// nothing is dithered.
+pub fn quantize(samples: &[f32], bits: u32, dither: Option<&mut Rng>) -> Vec<i32> {
+    let bits = bits.clamp(8, 32);
+    let full_scale = ((1i64 << (bits - 1)) - 1) as f64;
+    let mut dither = dither.filter(|_| bits < 32);
+    samples
+        .iter()
+        .map(|&s| {
+            let noise = match dither.as_mut() {
+                Some(rng) => (rng.next_f32() - rng.next_f32()) as f64,
+                None => 0.0,
+            };
+            (s as f64 * full_scale + noise).round().clamp(-full_scale - 1.0, full_scale) as i32
+        })
+        .collect()
+}
+
+/// How a multichannel source is reduced to mono when imported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MonoSource {
+    Sum,
+    Left,
+    Right,
+}
+
+impl MonoSource {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sum" => Some(Self::Sum),
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Sum => Self::Left,
+            Self::Left => Self::Right,
+            Self::Right => Self::Sum,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Sum => "L+R sum",
+            Self::Left => "left only",
+            Self::Right => "right only",
+        }
+    }
+
+    /// ffmpeg arguments selecting the channel(s) for a mono import. Summing
+    /// uses ffmpeg's own downmix so mono sources pass through untouched; a
+    /// source known to be mono has no left or right channel to pick.
+    pub fn ffmpeg_args(self, source_channels: Option<usize>) -> &'static [&'static str] {
+        match self {
+            Self::Sum => &["-ac", "1"],
+            Self::Left | Self::Right if source_channels == Some(1) => &["-ac", "1"],
+            Self::Left => &["-af", "pan=mono|c0=FL"],
+            Self::Right => &["-af", "pan=mono|c0=FR"],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_pipeline_format_parses() {
+        assert_eq!(AudioFormat::parse_format("s32le"), Ok("s32le"));
+        assert_eq!(BYTES_PER_SAMPLE, 4);
+        let err = AudioFormat::parse_format("s16le").unwrap_err();
+        assert!(err.contains("isn't supported yet") && err.contains("16-bit"), "{}", err);
+        assert!(AudioFormat::parse_format("s24be").unwrap_err().contains("24-bit"));
+        assert!(AudioFormat::parse_format("wav").unwrap_err().contains("unknown format"));
+    }
+
+    #[test]
+    fn fades_ramp_the_ends_and_quantizing_dithers_only_below_32_bits() {
+        let mut samples = vec![1.0f32; 8];
+        apply_fades(&mut samples, 1, 0, 8, 2, FadeCurve::Linear);
+        assert_eq!(samples, [0.0, 0.5, 1.0, 1.0, 1.0, 1.0, 0.5, 0.0]);
+
+        assert_eq!(quantize(&[0.5, -1.0, 1.0], 16, None), [16384, -32767, 32767]);
+        assert_eq!(quantize(&[0.5, -1.0, 1.0], 32, Some(&mut Rng::new(1))), [1 << 30, -i32::MAX, i32::MAX]);
+        let dithered = quantize(&[0.0; 64], 16, Some(&mut Rng::new(1)));
+        assert!(dithered.iter().all(|s| (-1..=1).contains(s)));
+        assert!(dithered.iter().any(|&s| s != 0));
+    }
+
+    #[test]
+    fn map_file_removes_its_temp_file_on_error() {
+        // A directory opens but can't be read, so the write fails midway.
+        let dir = std::env::temp_dir().join(format!("audionoise-map-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy().into_owned();
+        assert!(map_file(&path, 1, |_, chunk| chunk.to_vec()).is_err());
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn blend_raw_weights_dry_and_wet() {
+        let dry = [1000, -1000, 0];
+        let wet = [0, 1000, 400];
+        assert_eq!(blend_raw(&dry, &wet, 1.0), wet);
+        assert_eq!(blend_raw(&dry, &wet, 0.0), dry);
+        assert_eq!(blend_raw(&dry, &wet, 0.25), [750, -500, 100]);
+    }
+
+    #[test]
+    fn blend_raw_pads_the_shorter_buffer() {
+        // An echo tail longer than the dry input keeps going, scaled.
+        assert_eq!(blend_raw(&[100], &[100, 200, 300], 0.5), [100, 100, 150]);
+        assert_eq!(blend_raw(&[100, 200], &[], 0.5), [50, 100]);
+    }
+
+    #[test]
+    fn blend_raw_stays_in_range_at_full_scale() {
+        assert_eq!(blend_raw(&[i32::MAX], &[i32::MAX], 0.3), [i32::MAX]);
+        assert_eq!(blend_raw(&[i32::MIN], &[i32::MIN], 0.7), [i32::MIN]);
+    }
+}
@@ -0,0 +1,272 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::audio::{AudioFormat, ChannelLayout};
+use crate::player::{Backend, Resampler};
+
+/// Command-line options.
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub effect: Option<String>,
+    pub timeout: Option<Duration>,
+    pub pots: Option<[PotSpec; 4]>,
+    pub resampler: Option<Resampler>,
+    pub backend: Option<Backend>,
+    pub layout: Option<ChannelLayout>,
+    /// `--rate` and `--format`, checked while parsing.
+    pub rate: Option<u32>,
+    pub format: Option<&'static str>,
+    pub tutorial: bool,
+    /// Start with defaults instead of the session saved on the last quit.
+    pub no_restore: bool,
+    pub help: bool,
+    /// Audio file to use as the source instead of the auto-discovered one.
+    pub input: Option<PathBuf>,
+    /// Further INPUT arguments after the first; with them, or a directory
+    /// as INPUT, the TUI steps through the files as a playlist.
+    pub queue: Vec<PathBuf>,
+    /// Render once without the TUI and exit.
+    pub batch: bool,
+    /// Where `--batch` writes the render.
+    pub output: Option<PathBuf>,
+    /// Read raw s32le audio from stdin; only with `--batch`, since the TUI
+    /// reads its keys from stdin.
+    pub stdin: bool,
+    /// Log every command line run to `audionoise.log`.
+    pub verbose: bool,
+    /// Directory holding `convert`, `input.raw`, `output.raw` and
+    /// `presets.toml`, instead of looking in `.` and `..`.
+    pub project: Option<PathBuf>,
+    /// The DSP helper to run instead of the project's `convert`; a bare
+    /// name is looked up on PATH.
+    pub convert: Option<PathBuf>,
+}
+
+const MIN_RATE: u32 = 8000;
+const MAX_RATE: u32 = 384_000;
+
+pub const USAGE: &str = "\
+Usage: audionoise-tui [OPTIONS] [INPUT]...
+
+Arguments:
+  [INPUT]...        Source audio: mp3, wav, flac, ... (imported with ffmpeg)
+                    or raw s32le; defaults to input.raw, else the first
+                    mp3/wav/flac/ogg/m4a file found.
+                    Several files, or a directory, make a playlist (J/K)
+
+Options:
+  --effect <name>   Effect to select on startup
+  --pots <a,b,c,d>  Starting pot values for the selected effect (0.0-1.0);
+                    '+0.1'/'-0.2' adjust the default, '=' keeps it
+  --timeout <secs>  Kill 'convert' if it runs longer than this
+  --resampler <r>   Monitoring resampler: ffplay (default), swr or soxr
+  --backend <b>     Playback backend: ffplay (default) or stream
+  --layout <name>   Channel layout: mono (default), stereo, 2.1, 5.1, 7.1, ...
+  --rate <hz>       Sample rate for import, render and playback (48000)
+  --format <fmt>    Raw sample format (s32le, the only one convert reads so far)
+  --tutorial        Show the first-run tutorial again
+  --no-restore      Start fresh instead of restoring the last session
+  --batch           Render INPUT with --effect/--pots and exit, without
+                    the TUI or playback; exits nonzero on failure
+  --in <file>       Same as INPUT
+  --out <file>      Where --batch writes the render (default output.raw
+                    in the project directory)
+  --stdin           Read raw s32le mono audio at --rate from stdin instead
+                    of INPUT; needs --batch, as the TUI reads keys from
+                    stdin. E.g. ffmpeg -i song.mp3 -f s32le -ar 48000
+                    -ac 1 - | audionoise-tui --batch --stdin
+  --verbose         Log every convert/ffmpeg/ffplay command line to
+                    audionoise.log (Ctrl+D toggles it in the TUI)
+  --project <dir>   Keep convert, input.raw, output.raw and presets.toml
+                    in <dir> (default: . or .., whichever has convert)
+  --convert <path>  Run this convert binary instead of the project's, e.g.
+                    one installed system-wide (a bare name is looked up
+                    on PATH)
+  -h, --help        Show this help
+
+Environment:
+  AUDIONOISE_EFFECT  Same as --effect (the flag wins when both are set)
+  AUDIONOISE_POTS    Same as --pots (the flag wins when both are set)
+  AUDIONOISE_CONVERT Same as --convert (the flag wins when both are set)";
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        let mut args = Self::parse_from(std::env::args().skip(1))?;
+        args.merge_env(|key| std::env::var(key).ok())?;
+        Ok(args)
+    }
+
+    /// Fills options not given on the command line from `AUDIONOISE_*`
+    /// environment variables.
+    pub fn merge_env<F>(&mut self, var: F) -> Result<(), String>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if self.effect.is_none() {
+            self.effect = var("AUDIONOISE_EFFECT").filter(|v| !v.trim().is_empty());
+        }
+        if self.convert.is_none() {
+            self.convert = var("AUDIONOISE_CONVERT").filter(|v| !v.trim().is_empty()).map(PathBuf::from);
+        }
+        if self.pots.is_none() {
+            if let Some(raw) = var("AUDIONOISE_POTS").filter(|v| !v.trim().is_empty()) {
+                self.pots = Some(parse_pots(&raw).map_err(|e| format!("AUDIONOISE_POTS: {}", e))?);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn parse_from<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut parsed = Self::default();
+        let mut iter = args.into_iter();
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--effect" => parsed.effect = Some(value(&mut iter, &arg)?),
+                "--pots" => {
+                    let raw = value(&mut iter, &arg)?;
+                    parsed.pots = Some(parse_pots(&raw).map_err(|e| format!("--pots: {}", e))?);
+                }
+                "--timeout" => {
+                    let raw = value(&mut iter, &arg)?;
+                    let secs: f64 = raw
+                        .parse()
+                        .map_err(|_| format!("--timeout: invalid number '{}'", raw))?;
+                    // Non-finite or huge values don't fit a Duration.
+                    let timeout = Duration::try_from_secs_f64(secs).ok().filter(|d| !d.is_zero());
+                    let Some(timeout) = timeout else {
+                        return Err(format!("--timeout: '{}' is not a usable number of seconds", raw));
+                    };
+                    parsed.timeout = Some(timeout);
+                }
+                "--resampler" => {
+                    let raw = value(&mut iter, &arg)?;
+                    parsed.resampler = Some(Resampler::parse(&raw).ok_or_else(|| {
+                        format!("--resampler: unknown '{}' (ffplay, swr, soxr)", raw)
+                    })?);
+                }
+                "--backend" => {
+                    let raw = value(&mut iter, &arg)?;
+                    parsed.backend = Some(Backend::parse(&raw).ok_or_else(|| {
+                        format!("--backend: unknown '{}' (ffplay, stream)", raw)
+                    })?);
+                }
+                "--layout" => {
+                    let raw = value(&mut iter, &arg)?;
+                    parsed.layout = Some(ChannelLayout::parse(&raw).ok_or_else(|| {
+                        format!("--layout: unknown '{}' (one of: {})", raw, ChannelLayout::names())
+                    })?);
+                }
+                "--rate" => {
+                    let raw = value(&mut iter, &arg)?;
+                    let rate: u32 = raw.parse().map_err(|_| format!("--rate: invalid number '{}'", raw))?;
+                    if !(MIN_RATE..=MAX_RATE).contains(&rate) {
+                        return Err(format!("--rate must be between {} and {} Hz", MIN_RATE, MAX_RATE));
+                    }
+                    parsed.rate = Some(rate);
+                }
+                "--format" => {
+                    let raw = value(&mut iter, &arg)?;
+                    parsed.format = Some(AudioFormat::parse_format(&raw).map_err(|e| format!("--format: {}", e))?);
+                }
+                "--tutorial" => parsed.tutorial = true,
+                "--no-restore" => parsed.no_restore = true,
+                "--batch" => parsed.batch = true,
+                "--stdin" => parsed.stdin = true,
+                "--verbose" => parsed.verbose = true,
+                "--in" => parsed.input = Some(PathBuf::from(value(&mut iter, &arg)?)),
+                "--out" => parsed.output = Some(PathBuf::from(value(&mut iter, &arg)?)),
+                "--project" => {
+                    let dir = PathBuf::from(value(&mut iter, &arg)?);
+                    if !dir.is_dir() {
+                        return Err(format!("--project: '{}' is not a directory", dir.display()));
+                    }
+                    parsed.project = Some(dir);
+                }
+                "--convert" => parsed.convert = Some(PathBuf::from(value(&mut iter, &arg)?)),
+                "-h" | "--help" => parsed.help = true,
+                other if !other.starts_with('-') && parsed.input.is_none() => {
+                    parsed.input = Some(PathBuf::from(other));
+                }
+                other if !other.starts_with('-') => parsed.queue.push(PathBuf::from(other)),
+                other => return Err(format!("unknown argument '{}'\n\n{}", other, USAGE)),
+            }
+        }
+
+        if parsed.stdin && !parsed.batch {
+            return Err("--stdin needs --batch: the interactive TUI reads its keys from stdin".to_string());
+        }
+        if parsed.batch && !parsed.queue.is_empty() {
+            return Err("--batch renders a single input file".to_string());
+        }
+        if parsed.stdin && parsed.input.is_some() {
+            return Err("--stdin can't be combined with an input file".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+fn value(iter: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    iter.next()
+        .ok_or_else(|| format!("{} requires a value", flag))
+}
+
+/// One `--pots` entry: an absolute value, an offset from the effect's
+/// default, or `=` to keep the default.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PotSpec {
+    Abs(f32),
+    Rel(f32),
+    Keep,
+}
+
+impl PotSpec {
+    fn parse(token: &str) -> Result<Self, String> {
+        if token == "=" {
+            return Ok(Self::Keep);
+        }
+        let v: f32 = token
+            .parse()
+            .map_err(|_| format!("invalid number '{}'", token))?;
+        if !v.is_finite() {
+            return Err(format!("invalid number '{}'", token));
+        }
+        if token.starts_with('+') || token.starts_with('-') {
+            Ok(Self::Rel(v))
+        } else if (0.0..=1.0).contains(&v) {
+            Ok(Self::Abs(v))
+        } else {
+            Err(format!("value {} is outside 0.0-1.0", token))
+        }
+    }
+}
+
+/// Parses `a,b,c,d` where each entry is an absolute value in 0.0..=1.0,
+/// a signed offset like `+0.1`, or `=`.
+pub fn parse_pots(raw: &str) -> Result<[PotSpec; 4], String> {
+    let parts: Vec<&str> = raw.split(',').map(str::trim).collect();
+    if parts.len() != 4 {
+        return Err(format!("expected 4 comma-separated values, got {}", parts.len()));
+    }
+    let mut specs = [PotSpec::Keep; 4];
+    for (slot, part) in specs.iter_mut().zip(&parts) {
+        *slot = PotSpec::parse(part)?;
+    }
+    Ok(specs)
+}
+
+/// Applies pot specs to an effect's defaults, clamping offsets into range.
+pub fn resolve_pots(specs: &[PotSpec; 4], defaults: &[f32; 4]) -> [f32; 4] {
+    let mut pots = *defaults;
+    for (pot, spec) in pots.iter_mut().zip(specs) {
+        *pot = match *spec {
+            PotSpec::Abs(v) => v,
+            PotSpec::Rel(delta) => (*pot + delta).clamp(0.0, 1.0),
+            PotSpec::Keep => *pot,
+        };
+    }
+    pots
+}
@@ -0,0 +1,59 @@
+//! The `--verbose` command log. Every `convert`, ffmpeg and ffplay run is
+//! appended to `audionoise.log` as a shell-pasteable line, built from the
+//! `Command` itself so it always matches what ran. The render worker and
+//! the player log from their own threads, hence the process-wide state.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{command_line, shell_quote};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PATH: OnceLock<String> = OnceLock::new();
+
+/// Sets where the log goes and whether it starts on. Only the first call
+/// picks the path.
+pub fn init(path: &str, enabled: bool) {
+    let _ = PATH.set(path.to_string());
+    set_enabled(enabled);
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn path() -> &'static str {
+    PATH.get().map_or("audionoise.log", String::as_str)
+}
+
+/// Logs `cmd` as it is about to run.
+pub fn record(cmd: &Command) {
+    if enabled() {
+        append(&command_line(cmd));
+    }
+}
+
+/// Logs `cmd` with its stdin and stdout redirected from and to files.
+pub fn record_redirected(cmd: &Command, input: &str, output: &str) {
+    if enabled() {
+        append(&format!("{} < {} > {}", command_line(cmd), shell_quote(input), shell_quote(output)));
+    }
+}
+
+/// Appends one line stamped with the UTC time of day. A log that can't be
+/// written is skipped rather than getting in the way of the run itself.
+fn append(line: &str) {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let stamp = format!("{:02}:{:02}:{:02}", secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path()) {
+        let _ = writeln!(file, "[{}] {}", stamp, line);
+    }
+}
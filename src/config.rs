@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::audio::{AudioFormat, ChannelLayout, FadeCurve, MonoSource};
+use crate::keymap::Keymap;
+use crate::meter::MeterScale;
+use crate::player::{Backend, Resampler};
+use crate::presets;
+use crate::spectrogram::ColorMap;
+use crate::theme::{Theme, THEMES};
+use crate::toml_lite;
+
+/// User settings read from `~/.config/audionoise/config.toml`.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub default_effect: Option<String>,
+    /// Effects to show, by name; `None` shows all of them.
+    pub effects: Option<Vec<String>>,
+    /// Kill `convert` when a render runs longer than this.
+    pub convert_timeout: Option<Duration>,
+    /// Directory of per-effect preset banks; defaults to `<config dir>/banks`.
+    pub bank_dir: Option<PathBuf>,
+    /// Fade the rendered output in and out to avoid boundary clicks.
+    pub fade_enabled: bool,
+    pub fade_ms: u32,
+    pub fade_curve: FadeCurve,
+    /// Channel selection used when importing a stereo source to mono.
+    pub mono_source: MonoSource,
+    /// Resampling path used for monitoring playback.
+    pub resampler: Resampler,
+    /// How monitoring audio is played; `Stream` decodes in-process.
+    pub backend: Backend,
+    /// Rate to resample monitoring to; `None` keeps the render rate.
+    pub monitor_rate: Option<u32>,
+    /// Stop looped playback automatically after this long; off by default.
+    pub auto_stop: Option<Duration>,
+    /// Spectrogram PNG export: (width, height) in pixels and color map.
+    pub spectrogram_size: (u32, u32),
+    pub spectrogram_colormap: ColorMap,
+    /// Per-effect "panic" pot values from the `[safe_values]` table.
+    pub safe_values: HashMap<String, [f32; 4]>,
+    /// Channel layout for import, rendering and playback.
+    pub layout: ChannelLayout,
+    /// Sample rate and format for import, rendering and playback; set with
+    /// `--rate` and `--format`.
+    pub format: AudioFormat,
+    /// Units for the level meter and other level readouts.
+    pub meter_scale: MeterScale,
+    /// Bit depth of exported WAV files (16, 24 or 32) and whether to
+    /// TPDF-dither when that is below 32. `output.raw` always keeps 32.
+    pub output_bits: u32,
+    pub dither: bool,
+    /// How often a missing `convert`/`input.raw` warning is re-checked;
+    /// `None` only re-checks on demand.
+    pub env_recheck: Option<Duration>,
+    /// `(effect, pot)` names the four pots control in mapped mode, from
+    /// `pot_map = ["echo.mix", ...]`.
+    pub pot_map: Option<[(String, String); 4]>,
+    /// Program (and leading arguments) that opens a folder in the file
+    /// manager; the output directory is appended.
+    pub reveal_command: Vec<String>,
+    /// Left/Right step for pots that use the standard coarse step.
+    pub pot_step: f32,
+    /// Width in characters of the pot bars.
+    pub bar_width: usize,
+    /// How long the main loop waits for input before redrawing.
+    pub poll: Duration,
+    /// Render and play straight after 'x' randomizes the pots.
+    pub random_autoplay: bool,
+    /// End every render with a brickwall `compress` stage; Ctrl+B toggles.
+    pub limiter: bool,
+    /// Ask before quitting with tweaked pots or an unexported render.
+    pub confirm_quit: bool,
+    /// Colors for the whole UI; cycled with Ctrl+T.
+    pub theme: &'static Theme,
+    /// Recorded macros from the `[macros]` table: slot name (`f1`..`f4`) to
+    /// action names, resolved by the app.
+    pub macros: HashMap<String, Vec<String>>,
+    /// Keys for the core actions, from the `[keys]` table.
+    pub keymap: Keymap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_effect: None,
+            effects: None,
+            convert_timeout: None,
+            bank_dir: None,
+            fade_enabled: true,
+            fade_ms: 5,
+            fade_curve: FadeCurve::Linear,
+            mono_source: MonoSource::Sum,
+            resampler: Resampler::Player,
+            backend: Backend::Ffplay,
+            monitor_rate: None,
+            auto_stop: None,
+            spectrogram_size: (800, 256),
+            spectrogram_colormap: ColorMap::Heat,
+            safe_values: HashMap::new(),
+            layout: ChannelLayout::MONO,
+            format: AudioFormat::default(),
+            meter_scale: MeterScale::Linear,
+            output_bits: 32,
+            dither: true,
+            env_recheck: Some(Duration::from_secs(2)),
+            pot_map: None,
+            reveal_command: vec![default_reveal_command().to_string()],
+            pot_step: 0.05,
+            bar_width: 20,
+            poll: Duration::from_millis(100),
+            random_autoplay: false,
+            limiter: false,
+            confirm_quit: true,
+            theme: &THEMES[0],
+            macros: HashMap::new(),
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+fn default_reveal_command() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    }
+}
+
+/// `$XDG_CONFIG_HOME/audionoise`, falling back to `~/.config/audionoise`.
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("audionoise"));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("audionoise"))
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("config.toml"))
+}
+
+/// Where presets saved from the UI are kept, next to the config file.
+pub fn presets_path() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("presets.toml"))
+}
+
+/// Where the session is saved on quit and restored from on launch.
+pub fn session_path() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("session.toml"))
+}
+
+/// Commented starter config written once the first-run tutorial finishes.
+const STARTER_CONFIG: &str = "\
+# audionoise configuration. Uncomment a line to change the default.
+# default_effect = \"flanger\"
+# effects = [\"flanger\", \"echo\"]   # show only these
+# convert_timeout = 30          # seconds
+# bank_dir = \"~/.config/audionoise/banks\"
+# fade = true
+# fade_ms = 5
+# fade_curve = \"linear\"         # linear, equal-power
+# mono_source = \"sum\"           # sum, left, right
+# resampler = \"ffplay\"          # ffplay, swr, soxr
+# backend = \"ffplay\"            # ffplay, stream
+# monitor_rate = 44100
+# auto_stop_secs = 600
+# spectrogram_width = 800
+# spectrogram_height = 256
+# spectrogram_colormap = \"heat\" # gray, heat
+# layout = \"mono\"
+# meter_scale = \"linear\"        # linear, db
+# output_bits = 32              # WAV export: 16, 24, 32
+# dither = true                 # only when exporting below 32 bits
+# env_recheck_secs = 2          # 0 to only re-check with F5
+# reveal_command = \"xdg-open\"   # opens the output folder (Ctrl+O)
+# pot_map = [\"echo.mix\", \"flanger.depth\", \"phaser.rate\", \"fm.mix\"]   # 't' mode
+# pot_step = 0.05               # Left/Right step (Shift: 0.01)
+# bar_width = 20                # pot bar width in characters
+# poll_ms = 100                 # input poll interval; raise on slow terminals
+# random_autoplay = false       # process right after 'x' randomizes
+# limiter = false               # brickwall limiter before playback (Ctrl+B)
+# confirm_quit = true           # ask before quitting with unsaved changes
+# theme = \"default\"            # default, monochrome, high-contrast, solarized
+
+# [safe_values]
+# echo = [0.3, 0.1, 0.3, 0.3]
+
+# Macros replayed with F1-F4; recording with F9 saves them here.
+# [macros]
+# f1 = [\"smart_randomize\", \"process\"]
+
+# Keys for the core actions. A listed action gets exactly these keys, and
+# they stop doing whatever they did by default; keys with a fixed binding
+# in the '?' help can't be used. Names are single characters
+# or Up, Down, Left, Right, Tab, Enter, Esc, Space, Backspace, Home, End,
+# PageUp, PageDown, F1-F12. The defaults:
+# [keys]
+# next_effect = [\"Down\", \"j\"]
+# prev_effect = [\"Up\", \"k\"]
+# inc_pot = [\"Right\", \"l\"]
+# dec_pot = [\"Left\", \"h\"]
+# next_pot = [\"Tab\"]
+# play = [\"p\", \"P\"]
+# stop = [\"s\", \"S\"]
+# reset = [\"r\"]
+# quit = [\"q\", \"Q\"]
+";
+
+/// Writes the starter config if no config file exists yet.
+pub fn write_starter_config() -> std::io::Result<()> {
+    let Some(path) = config_path() else {
+        return Ok(());
+    };
+    if path.exists() {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, STARTER_CONFIG)
+}
+
+/// Stores a macro as `slot = [..]` in the config file's `[macros]` table.
+pub fn save_macro(slot: &str, actions: &[String]) -> std::io::Result<()> {
+    let value = toml_lite::Value::Array(actions.iter().map(|a| toml_lite::Value::Str(a.clone())).collect());
+    save_entry(Some("macros"), slot, &value)
+}
+
+/// Stores the theme name as a top-level `theme = ".."`.
+pub fn save_theme(theme: &Theme) -> std::io::Result<()> {
+    save_entry(None, "theme", &toml_lite::Value::Str(theme.name.to_string()))
+}
+
+/// Sets `key = value` in `[table]`, or above the first table when `table`
+/// is `None`, editing line by line so the user's comments and layout
+/// survive.
+fn save_entry(table: Option<&str>, key: &str, value: &toml_lite::Value) -> std::io::Result<()> {
+    let Some(path) = config_path() else {
+        return Err(std::io::Error::other("no config directory"));
+    };
+    let text = std::fs::read_to_string(&path).unwrap_or_default();
+    let entry = format!("{} = {}", key, toml_lite::format_value(value));
+
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let is_header = |line: &String| line.trim_start().starts_with('[');
+    let is_key = |line: &String| line.split_once('=').is_some_and(|(k, _)| k.trim() == key);
+    let header = match table {
+        Some(table) => lines.iter().position(|l| l.trim() == format!("[{}]", table)),
+        None => Some(0),
+    };
+    match header {
+        Some(start) => {
+            let first = if table.is_some() { start + 1 } else { start };
+            let end = lines[first..].iter().position(is_header).map_or(lines.len(), |i| first + i);
+            match (first..end).find(|&i| is_key(&lines[i])) {
+                Some(i) => lines[i] = entry,
+                None if table.is_some() => lines.insert(first, entry),
+                None => {
+                    // After the last top-level line, not glued to a header.
+                    let at = (first..end).rev().find(|&i| !lines[i].trim().is_empty()).map_or(first, |i| i + 1);
+                    lines.insert(at, entry);
+                }
+            }
+        }
+        None => {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", table.unwrap_or_default()));
+            lines.push(entry);
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, lines.join("\n") + "\n")
+}
+
+/// A number of seconds from the config as a duration, `None` when it's 0
+/// or less. Values too large (or not finite) for a `Duration` are errors.
+fn seconds(v: &toml_lite::Value, key: &str) -> Result<Option<Duration>, String> {
+    let secs = v.as_f64().ok_or_else(|| format!("{} must be a number of seconds", key))?;
+    if secs <= 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(secs).map(Some).map_err(|_| format!("{} is not a usable number of seconds", key))
+}
+
+/// Expands a leading `~/` to the user's home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+impl Config {
+    pub fn bank_dir(&self) -> Option<PathBuf> {
+        self.bank_dir.clone().or_else(|| config_dir().map(|d| d.join("banks")))
+    }
+
+    /// Loads the config file, returning defaults when it doesn't exist.
+    pub fn load() -> Result<Self, String> {
+        let path = match config_path() {
+            Some(p) if p.exists() => p,
+            _ => return Ok(Self::default()),
+        };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let doc = toml_lite::parse(text)?;
+        let mut config = Self::default();
+
+        if let Some(v) = doc.get("default_effect") {
+            let name = v.as_str().ok_or("default_effect must be a string")?;
+            config.default_effect = Some(name.to_string());
+        }
+
+        if let Some(v) = doc.get("effects") {
+            let items = v.as_array().ok_or("effects must be an array of effect names")?;
+            let names = items
+                .iter()
+                .map(|item| item.as_str().map(str::to_lowercase).ok_or("effects must be an array of effect names"))
+                .collect::<Result<Vec<_>, _>>()?;
+            config.effects = Some(names);
+        }
+
+        if let Some(v) = doc.get("convert_timeout") {
+            if let Some(timeout) = seconds(v, "convert_timeout")? {
+                config.convert_timeout = Some(timeout);
+            }
+        }
+
+        if let Some(v) = doc.get("bank_dir") {
+            let dir = v.as_str().ok_or("bank_dir must be a string")?;
+            config.bank_dir = Some(expand_home(dir));
+        }
+
+        if let Some(v) = doc.get("fade") {
+            config.fade_enabled = v.as_bool().ok_or("fade must be true or false")?;
+        }
+
+        if let Some(v) = doc.get("fade_ms") {
+            let ms = v.as_f64().ok_or("fade_ms must be a number")?;
+            config.fade_ms = ms.max(0.0) as u32;
+        }
+
+        if let Some(v) = doc.get("fade_curve") {
+            let name = v.as_str().ok_or("fade_curve must be a string")?;
+            config.fade_curve = FadeCurve::parse(name)
+                .ok_or_else(|| format!("unknown fade_curve '{}' (linear, equal-power)", name))?;
+        }
+
+        if let Some(v) = doc.get("mono_source") {
+            let name = v.as_str().ok_or("mono_source must be a string")?;
+            config.mono_source = MonoSource::parse(name)
+                .ok_or_else(|| format!("unknown mono_source '{}' (sum, left, right)", name))?;
+        }
+
+        if let Some(v) = doc.get("resampler") {
+            let name = v.as_str().ok_or("resampler must be a string")?;
+            config.resampler = Resampler::parse(name)
+                .ok_or_else(|| format!("unknown resampler '{}' (ffplay, swr, soxr)", name))?;
+        }
+
+        if let Some(v) = doc.get("backend") {
+            let name = v.as_str().ok_or("backend must be a string")?;
+            config.backend = Backend::parse(name)
+                .ok_or_else(|| format!("unknown backend '{}' (ffplay, stream)", name))?;
+        }
+
+        if let Some(v) = doc.get("monitor_rate") {
+            let rate = v.as_f64().ok_or("monitor_rate must be a number")?;
+            if rate < 1.0 {
+                return Err("monitor_rate must be a positive sample rate".to_string());
+            }
+            config.monitor_rate = Some(rate as u32);
+        }
+
+        if let Some(v) = doc.get("auto_stop_secs") {
+            if let Some(auto_stop) = seconds(v, "auto_stop_secs")? {
+                config.auto_stop = Some(auto_stop);
+            }
+        }
+
+        for (key, slot) in [
+            ("spectrogram_width", &mut config.spectrogram_size.0),
+            ("spectrogram_height", &mut config.spectrogram_size.1),
+        ] {
+            if let Some(v) = doc.get(key) {
+                let px = v.as_f64().ok_or_else(|| format!("{} must be a number", key))?;
+                if !(16.0..=8192.0).contains(&px) {
+                    return Err(format!("{} must be between 16 and 8192", key));
+                }
+                *slot = px as u32;
+            }
+        }
+
+        if let Some(v) = doc.get("spectrogram_colormap") {
+            let name = v.as_str().ok_or("spectrogram_colormap must be a string")?;
+            config.spectrogram_colormap = ColorMap::parse(name)
+                .ok_or_else(|| format!("unknown spectrogram_colormap '{}' (gray, heat)", name))?;
+        }
+
+        if let Some(table) = doc.tables.get("safe_values") {
+            for (effect, value) in table {
+                let pots = presets::parse_pots(value)
+                    .map_err(|e| format!("safe_values.{}: {}", effect, e))?;
+                config.safe_values.insert(effect.to_lowercase(), pots);
+            }
+        }
+
+        if let Some(table) = doc.tables.get("keys") {
+            config.keymap = Keymap::from_table(table)?;
+        }
+
+        if let Some(table) = doc.tables.get("macros") {
+            for (slot, value) in table {
+                let names = value
+                    .as_array()
+                    .and_then(|items| items.iter().map(|i| i.as_str().map(str::to_string)).collect::<Option<Vec<_>>>())
+                    .ok_or_else(|| format!("macros.{} must be an array of action names", slot))?;
+                config.macros.insert(slot.to_lowercase(), names);
+            }
+        }
+
+        if let Some(v) = doc.get("layout") {
+            let name = v.as_str().ok_or("layout must be a string")?;
+            config.layout = ChannelLayout::parse(name).ok_or_else(|| {
+                format!("unknown layout '{}' (one of: {})", name, ChannelLayout::names())
+            })?;
+        }
+
+        if let Some(v) = doc.get("meter_scale") {
+            let name = v.as_str().ok_or("meter_scale must be a string")?;
+            config.meter_scale = MeterScale::parse(name)
+                .ok_or_else(|| format!("unknown meter_scale '{}' (linear, db)", name))?;
+        }
+
+        if let Some(v) = doc.get("output_bits") {
+            let bits = v.as_f64().ok_or("output_bits must be a number")?;
+            if ![16.0, 24.0, 32.0].contains(&bits) {
+                return Err("output_bits must be 16, 24 or 32".to_string());
+            }
+            config.output_bits = bits as u32;
+        }
+
+        if let Some(v) = doc.get("dither") {
+            config.dither = v.as_bool().ok_or("dither must be true or false")?;
+        }
+
+        if let Some(v) = doc.get("env_recheck_secs") {
+            config.env_recheck = seconds(v, "env_recheck_secs")?;
+        }
+
+        if let Some(v) = doc.get("pot_step") {
+            let step = v.as_f64().ok_or("pot_step must be a number")?;
+            if !(step > 0.0 && step <= 1.0) {
+                return Err("pot_step must be in 0.0 < step <= 1.0".to_string());
+            }
+            config.pot_step = step as f32;
+        }
+
+        if let Some(v) = doc.get("bar_width") {
+            let width = v.as_f64().ok_or("bar_width must be a number")?;
+            if !(1.0..=200.0).contains(&width) {
+                return Err("bar_width must be between 1 and 200".to_string());
+            }
+            config.bar_width = width as usize;
+        }
+
+        if let Some(v) = doc.get("theme") {
+            let name = v.as_str().ok_or("theme must be a string")?;
+            config.theme = Theme::parse(name)
+                .ok_or_else(|| format!("unknown theme '{}' ({})", name, Theme::names()))?;
+        }
+
+        if let Some(v) = doc.get("poll_ms") {
+            let ms = v.as_f64().ok_or("poll_ms must be a number of milliseconds")?;
+            if ms < 1.0 {
+                return Err("poll_ms must be at least 1".to_string());
+            }
+            config.poll = Duration::from_millis(ms as u64);
+        }
+
+        if let Some(v) = doc.get("random_autoplay") {
+            config.random_autoplay = v.as_bool().ok_or("random_autoplay must be true or false")?;
+        }
+
+        if let Some(v) = doc.get("limiter") {
+            config.limiter = v.as_bool().ok_or("limiter must be true or false")?;
+        }
+
+        if let Some(v) = doc.get("confirm_quit") {
+            config.confirm_quit = v.as_bool().ok_or("confirm_quit must be true or false")?;
+        }
+
+        if let Some(v) = doc.get("pot_map") {
+            const MSG: &str = "pot_map must be 4 \"effect.pot\" strings";
+            let items = v.as_array().filter(|a| a.len() == 4).ok_or(MSG)?;
+            let mut map: [(String, String); 4] = Default::default();
+            for (slot, item) in map.iter_mut().zip(items) {
+                let (effect, pot) = item.as_str().and_then(|t| t.split_once('.')).ok_or(MSG)?;
+                *slot = (effect.trim().to_lowercase(), pot.trim().to_lowercase());
+            }
+            config.pot_map = Some(map);
+        }
+
+        if let Some(v) = doc.get("reveal_command") {
+            let cmd: Vec<String> = v
+                .as_str()
+                .ok_or("reveal_command must be a string")?
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            if cmd.is_empty() {
+                return Err("reveal_command must not be empty".to_string());
+            }
+            config.reveal_command = cmd;
+        }
+
+        Ok(config)
+    }
+}
@@ -0,0 +1,135 @@
+//! Running the external `convert` binary: its command line, spawning it
+//! with its input and output files, and the messages for its failures.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use crate::cmdlog;
+
+/// Whether `path` has an execute bit set. Always true off Unix, where
+/// there's no such bit to check.
+pub fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        true
+    }
+}
+
+/// `chmod +x`: adds the execute bits wherever the file is readable.
+pub fn make_executable(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = std::fs::metadata(path)?.permissions();
+        let mode = permissions.mode();
+        permissions.set_mode(mode | (mode & 0o444) >> 2);
+        std::fs::set_permissions(path, permissions)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
+/// The pot arguments for `convert`: clamped to 0.0-1.0 and given two
+/// decimals. NaN becomes 0.00, so a bad value can't reach `convert` as text
+/// it won't parse.
+pub fn format_pots(pots: &[f32; 4]) -> [String; 4] {
+    pots.map(|p| {
+        let p = if p.is_nan() { 0.0 } else { p.clamp(0.0, 1.0) };
+        // `+ 0.0` turns -0.0 into 0.0, which would otherwise print "-0.00".
+        format!("{:.2}", p + 0.0)
+    })
+}
+
+/// The `convert` invocation. Multichannel renders pass the channel count as
+/// a fifth argument; mono keeps the original four-pot argument list.
+pub fn convert_command(convert_path: &str, effect_name: &str, pots: &[f32; 4], channels: usize) -> Command {
+    let mut cmd = Command::new(convert_path);
+    cmd.arg(effect_name).args(format_pots(pots));
+    if channels > 1 {
+        cmd.arg(channels.to_string());
+    }
+    cmd
+}
+
+/// The status message for `convert` failing to start: a specific hint
+/// when it is there but can't be executed.
+pub fn convert_spawn_error(convert_path: &str, e: &io::Error) -> String {
+    if e.kind() == io::ErrorKind::PermissionDenied && !is_executable(Path::new(convert_path)) {
+        format!("'{}' is not executable - run chmod +x {}", convert_path, convert_path)
+    } else {
+        format!("Processing failed ({})", e)
+    }
+}
+
+/// The status message for a failed `convert` run: its exit code and the
+/// first line it wrote to stderr, if any.
+pub fn format_convert_error(code: Option<i32>, stderr: &str) -> String {
+    let how = match code {
+        Some(code) => format!("'convert' failed (exit code {})", code),
+        None => "'convert' was killed".to_string(),
+    };
+    match stderr.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(line) => format!("{}: {}", how, line),
+        None => how,
+    }
+}
+
+/// Starts `convert` reading `input_path` and writing `output_path`.
+/// `stderr` is piped by the render worker, which reads progress from it.
+pub fn spawn_convert(
+    convert_path: &str,
+    effect_name: &str,
+    pots: &[f32; 4],
+    channels: usize,
+    input_path: &str,
+    output_path: &str,
+    stderr: Stdio,
+) -> io::Result<Child> {
+    let input_file = File::open(input_path)?;
+    let output_file = File::create(output_path)?;
+    let mut cmd = convert_command(convert_path, effect_name, pots, channels);
+    cmdlog::record_redirected(&cmd, input_path, output_path);
+    cmd.stdin(Stdio::from(input_file))
+        .stdout(Stdio::from(output_file))
+        .stderr(stderr)
+        .spawn()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pots_are_clamped_and_formatted_for_convert() {
+        assert_eq!(format_pots(&[0.0, 1.0, 0.5, 0.123]), ["0.00", "1.00", "0.50", "0.12"]);
+        assert_eq!(format_pots(&[-0.3, 1.7, -0.0, 0.999]), ["0.00", "1.00", "0.00", "1.00"]);
+        assert_eq!(
+            format_pots(&[f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -f32::NAN]),
+            ["0.00", "1.00", "0.00", "0.00"]
+        );
+        let cmd = convert_command("./convert", "echo", &[f32::NAN, 2.0, 0.25, -1.0], 1);
+        let args: Vec<String> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(args, ["echo", "0.00", "1.00", "0.25", "0.00"]);
+    }
+
+    #[test]
+    fn convert_errors_quote_the_first_stderr_line() {
+        assert_eq!(format_convert_error(Some(1), ""), "'convert' failed (exit code 1)");
+        assert_eq!(format_convert_error(None, "   \n"), "'convert' was killed");
+        assert_eq!(
+            format_convert_error(Some(2), "\n  unknown effect 'foo'  \nusage: convert <effect> ...\n"),
+            "'convert' failed (exit code 2): unknown effect 'foo'"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Output devices for monitoring playback. ffplay has no device option of
+//! its own; it plays through SDL, which picks the sink from environment
+//! variables, so a chosen device is handed to it that way.
+
+use std::process::Command;
+
+/// Which sound server a device belongs to, and so how SDL is told to use it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Server {
+    /// PulseAudio or PipeWire's Pulse layer, from `pactl`.
+    Pulse,
+    /// A plain ALSA PCM, from `aplay -L`.
+    Alsa,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Device {
+    pub name: String,
+    pub description: String,
+    pub server: Server,
+}
+
+impl Device {
+    /// Points an ffplay command at this device.
+    pub fn apply(&self, cmd: &mut Command) {
+        match self.server {
+            Server::Pulse => {
+                cmd.env("SDL_AUDIODRIVER", "pulseaudio").env("PULSE_SINK", &self.name);
+            }
+            Server::Alsa => {
+                cmd.env("SDL_AUDIODRIVER", "alsa").env("AUDIODEV", &self.name);
+            }
+        }
+    }
+}
+
+/// The output devices the sound server knows about right now: Pulse sinks
+/// when `pactl` answers, otherwise ALSA PCMs. Empty when neither works.
+pub fn list() -> Vec<Device> {
+    if let Some(text) = run("pactl", &["list", "short", "sinks"]) {
+        let sinks = parse_pactl(&text);
+        if !sinks.is_empty() {
+            return sinks;
+        }
+    }
+    run("aplay", &["-L"]).map(|text| parse_aplay(&text)).unwrap_or_default()
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    out.status.success().then(|| String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// `pactl list short sinks` lines: index, name, driver, sample spec, state.
+fn parse_pactl(text: &str) -> Vec<Device> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.nth(1)?.trim();
+            let state = fields.nth(2).unwrap_or("").trim();
+            (!name.is_empty()).then(|| Device {
+                name: name.to_string(),
+                description: state.to_lowercase(),
+                server: Server::Pulse,
+            })
+        })
+        .collect()
+}
+
+/// `aplay -L`: a PCM name on its own line, then indented description lines.
+/// `null` is left out, as nothing could be heard through it.
+fn parse_aplay(text: &str) -> Vec<Device> {
+    let mut devices: Vec<Device> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(char::is_whitespace) {
+            if let Some(device) = devices.last_mut().filter(|d| d.description.is_empty()) {
+                device.description = line.trim().to_string();
+            }
+        } else if !line.trim().is_empty() && line.trim() != "null" {
+            devices.push(Device { name: line.trim().to_string(), description: String::new(), server: Server::Alsa });
+        }
+    }
+    devices
+}
@@ -0,0 +1,70 @@
+//! FFT and windowing helpers shared by the analysis views.
+
+use std::f32::consts::PI;
+
+/// In-place iterative radix-2 FFT. Both slices must share a power-of-two
+/// length.
+pub fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_im, w_re) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+pub fn hann(n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos())
+        .collect()
+}
+
+/// Hann-windowed magnitude spectrum of `frame` (whose length must be a power
+/// of two), normalized so a full-scale sine peaks near 1.0. Returns the
+/// first `frame.len() / 2` bins.
+pub fn magnitudes(frame: &[f32], window: &[f32]) -> Vec<f32> {
+    let n = frame.len();
+    let mut re: Vec<f32> = frame.iter().zip(window).map(|(s, w)| s * w).collect();
+    let mut im = vec![0.0; n];
+    fft(&mut re, &mut im);
+    let scale = 4.0 / n as f32;
+    re.iter()
+        .zip(&im)
+        .take(n / 2)
+        .map(|(r, i)| (r * r + i * i).sqrt() * scale)
+        .collect()
+}
+
+/// Converts i32 samples to f32 in -1.0..1.0.
+pub fn to_f32(samples: &[i32]) -> Vec<f32> {
+    samples.iter().map(|&s| s as f32 / i32::MAX as f32).collect()
+}
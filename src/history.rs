@@ -0,0 +1,88 @@
+//! Bounded undo/redo history of state snapshots.
+
+use std::collections::VecDeque;
+
+const DEFAULT_CAPACITY: usize = 100;
+
+/// Past states to undo back to and undone states to redo. Recording a new
+/// change clears the redo side, as in any editor.
+#[derive(Debug)]
+pub struct History<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    capacity: usize,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl<T> History<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { undo: VecDeque::new(), redo: Vec::new(), capacity: capacity.max(1) }
+    }
+
+    /// Records `before`, the state just prior to a change. The oldest entry
+    /// is dropped once the history is full.
+    pub fn push(&mut self, before: T) {
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(before);
+        self.redo.clear();
+    }
+
+    /// Steps back, returning the state to restore. `current` is kept so the
+    /// undo can itself be redone.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let prev = self.undo.pop_back()?;
+        self.redo.push(current);
+        Some(prev)
+    }
+
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push_back(current);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_walk_the_history() {
+        let mut history = History::default();
+        history.push([0.1; 4]);
+        history.push([0.2; 4]);
+        assert_eq!(history.undo([0.3; 4]), Some([0.2; 4]));
+        assert_eq!(history.undo([0.2; 4]), Some([0.1; 4]));
+        assert_eq!(history.undo([0.1; 4]), None);
+        assert_eq!(history.redo([0.1; 4]), Some([0.2; 4]));
+        assert_eq!(history.redo([0.2; 4]), Some([0.3; 4]));
+        assert_eq!(history.redo([0.3; 4]), None);
+    }
+
+    #[test]
+    fn push_clears_redo() {
+        let mut history = History::default();
+        history.push(1);
+        assert_eq!(history.undo(2), Some(1));
+        history.push(1);
+        assert_eq!(history.redo(3), None);
+    }
+
+    #[test]
+    fn oldest_entries_fall_off_at_capacity() {
+        let mut history = History::with_capacity(2);
+        for state in 1..=3 {
+            history.push(state);
+        }
+        assert_eq!(history.undo(4), Some(3));
+        assert_eq!(history.undo(3), Some(2));
+        assert_eq!(history.undo(2), None);
+    }
+}
@@ -0,0 +1,72 @@
+//! Size and length of the raw input, for the info line under the status.
+
+use std::time::Duration;
+
+use crate::audio::BYTES_PER_SAMPLE;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FileInfo {
+    /// False when the file is missing or unreadable; the counts are then 0.
+    pub exists: bool,
+    pub bytes: u64,
+    /// Samples per channel.
+    pub frames: u64,
+    pub duration: Duration,
+}
+
+/// Stats a headerless s32le file and works out its length from the sample
+/// rate and channel count. A trailing partial frame is ignored.
+pub fn input_info(path: &str, sample_rate: u32, channels: u16) -> FileInfo {
+    let Ok(meta) = std::fs::metadata(path) else {
+        return FileInfo::default();
+    };
+    let bytes = meta.len();
+    let frame_bytes = BYTES_PER_SAMPLE as u64 * channels.max(1) as u64;
+    let frames = bytes / frame_bytes;
+    let duration = if sample_rate == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64(frames as f64 / sample_rate as f64)
+    };
+    FileInfo { exists: true, bytes, frames, duration }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, bytes: usize) -> String {
+        let path = std::env::temp_dir()
+            .join(format!("audionoise-info-{}-{}.raw", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned();
+        std::fs::write(&path, vec![0u8; bytes]).unwrap();
+        path
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        assert_eq!(input_info("/nonexistent/input.raw", 48000, 1), FileInfo::default());
+    }
+
+    #[test]
+    fn mono_second_of_audio() {
+        let path = write_temp("mono", 48000 * 4);
+        let info = input_info(&path, 48000, 1);
+        assert!(info.exists);
+        assert_eq!(info.bytes, 192_000);
+        assert_eq!(info.frames, 48000);
+        assert_eq!(info.duration, Duration::from_secs(1));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn stereo_counts_frames_not_samples() {
+        // Half a second of stereo plus a stray partial frame.
+        let path = write_temp("stereo", 24000 * 8 + 4);
+        let info = input_info(&path, 48000, 2);
+        assert_eq!(info.frames, 24000);
+        assert_eq!(info.duration, Duration::from_millis(500));
+        let _ = std::fs::remove_file(path);
+    }
+}
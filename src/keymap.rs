@@ -0,0 +1,347 @@
+//! Remappable keys for the core actions, from the config's `[keys]` table.
+//! Every other key keeps its fixed binding in `run_app`.
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::toml_lite::Table;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyAction {
+    NextEffect,
+    PrevEffect,
+    IncPot,
+    DecPot,
+    NextPot,
+    Play,
+    Stop,
+    Reset,
+    Quit,
+}
+
+impl KeyAction {
+    pub const ALL: [Self; 9] = [
+        Self::NextEffect,
+        Self::PrevEffect,
+        Self::IncPot,
+        Self::DecPot,
+        Self::NextPot,
+        Self::Play,
+        Self::Stop,
+        Self::Reset,
+        Self::Quit,
+    ];
+
+    /// The action's key in the `[keys]` table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NextEffect => "next_effect",
+            Self::PrevEffect => "prev_effect",
+            Self::IncPot => "inc_pot",
+            Self::DecPot => "dec_pot",
+            Self::NextPot => "next_pot",
+            Self::Play => "play",
+            Self::Stop => "stop",
+            Self::Reset => "reset",
+            Self::Quit => "quit",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    fn defaults(self) -> &'static [KeyCode] {
+        match self {
+            Self::NextEffect => &[KeyCode::Down, KeyCode::Char('j')],
+            Self::PrevEffect => &[KeyCode::Up, KeyCode::Char('k')],
+            Self::IncPot => &[KeyCode::Right, KeyCode::Char('l')],
+            Self::DecPot => &[KeyCode::Left, KeyCode::Char('h')],
+            Self::NextPot => &[KeyCode::Tab],
+            Self::Play => &[KeyCode::Char('p'), KeyCode::Char('P')],
+            Self::Stop => &[KeyCode::Char('s'), KeyCode::Char('S')],
+            Self::Reset => &[KeyCode::Char('r')],
+            Self::Quit => &[KeyCode::Char('q'), KeyCode::Char('Q')],
+        }
+    }
+}
+
+/// Keys `run_app` binds itself without Ctrl. They're matched after the
+/// keymap, so `[keys]` can't take them without hiding their action.
+const FIXED_KEYS: &[KeyCode] = &[
+    KeyCode::Char(' '),
+    KeyCode::Char('('),
+    KeyCode::Char(')'),
+    KeyCode::Char('H'),
+    KeyCode::Char('L'),
+    KeyCode::Char('?'),
+    KeyCode::Char('+'),
+    KeyCode::Char('='),
+    KeyCode::Char('-'),
+    KeyCode::Char('{'),
+    KeyCode::Char('}'),
+    KeyCode::Char('x'),
+    KeyCode::Char('X'),
+    KeyCode::Char('>'),
+    KeyCode::Char('<'),
+    KeyCode::Char('1'),
+    KeyCode::Char('2'),
+    KeyCode::Char('3'),
+    KeyCode::Char('4'),
+    KeyCode::Char('5'),
+    KeyCode::Char('6'),
+    KeyCode::Char('7'),
+    KeyCode::Char('8'),
+    KeyCode::Char('9'),
+    KeyCode::Char('J'),
+    KeyCode::Char('K'),
+    KeyCode::Char('f'),
+    KeyCode::Char('u'),
+    KeyCode::Char('U'),
+    KeyCode::Char('t'),
+    KeyCode::Char('T'),
+    KeyCode::Char('@'),
+    KeyCode::Char(':'),
+    KeyCode::Char('R'),
+    KeyCode::Char('v'),
+    KeyCode::Char('V'),
+    KeyCode::Char('d'),
+    KeyCode::Char('D'),
+    KeyCode::Char('a'),
+    KeyCode::Char('A'),
+    KeyCode::Char('b'),
+    KeyCode::Char('B'),
+    KeyCode::Char('/'),
+    KeyCode::Char('o'),
+    KeyCode::Char('O'),
+    KeyCode::Char('e'),
+    KeyCode::Char('E'),
+    KeyCode::Char('y'),
+    KeyCode::Char('Y'),
+    KeyCode::Char('z'),
+    KeyCode::Char('Z'),
+    KeyCode::Char('i'),
+    KeyCode::Char('I'),
+    KeyCode::Char('n'),
+    KeyCode::Char('N'),
+    KeyCode::Char('w'),
+    KeyCode::Char('W'),
+    KeyCode::Char('g'),
+    KeyCode::Char('G'),
+    KeyCode::Char('!'),
+    KeyCode::Char('.'),
+    KeyCode::Char('m'),
+    KeyCode::Char('M'),
+    KeyCode::Char('['),
+    KeyCode::Char(']'),
+    KeyCode::Char('c'),
+    KeyCode::Char('C'),
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Enter,
+    KeyCode::Esc,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::F(1),
+    KeyCode::F(2),
+    KeyCode::F(3),
+    KeyCode::F(4),
+    KeyCode::F(5),
+    KeyCode::F(9),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(KeyCode, KeyAction)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = KeyAction::ALL
+            .into_iter()
+            .flat_map(|action| action.defaults().iter().map(move |&key| (key, action)))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap from a `[keys]` table of `action = ["key", ...]`.
+    /// Listed actions get exactly those keys, which also leave any action
+    /// that had them by default; two listed actions can't share a key, and
+    /// none can take one of the fixed keys in `FIXED_KEYS`.
+    pub fn from_table(table: &Table) -> Result<Self, String> {
+        let mut configured: Vec<(KeyAction, Vec<KeyCode>)> = Vec::new();
+        for (name, value) in table {
+            let action = KeyAction::parse(name).ok_or_else(|| {
+                let names: Vec<_> = KeyAction::ALL.iter().map(|a| a.name()).collect();
+                format!("keys.{}: unknown action ({})", name, names.join(", "))
+            })?;
+            let keys = value
+                .as_array()
+                .ok_or_else(|| format!("keys.{} must be an array of key names", name))?
+                .iter()
+                .map(|item| {
+                    let key = item.as_str().ok_or_else(|| format!("keys.{} must be an array of key names", name))?;
+                    parse_key(key).ok_or_else(|| format!("keys.{}: unknown key '{}'", name, key))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for key in &keys {
+                if FIXED_KEYS.contains(key) {
+                    return Err(format!("keys.{}: '{}' has a fixed binding (see '?')", name, key_name(*key)));
+                }
+                if let Some((other, _)) = configured.iter().find(|(_, k)| k.contains(key)) {
+                    return Err(format!("keys.{}: '{}' is already bound to {}", name, key_name(*key), other.name()));
+                }
+            }
+            configured.push((action, keys));
+        }
+
+        let mut keymap = Self::default();
+        let taken: Vec<KeyCode> = configured.iter().flat_map(|(_, keys)| keys.iter().copied()).collect();
+        keymap.bindings.retain(|(key, action)| {
+            !taken.contains(key) && !configured.iter().any(|(a, _)| a == action)
+        });
+        for (action, keys) in configured {
+            keymap.bindings.extend(keys.into_iter().map(|key| (key, action)));
+        }
+        Ok(keymap)
+    }
+
+    /// The action bound to `code`. Ctrl and Alt combinations never match,
+    /// and Shift only as part of a character, so Ctrl+P or Shift+Left keep
+    /// their own meaning.
+    pub fn resolve(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<KeyAction> {
+        if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return None;
+        }
+        if modifiers.contains(KeyModifiers::SHIFT) && !matches!(code, KeyCode::Char(_)) {
+            return None;
+        }
+        self.bindings.iter().find(|(key, _)| *key == code).map(|&(_, action)| action)
+    }
+
+    /// Actions whose keys differ from the defaults, with their keys, for
+    /// the help screen.
+    pub fn overrides(&self) -> Vec<(String, &'static str)> {
+        KeyAction::ALL
+            .into_iter()
+            .filter_map(|action| {
+                let keys: Vec<KeyCode> =
+                    self.bindings.iter().filter(|(_, a)| *a == action).map(|&(key, _)| key).collect();
+                (keys != action.defaults()).then(|| {
+                    let names: Vec<String> = keys.into_iter().map(key_name).collect();
+                    let names = if names.is_empty() { "(none)".to_string() } else { names.join(", ") };
+                    (names, action.name())
+                })
+            })
+            .collect()
+    }
+}
+
+/// A key by name: a single character, or one of Up, Down, Left, Right,
+/// Tab, Enter, Esc, Space, Backspace, Home, End, PageUp, PageDown, F1-F12.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let key = match name.to_ascii_lowercase().as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "tab" => KeyCode::Tab,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        "backspace" => KeyCode::Backspace,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        other => {
+            let n: u8 = other.strip_prefix('f')?.parse().ok()?;
+            return (1..=12).contains(&n).then_some(KeyCode::F(n));
+        }
+    };
+    Some(key)
+}
+
+/// The name `parse_key` reads back.
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toml_lite;
+
+    fn keymap(text: &str) -> Result<Keymap, String> {
+        let doc = toml_lite::parse(text).unwrap();
+        Keymap::from_table(doc.tables.get("keys").unwrap_or(&Table::new()))
+    }
+
+    #[test]
+    fn defaults_resolve_arrows_and_vim_keys() {
+        let keys = Keymap::default();
+        let none = KeyModifiers::NONE;
+        assert_eq!(keys.resolve(KeyCode::Down, none), Some(KeyAction::NextEffect));
+        assert_eq!(keys.resolve(KeyCode::Char('k'), none), Some(KeyAction::PrevEffect));
+        assert_eq!(keys.resolve(KeyCode::Char('P'), KeyModifiers::SHIFT), Some(KeyAction::Play));
+        assert_eq!(keys.resolve(KeyCode::Char('x'), none), None);
+        assert!(keys.overrides().is_empty());
+    }
+
+    #[test]
+    fn modifier_combinations_are_left_alone() {
+        let keys = Keymap::default();
+        assert_eq!(keys.resolve(KeyCode::Char('p'), KeyModifiers::CONTROL), None);
+        assert_eq!(keys.resolve(KeyCode::Left, KeyModifiers::SHIFT), None);
+    }
+
+    #[test]
+    fn configured_keys_replace_the_defaults() {
+        let keys = keymap("[keys]\nnext_effect = [\"s\"]\nprev_effect = [\";\", \"Up\"]\n").unwrap();
+        let none = KeyModifiers::NONE;
+        assert_eq!(keys.resolve(KeyCode::Char('s'), none), Some(KeyAction::NextEffect));
+        assert_eq!(keys.resolve(KeyCode::Char('j'), none), None);
+        assert_eq!(keys.resolve(KeyCode::Char(';'), none), Some(KeyAction::PrevEffect));
+        // Stop keeps the default key 'S' that wasn't taken.
+        assert_eq!(keys.resolve(KeyCode::Char('S'), KeyModifiers::SHIFT), Some(KeyAction::Stop));
+        assert_eq!(keys.overrides().len(), 3);
+    }
+
+    #[test]
+    fn bad_tables_are_rejected() {
+        assert!(keymap("[keys]\njump = [\"x\"]\n").unwrap_err().contains("unknown action"));
+        assert!(keymap("[keys]\nplay = [\"Hyper\"]\n").unwrap_err().contains("unknown key"));
+        assert!(keymap("[keys]\nplay = \"p\"\n").unwrap_err().contains("array"));
+        assert!(keymap("[keys]\nplay = [\"F\"]\nstop = [\"F\"]\n").unwrap_err().contains("already bound"));
+    }
+
+    #[test]
+    fn fixed_keys_cannot_be_taken() {
+        for key in ["x", "R", "d", "v", "(", ")", "Space", "F5"] {
+            let err = keymap(&format!("[keys]\nplay = [\"{}\"]\n", key)).unwrap_err();
+            assert!(err.contains("fixed binding"), "{}: {}", key, err);
+        }
+        // Keys the keymap owns by default stay free to move around.
+        assert!(keymap("[keys]\nplay = [\"q\"]\nquit = [\"Esc\"]\n").is_err());
+        assert!(keymap("[keys]\nplay = [\"q\"]\nquit = [\"F10\"]\n").is_ok());
+    }
+
+    #[test]
+    fn key_names_round_trip() {
+        for name in ["Up", "PageDown", "Space", "F5", "Tab", "q"] {
+            assert_eq!(key_name(parse_key(name).unwrap()), name);
+        }
+    }
+}
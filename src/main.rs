@@ -1,379 +1,5324 @@
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode,
+        KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Constraint, Direction, Layout, Margin, Position, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
-    Frame, Terminal,
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Wrap},
+    Frame, Terminal, TerminalOptions, Viewport,
 };
 use std::fs::File;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+mod audio;
+mod cli;
+mod clipboard;
+mod cmdlog;
+mod config;
+mod convert;
+mod devices;
+mod dsp;
+mod history;
+mod info;
+mod keymap;
+mod meter;
+mod player;
+mod playlist;
+mod png;
+mod presets;
+mod render;
+mod rng;
+mod session;
+mod spectrogram;
+mod spectrum;
+mod toml_lite;
+mod theme;
+mod tutorial;
+mod wav;
+mod waveform;
+
+use cli::Args;
+use config::Config;
+use convert::{convert_command, is_executable, make_executable};
+use history::History;
+use audio::{AudioFormat, ChannelLayout, MonoSource};
+use meter::{MeterScale, PeakMeter};
+use player::{Player, RawFormat};
+use playlist::{Cached, Playlist};
+use presets::{Banks, NamedPreset};
+use info::FileInfo;
+use keymap::KeyAction;
+use session::Session;
+use render::{output_sibling, Outcome, PendingRender, PostProcess, RackSlot, RenderJob, RenderRequest};
+use rng::Rng;
+use theme::Theme;
+use tutorial::Region;
+
 
-const SAMPLE_RATE: &str = "48000";
-const SAMPLE_FORMAT: &str = "s32le";
-const CHANNELS: &str = "mono";
+/// Terminal size assumed when it can't be queried outside the alternate
+/// screen.
+const FALLBACK_SIZE: (u16, u16) = (80, 24);
+
+const NO_EFFECTS: &str = "No effects configured - check the 'effects' list in config.toml";
 
 #[derive(Clone)]
 struct Effect {
     name: &'static str,
     defaults: [f32; 4],
     pots: [&'static str; 4],
+    /// Musically useful (min, max) range per pot, used by smart randomize.
+    ranges: [(f32, f32); 4],
+    steps: [PotStep; 4],
+    /// Optional display units per pot, e.g. `linear("ms", 0.0, 1250.0)`.
+    units: [Option<Units>; 4],
     desc: &'static str,
 }
 
+/// Display units for a pot: the 0..1 value maps onto `min..=max`,
+/// linearly or, for frequencies, logarithmically.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Units {
+    label: &'static str,
+    min: f32,
+    max: f32,
+    log: bool,
+}
+
+const fn linear(label: &'static str, min: f32, max: f32) -> Option<Units> {
+    Some(Units { label, min, max, log: false })
+}
+
+/// Equal pot travel per octave; `min` must be above zero.
+const fn log(label: &'static str, min: f32, max: f32) -> Option<Units> {
+    Some(Units { label, min, max, log: true })
+}
+
+impl Units {
+    /// The pot value `v` (0..1) in these units.
+    fn map(self, v: f32) -> f32 {
+        let v = v.clamp(0.0, 1.0);
+        if self.log {
+            self.min * (self.max / self.min).powf(v)
+        } else {
+            self.min + (self.max - self.min) * v
+        }
+    }
+}
+
+/// Audible range for filter cutoffs.
+const CUTOFF_HZ: Option<Units> = log("Hz", 20.0, 20_000.0);
+
+/// How a pot moves with Left/Right and how its value is shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PotStep {
+    /// Free 0..1 value moved by a fixed increment.
+    Continuous(f32),
+    /// Whole numbers `lo..=hi` spread evenly over 0..1.
+    Integer(u32, u32),
+}
+
+const COARSE: PotStep = PotStep::Continuous(0.05);
+const FINE: PotStep = PotStep::Continuous(0.01);
+
+impl PotStep {
+    /// The value one step up (`dir` = 1) or down (`dir` = -1), snapping
+    /// integer pots onto their grid.
+    fn apply(self, value: f32, dir: f32) -> f32 {
+        let next = match self {
+            Self::Continuous(step) => value + step * dir,
+            Self::Integer(lo, hi) => {
+                let n = hi.saturating_sub(lo).max(1) as f32;
+                ((value * n).round() + dir) / n
+            }
+        };
+        next.clamp(0.0, 1.0)
+    }
+
+    /// Rounds integer pots onto their grid; continuous values pass through.
+    fn snap(self, value: f32) -> f32 {
+        self.apply(value, 0.0)
+    }
+
+    fn label(self, value: f32) -> String {
+        match self {
+            Self::Continuous(_) => format!("{:.2}", value),
+            Self::Integer(lo, hi) => {
+                let n = hi.saturating_sub(lo) as f32;
+                format!("{}", lo + (value * n).round() as u32)
+            }
+        }
+    }
+}
+
 const EFFECTS: &[Effect] = &[
     Effect {
         name: "flanger",
         defaults: [0.6, 0.6, 0.6, 0.6],
         pots: ["Depth", "Rate", "Feedback", "Mix"],
+        ranges: [(0.2, 0.9), (0.05, 0.5), (0.0, 0.7), (0.3, 0.8)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [None, linear("Hz", 0.1, 10.0), None, linear("%", 0.0, 100.0)],
         desc: "Modulated delay - jet-plane swoosh",
     },
     Effect {
         name: "echo",
         defaults: [0.3, 0.3, 0.3, 0.3],
         pots: ["Delay", "Feedback", "Mix", "Tone"],
+        ranges: [(0.1, 0.8), (0.0, 0.65), (0.2, 0.6), (0.2, 0.9)],
+        steps: [COARSE, COARSE, FINE, COARSE],
+        units: [linear("ms", 0.0, ECHO_MAX_DELAY_MS), None, linear("%", 0.0, 100.0), None],
         desc: "Delay loop up to 1.25 seconds",
     },
     Effect {
         name: "fm",
         defaults: [0.25, 0.25, 0.5, 0.5],
         pots: ["Mod Depth", "Mod Rate", "Carrier", "Mix"],
+        ranges: [(0.05, 0.6), (0.05, 0.5), (0.2, 0.8), (0.3, 0.8)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [None, None, None, linear("%", 0.0, 100.0)],
         desc: "Frequency modulation synthesis",
     },
     Effect {
         name: "am",
         defaults: [0.5, 0.5, 0.5, 0.5],
         pots: ["Depth", "Rate", "Shape", "Mix"],
+        ranges: [(0.2, 0.9), (0.05, 0.6), (0.0, 1.0), (0.3, 0.8)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [None, None, None, linear("%", 0.0, 100.0)],
         desc: "Amplitude modulation",
     },
     Effect {
         name: "phaser",
         defaults: [0.3, 0.3, 0.5, 0.5],
         pots: ["Depth", "Rate", "Stages", "Feedback"],
+        ranges: [(0.2, 0.9), (0.05, 0.5), (0.0, 1.0), (0.0, 0.7)],
+        steps: [COARSE, COARSE, PotStep::Integer(2, 8), COARSE],
+        units: [None; 4],
         desc: "All-pass filter sweep",
     },
+    Effect {
+        name: "tremolo",
+        defaults: [0.25, 0.5, 0.0, 1.0],
+        pots: ["Rate", "Depth", "Shape", "Mix"],
+        ranges: [(0.1, 0.5), (0.3, 0.9), (0.0, 1.0), (0.6, 1.0)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [linear("Hz", 0.1, 20.0), linear("%", 0.0, 100.0), None, linear("%", 0.0, 100.0)],
+        desc: "Volume wobble - sine to square",
+    },
+    Effect {
+        name: "chorus",
+        defaults: [0.15, 0.4, 0.25, 0.5],
+        pots: ["Rate", "Depth", "Voices", "Mix"],
+        ranges: [(0.05, 0.4), (0.2, 0.7), (0.0, 1.0), (0.3, 0.7)],
+        steps: [COARSE, COARSE, PotStep::Integer(1, 5), FINE],
+        units: [linear("Hz", 0.1, 5.0), None, None, linear("%", 0.0, 100.0)],
+        desc: "Detuned copies - thickens and widens",
+    },
     Effect {
         name: "discont",
         defaults: [0.8, 0.1, 0.2, 0.2],
         pots: ["Pitch", "Rate", "Blend", "Mix"],
+        ranges: [(0.3, 1.0), (0.05, 0.4), (0.1, 0.6), (0.2, 0.7)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [None, None, None, linear("%", 0.0, 100.0)],
         desc: "Pitch shift via crossfade",
     },
+    Effect {
+        name: "reverb",
+        defaults: [0.5, 0.4, 0.5, 0.3],
+        pots: ["Room", "Damp", "Decay", "Mix"],
+        ranges: [(0.2, 0.9), (0.1, 0.7), (0.2, 0.8), (0.15, 0.5)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [None, None, None, linear("%", 0.0, 100.0)],
+        desc: "Room reverberation",
+    },
+    Effect {
+        name: "bitcrush",
+        defaults: [0.5, 0.3, 0.4, 0.7],
+        pots: ["Bits", "Downsample", "Drive", "Mix"],
+        ranges: [(0.2, 0.8), (0.0, 0.6), (0.1, 0.7), (0.4, 1.0)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [None, None, None, linear("%", 0.0, 100.0)],
+        desc: "Bit-depth and sample-rate reduction",
+    },
+    Effect {
+        name: "lowpass",
+        defaults: [0.7, 0.2, 0.1, 1.0],
+        pots: ["Cutoff", "Resonance", "Drive", "Mix"],
+        ranges: [(0.4, 0.9), (0.0, 0.6), (0.0, 0.4), (0.6, 1.0)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [CUTOFF_HZ, None, None, linear("%", 0.0, 100.0)],
+        desc: "Resonant low-pass filter - tames highs",
+    },
+    Effect {
+        name: "highpass",
+        defaults: [0.2, 0.2, 0.1, 1.0],
+        pots: ["Cutoff", "Resonance", "Drive", "Mix"],
+        ranges: [(0.05, 0.5), (0.0, 0.6), (0.0, 0.4), (0.6, 1.0)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [CUTOFF_HZ, None, None, linear("%", 0.0, 100.0)],
+        desc: "Resonant high-pass filter - removes rumble",
+    },
+    Effect {
+        name: "compress",
+        defaults: [0.7, 0.16, 0.5, 0.5],
+        pots: ["Threshold", "Ratio", "Attack", "Release"],
+        ranges: [(0.5, 0.9), (0.05, 0.4), (0.3, 0.7), (0.3, 0.7)],
+        steps: [COARSE, COARSE, COARSE, COARSE],
+        units: [linear("dB", -60.0, 0.0), linear(":1", 1.0, 20.0), log("ms", 0.1, 100.0), log("ms", 10.0, 1000.0)],
+        desc: "Compressor/limiter - evens out levels, stops clipping",
+    },
+    Effect {
+        name: "gate",
+        defaults: [0.5, 0.3, 0.4, 0.75],
+        pots: ["Threshold", "Attack", "Release", "Range"],
+        ranges: [(0.3, 0.7), (0.1, 0.5), (0.2, 0.6), (0.4, 1.0)],
+        steps: [COARSE, COARSE, COARSE, COARSE],
+        units: [linear("dB", -80.0, 0.0), log("ms", 0.1, 50.0), log("ms", 10.0, 2000.0), linear("dB", 0.0, -80.0)],
+        desc: "Noise gate/expander - mutes hiss between notes",
+    },
+    Effect {
+        name: "distort",
+        defaults: [0.4, 0.5, 0.3, 1.0],
+        pots: ["Drive", "Tone", "Level", "Mix"],
+        ranges: [(0.1, 0.8), (0.3, 0.8), (0.2, 0.4), (0.5, 1.0)],
+        steps: [COARSE, COARSE, COARSE, FINE],
+        units: [linear("dB", 0.0, 40.0), log("Hz", 500.0, 10_000.0), linear("dB", -40.0, 0.0), linear("%", 0.0, 100.0)],
+        desc: "Overdrive/distortion - always followed by the limiter",
+    },
 ];
 
-struct App {
-    effect_idx: usize,
-    pot_idx: usize,
-    pot_values: Vec<[f32; 4]>,
-    status: String,
-    status_ok: bool,
-    list_state: ListState,
-    player: Option<Child>,
-}
+/// Shown under the gate's description: its threshold is on the same dB
+/// scale as the level meter.
+const GATE_HINT: &str = "Tip: set Threshold just above the RMS meter's reading (B: dB) in the quiet gaps";
 
-impl App {
-    fn new() -> Self {
-        let pot_values = EFFECTS.iter().map(|e| e.defaults).collect();
-        let mut list_state = ListState::default();
-        list_state.select(Some(0));
-        
-        let mut app = Self {
-            effect_idx: 0,
-            pot_idx: 0,
-            pot_values,
-            status: String::new(),
-            status_ok: true,
-            list_state,
-            player: None,
-        };
-        app.check_environment();
-        app
-    }
+/// `compress` pots for the automatic limiter stage: -1.2 dB threshold,
+/// 20:1, 0.1 ms attack and a 63 ms release.
+const LIMITER_POTS: [f32; 4] = [0.98, 1.0, 0.0, 0.4];
 
-    fn check_environment(&mut self) {
-        if !std::path::Path::new("../convert").exists() 
-            && !std::path::Path::new("./convert").exists() {
-            self.status = "Warning: 'convert' not found. Run 'make convert' first.".to_string();
-            self.status_ok = false;
-        } else if !std::path::Path::new("../input.raw").exists() 
-            && !std::path::Path::new("./input.raw").exists() {
-            self.status = "No input.raw found - will try to convert MP3".to_string();
-            self.status_ok = true;
-        } else {
-            self.status = "Ready - press 'p' to process, 'q' to quit".to_string();
-            self.status_ok = true;
-        }
+/// The stages a render runs: `stages` with a limiter appended when
+/// `limiter` is on, unless the user already has a compressor in there.
+/// Distortion can add up to 40 dB of gain, so a `distort` stage with no
+/// compressor after it gets the limiter even when it is off.
+fn with_limiter(stages: &[(usize, [f32; 4])], limiter: bool) -> Vec<(usize, [f32; 4])> {
+    let mut stages = stages.to_vec();
+    let compress = effect_index("compress").expect("compress is in EFFECTS");
+    let distort = effect_index("distort").expect("distort is in EFFECTS");
+    let last_compress = stages.iter().rposition(|&(effect, _)| effect == compress);
+    let unguarded_drive = stages
+        .iter()
+        .rposition(|&(effect, _)| effect == distort)
+        .is_some_and(|d| last_compress.is_none_or(|c| c < d));
+    if (limiter && last_compress.is_none()) || unguarded_drive {
+        stages.push((compress, LIMITER_POTS));
     }
+    stages
+}
 
-    fn next_effect(&mut self) {
-        self.effect_idx = (self.effect_idx + 1) % EFFECTS.len();
-        self.list_state.select(Some(self.effect_idx));
-        self.pot_idx = 0;
-    }
+/// A pot's value in its display units, e.g. "375 ms"; pots without units
+/// show their usual readout. Precision follows the size of the range, or
+/// of the value itself on a log scale, which spans several decades.
+fn display_pot(effect: &Effect, i: usize, v: f32) -> String {
+    let Some(units) = effect.units[i] else {
+        return effect.steps[i].label(v);
+    };
+    let value = units.map(v);
+    let magnitude = if units.log { value.abs() } else { (units.max - units.min).abs() };
+    let precision = if magnitude >= 100.0 { 0 } else if magnitude >= 10.0 { 1 } else { 2 };
+    let separator = if matches!(units.label, "%" | ":1") { "" } else { " " };
+    format!("{:.*}{}{}", precision, value, separator, units.label)
+}
 
-    fn prev_effect(&mut self) {
-        self.effect_idx = if self.effect_idx == 0 {
-            EFFECTS.len() - 1
-        } else {
-            self.effect_idx - 1
-        };
-        self.list_state.select(Some(self.effect_idx));
-        self.pot_idx = 0;
-    }
+fn effect_index(name: &str) -> Option<usize> {
+    EFFECTS.iter().position(|e| e.name.eq_ignore_ascii_case(name))
+}
 
-    fn next_pot(&mut self) {
-        self.pot_idx = (self.pot_idx + 1) % 4;
-    }
+/// Resolves an `(effect, pot)` name pair from the config `pot_map`; pots
+/// match by name (case-insensitive) or 1-based number.
+fn pot_target(effect: &str, pot: &str) -> Result<(usize, usize), String> {
+    let eff = effect_index(effect).ok_or_else(|| format!("unknown effect '{}'", effect))?;
+    let pots = &EFFECTS[eff].pots;
+    pots.iter()
+        .position(|p| p.eq_ignore_ascii_case(pot))
+        .or_else(|| pot.parse::<usize>().ok().filter(|n| (1..=4).contains(n)).map(|n| n - 1))
+        .map(|pot_idx| (eff, pot_idx))
+        .ok_or_else(|| format!("{} has no pot '{}' ({})", EFFECTS[eff].name, pot, pots.join(", ")))
+}
 
-    fn increase_pot(&mut self) {
-        let idx = self.pot_idx;
-        let eff_idx = self.effect_idx;
-        self.pot_values[eff_idx][idx] = (self.pot_values[eff_idx][idx] + 0.05).min(1.0);
-    }
+fn effect_names() -> String {
+    EFFECTS.iter().map(|e| e.name).collect::<Vec<_>>().join(", ")
+}
 
-    fn decrease_pot(&mut self) {
-        let idx = self.pot_idx;
-        let eff_idx = self.effect_idx;
-        self.pot_values[eff_idx][idx] = (self.pot_values[eff_idx][idx] - 0.05).max(0.0);
+/// The directory holding `convert`, `input.raw` and `output.raw`: the one
+/// given with `--project`, else `.` or `..`, whichever has `convert`.
+fn project_dir(flag: Option<&Path>) -> PathBuf {
+    if let Some(dir) = flag {
+        return dir.to_path_buf();
     }
+    [".", ".."]
+        .into_iter()
+        .map(PathBuf::from)
+        .find(|dir| dir.join("convert").exists())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
 
-    fn reset_pots(&mut self) {
-        let defaults = EFFECTS[self.effect_idx].defaults;
-        self.pot_values[self.effect_idx] = defaults;
-        self.status = format!("Reset {} to defaults", EFFECTS[self.effect_idx].name);
-        self.status_ok = true;
-    }
+/// `(input.raw, output.raw)` inside `dir`.
+fn project_paths(dir: &Path) -> (String, String) {
+    let path = |name| dir.join(name).to_string_lossy().into_owned();
+    (path("input.raw"), path("output.raw"))
+}
 
-    fn stop_audio(&mut self) {
-        if let Some(ref mut child) = self.player {
-            let _ = child.kill();
+/// The convert binary to run: `flag` (`--convert`, else
+/// `AUDIONOISE_CONVERT`) when set, else `convert` in the project directory.
+/// A bare name is looked up on PATH unless there's such a file here.
+fn convert_path(flag: Option<&Path>, project_dir: &Path) -> PathBuf {
+    match flag {
+        None => project_dir.join("convert"),
+        Some(path) if path.components().count() == 1 => {
+            // Command::new would search PATH for a bare name; a local file
+            // needs the "./" to be run as it is.
+            if path.exists() {
+                Path::new(".").join(path)
+            } else {
+                find_on_path(path).unwrap_or_else(|| path.to_path_buf())
+            }
         }
-        self.player = None;
+        Some(path) => path.to_path_buf(),
     }
+}
 
-    fn process_and_play(&mut self) {
-        let effect_name = EFFECTS[self.effect_idx].name.to_string();
-        let effect_pots = self.pot_values[self.effect_idx];
-        
-        self.status = format!("Processing {}...", effect_name);
-        self.status_ok = true;
+fn find_on_path(name: &Path) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).map(|dir| dir.join(name)).find(|path| path.is_file())
+}
 
-        let (convert_path, input_path, output_path) = 
-            if std::path::Path::new("../convert").exists() {
-                ("../convert", "../input.raw", "../output.raw")
-            } else {
-                ("./convert", "./input.raw", "./output.raw")
-            };
+/// A fresh path in the system temp directory for scratch audio, unique to
+/// this process and call, so nothing in the project is written over.
+fn scratch_path(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("audionoise-{}-{}-{}.raw", std::process::id(), n, name))
+}
 
-        if !std::path::Path::new(input_path).exists() {
-            let mp3_path = if std::path::Path::new("../BassForLinus.mp3").exists() {
-                "../BassForLinus.mp3"
-            } else if std::path::Path::new("./BassForLinus.mp3").exists() {
-                "./BassForLinus.mp3"
-            } else {
-                self.status = "Error: No input.raw or .mp3 file found".to_string();
-                self.status_ok = false;
-                return;
-            };
+/// Where the next render gets its input from.
+#[derive(Clone, Debug, PartialEq)]
+enum InputSource {
+    /// `input.raw` or the current `input_file`, ready to use as it is.
+    Raw(String),
+    /// A file to import into a scratch file first: a dropped or given
+    /// file, else one found in the working directory.
+    Import(String),
+    None,
+}
+
+/// Whether `pots` are a tweak that neither reset nor recalling the last
+/// played values would bring back: they differ from the defaults and from
+/// the last played snapshot, if there is one.
+fn unsaved_tweak(pots: &[f32; 4], defaults: &[f32; 4], last_played: Option<[f32; 4]>) -> bool {
+    pots != defaults && last_played.is_none_or(|played| played != *pots)
+}
 
-            let result = Command::new("ffmpeg")
-                .args(["-y", "-v", "fatal", "-i", mp3_path,
-                       "-f", SAMPLE_FORMAT, "-ar", SAMPLE_RATE, "-ac", "1", input_path])
-                .status();
+/// How many effects' pots differ between `current` and `saved`.
+fn modified_effects(current: &[[f32; 4]], saved: &[[f32; 4]]) -> usize {
+    current.iter().zip(saved).filter(|(a, b)| a != b).count()
+}
 
-            if result.is_err() || !result.unwrap().success() {
-                self.status = "Error: Failed to convert MP3".to_string();
-                self.status_ok = false;
-                return;
-            }
-        }
+/// Whether rendering should stop and ask first: an `output.raw` exists that
+/// nothing this session has exported.
+fn needs_overwrite_confirm(exists: bool, exported: bool) -> bool {
+    exists && !exported
+}
 
-        if !std::path::Path::new(convert_path).exists() {
-            self.status = "Error: 'convert' not found - run 'make convert'".to_string();
-            self.status_ok = false;
-            return;
-        }
+/// Renders `input` through one effect into `output` without the TUI,
+/// using the same render request as 'p' so the config's layout, limiter
+/// and fades apply. Anything but a raw file is imported first.
+fn run_batch(args: &Args, config: Config, effect_idx: usize, pots: [f32; 4], input: &Path, output: &Path) -> Result<(), String> {
+    let mut app = App::new(
+        config,
+        args.project.as_deref(),
+        args.convert.as_deref(),
+        vec![effect_idx],
+        effect_idx,
+        Banks::new(),
+        None,
+    );
+    app.pot_values[effect_idx] = pots;
+    let convert_path = app.convert_path.clone();
+    if !convert_path.exists() {
+        return Err("'convert' not found - run 'make convert'".to_string());
+    }
+    if !input.is_file() {
+        return Err(format!("input file not found: {}", input.display()));
+    }
+    let input_path = input.to_string_lossy().into_owned();
+    let is_raw = input.extension().is_some_and(|e| e.eq_ignore_ascii_case("raw"));
+    let import_from = (!is_raw).then(|| input_path.clone());
+    let request = app.render_request(import_from, input_path, output.to_string_lossy().into_owned());
+    match RenderJob::run_blocking(request).outcome {
+        Outcome::Done { .. } | Outcome::Imported => Ok(()),
+        Outcome::Failed(e) => Err(e),
+        Outcome::TimedOut => Err("processing timed out".to_string()),
+        Outcome::Cancelled => Err("render cancelled".to_string()),
+    }
+}
 
-        let input_file = match File::open(input_path) {
-            Ok(f) => f,
-            Err(e) => {
-                self.status = format!("Error opening input: {}", e);
-                self.status_ok = false;
-                return;
-            }
-        };
+fn chain_label(chain: &[(usize, [f32; 4])]) -> String {
+    chain.iter().map(|&(i, _)| EFFECTS[i].name).collect::<Vec<_>>().join(" > ")
+}
 
-        let output_file = match File::create(output_path) {
-            Ok(f) => f,
-            Err(e) => {
-                self.status = format!("Error creating output: {}", e);
-                self.status_ok = false;
-                return;
-            }
-        };
+/// The status line shown while `request` renders.
+fn render_label(request: &RenderRequest) -> String {
+    let spec = &request.spec;
+    if !spec.chain.is_empty() {
+        format!("Processing chain {}...", chain_label(&spec.chain))
+    } else if !spec.rack.is_empty() {
+        format!("Processing {} effects in parallel...", spec.rack.len())
+    } else if !request.segments.is_empty() {
+        format!("Processing {} with ramped pots...", spec.effect_name)
+    } else {
+        format!("Processing {}...", spec.effect_name)
+    }
+}
 
-        let result = Command::new(convert_path)
-            .arg(&effect_name)
-            .args(effect_pots.iter().map(|p| format!("{:.2}", p)))
-            .stdin(Stdio::from(input_file))
-            .stdout(Stdio::from(output_file))
-            .status();
+fn rack_label(rack: &[RackSlot]) -> String {
+    rack.iter()
+        .map(|s| format!("{} x{:.2}", EFFECTS[s.effect_idx].name, s.gain))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
 
-        match result {
-            Ok(status) if status.success() => {
-                self.stop_audio();
-                
-                self.player = Command::new("ffplay")
-                    .args(["-v", "fatal", "-nodisp", "-autoexit",
-                           "-f", SAMPLE_FORMAT, "-ar", SAMPLE_RATE,
-                           "-ch_layout", CHANNELS, "-i", output_path])
-                    .spawn()
-                    .ok();
+/// Where the previous render is kept when a new one replaces `output.raw`.
+fn prev_output_path(output_path: &str) -> String {
+    output_sibling(output_path, "prev.raw")
+}
 
-                self.status = format!(
-                    "Playing: {} [{:.2}, {:.2}, {:.2}, {:.2}]",
-                    effect_name, effect_pots[0], effect_pots[1], effect_pots[2], effect_pots[3]
-                );
-                self.status_ok = true;
-            }
-            _ => {
-                self.status = "Error: Processing failed".to_string();
-                self.status_ok = false;
-            }
-        }
+/// Puts back the render that a failed one moved aside. Only done when
+/// `output.raw` is missing: an import-only job never moved it.
+fn restore_prev_output(output_path: &str) {
+    let prev_path = prev_output_path(output_path);
+    if !Path::new(output_path).exists() && Path::new(&prev_path).exists() {
+        let _ = std::fs::rename(prev_path, output_path);
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+/// Extensions picked up as the input when no file was given, most
+/// preferred first.
+const DISCOVER_EXTENSIONS: &[&str] = &["mp3", "wav", "flac", "ogg", "m4a"];
+
+/// The audio file in `dir` to import when none was given: a file with the
+/// most preferred extension present, the first by name if there are
+/// several. The `output*` files this app writes are never picked.
+fn discover_audio_file(dir: &Path) -> Option<PathBuf> {
+    let mut candidates: Vec<(usize, PathBuf)> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| !path.file_name().is_some_and(|n| n.to_string_lossy().starts_with("output")))
+        .filter_map(|path| {
+            let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+            let rank = DISCOVER_EXTENSIONS.iter().position(|&e| e == ext)?;
+            Some((rank, path))
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().next().map(|(_, path)| path)
+}
 
-    let mut app = App::new();
+/// The audio file to import from: found in the project directory, then in
+/// the current one.
+fn find_audio_file(project_dir: &Path) -> Option<String> {
+    [project_dir, Path::new(".")]
+        .into_iter()
+        .find_map(discover_audio_file)
+        .map(|path| path.to_string_lossy().into_owned())
+}
 
-    loop {
-        terminal.draw(|f| ui(f, &mut app))?;
+/// Extensions accepted as a dropped input file; anything but `.raw` goes
+/// through the ffmpeg import.
+const AUDIO_EXTENSIONS: &[&str] = &["raw", "mp3", "wav", "flac", "ogg", "opus", "m4a", "aac", "aif", "aiff"];
 
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Char('Q') => {
-                            app.stop_audio();
-                            break;
-                        }
-                        KeyCode::Up | KeyCode::Char('k') => app.prev_effect(),
-                        KeyCode::Down | KeyCode::Char('j') => app.next_effect(),
-                        KeyCode::Tab => app.next_pot(),
-                        KeyCode::Left | KeyCode::Char('h') => app.decrease_pot(),
-                        KeyCode::Right | KeyCode::Char('l') => app.increase_pot(),
-                        KeyCode::Char('p') | KeyCode::Char('P') => app.process_and_play(),
-                        KeyCode::Char('r') | KeyCode::Char('R') => app.reset_pots(),
-                        KeyCode::Char('s') | KeyCode::Char('S') => {
-                            app.stop_audio();
-                            app.status = "Stopped playback".to_string();
-                            app.status_ok = true;
-                        }
-                        _ => {}
-                    }
+/// `C:\...` or `C:/...`: a Windows path, whose backslashes separate
+/// directories rather than escape spaces.
+fn is_drive_path(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/')
+}
+
+/// Interprets pasted text as a dropped file path. Terminals variously wrap
+/// it in quotes, prefix `file://`, or backslash-escape spaces; a path that
+/// exists as pasted, or a Windows one, is taken as it is.
+fn pasted_path(text: &str) -> Option<std::path::PathBuf> {
+    let text = text.trim();
+    if text.is_empty() || text.contains('\n') {
+        return None;
+    }
+    let unquoted = ['\'', '"']
+        .iter()
+        .find_map(|&q| text.strip_prefix(q).and_then(|t| t.strip_suffix(q)))
+        .unwrap_or(text);
+
+    let path = if let Some(uri) = unquoted.strip_prefix("file://") {
+        let bytes = uri.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+            match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+                (b'%', Some(b)) => {
+                    decoded.push(b);
+                    i += 3;
+                }
+                (b, _) => {
+                    decoded.push(b);
+                    i += 1;
                 }
             }
         }
-    }
+        String::from_utf8_lossy(&decoded).into_owned()
+    } else if is_drive_path(unquoted) || Path::new(unquoted).exists() {
+        unquoted.to_string()
+    } else {
+        let mut path = String::with_capacity(unquoted.len());
+        let mut chars = unquoted.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                path.extend(chars.next());
+            } else {
+                path.push(c);
+            }
+        }
+        path
+    };
+    Some(config::expand_home(&path))
+}
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
-    Ok(())
+/// The channel count in an ffmpeg stream description such as
+/// "Stream #0:0: Audio: mp3, 44100 Hz, stereo, fltp, 128 kb/s".
+fn stream_channels(line: &str) -> Option<usize> {
+    line.split(", ").find_map(|field| {
+        let field = field.trim();
+        ChannelLayout::parse(field)
+            .map(|layout| layout.channels)
+            .or_else(|| field.strip_suffix(" channels")?.parse().ok())
+    })
 }
 
-fn ui(f: &mut Frame, app: &mut App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Length(8),
-            Constraint::Length(7),
-            Constraint::Length(2),
-            Constraint::Length(1),
-        ])
-        .split(f.area());
+/// Channels in the first audio stream of `source`, as ffmpeg describes it;
+/// `None` if ffmpeg can't be run or doesn't say.
+fn source_channels(source: &str) -> Option<usize> {
+    let output = Command::new("ffmpeg").args(["-hide_banner", "-i", source]).stdin(Stdio::null()).output().ok()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr.lines().filter(|line| line.contains("Audio:")).find_map(stream_channels)
+}
 
-    let title = Paragraph::new("=== AUDIONOISE TUI ===")
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-        .alignment(ratatui::layout::Alignment::Center);
-    f.render_widget(title, chunks[0]);
+/// The ffmpeg import of a source file as raw audio. Mono imports use the
+/// configured channel selection; wider layouts are remapped by ffmpeg.
+fn import_command(
+    source_path: &str,
+    input_path: &str,
+    mono: MonoSource,
+    layout: ChannelLayout,
+    format: AudioFormat,
+) -> Command {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-y", "-v", "fatal", "-i", source_path]);
+    if layout.channels == 1 {
+        // Picking a side needs to know whether the source has one.
+        let channels = if mono == MonoSource::Sum { None } else { source_channels(source_path) };
+        cmd.args(mono.ffmpeg_args(channels));
+    } else {
+        cmd.args(["-ch_layout", layout.name]);
+    }
+    cmd.args(["-f", format.format, "-ar", &format.rate.to_string(), input_path]);
+    cmd
+}
 
-    let items: Vec<ListItem> = EFFECTS
-        .iter()
-        .enumerate()
-        .map(|(i, e)| {
-            let style = if i == app.effect_idx {
-                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
-            } else {
-                Style::default().fg(Color::Gray)
-            };
-            let marker = if i == app.effect_idx { "> " } else { "  " };
-            ListItem::new(format!("{}{}", marker, e.name.to_uppercase())).style(style)
+/// Renders a `Command` as a shell-pasteable line, with any environment it
+/// sets in front.
+fn command_line(cmd: &Command) -> String {
+    let envs = cmd.get_envs().filter_map(|(key, value)| {
+        value.map(|v| format!("{}={}", key.to_string_lossy(), shell_quote(&v.to_string_lossy())))
+    });
+    envs.chain(
+        std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|a| shell_quote(&a.to_string_lossy())),
+    )
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Uniform random pots over the full 0.0-1.0 range, rounded to the 0.01
+/// sent to `convert` and snapped for integer pots.
+fn random_pots(effect: &Effect, rng: &mut Rng) -> [f32; 4] {
+    let mut pots = [0.0; 4];
+    for (pot, step) in pots.iter_mut().zip(effect.steps) {
+        *pot = step.snap((rng.next_f32() * 100.0).round() / 100.0);
+    }
+    pots
+}
+
+/// The pots for each of `segments` successive slices of the clip: ramped
+/// pots step linearly from their start to their end value, the others stay
+/// at `pots`.
+fn ramp_pots(effect: &Effect, pots: [f32; 4], ramps: &[Option<(f32, f32)>; 4], segments: usize) -> Vec<[f32; 4]> {
+    (0..segments)
+        .map(|i| {
+            let t = if segments > 1 { i as f32 / (segments - 1) as f32 } else { 0.0 };
+            let mut stepped = pots;
+            for (pot, ramp) in ramps.iter().enumerate() {
+                if let Some((start, end)) = *ramp {
+                    stepped[pot] = effect.steps[pot].snap(start + (end - start) * t);
+                }
+            }
+            stepped
         })
-        .collect();
+        .collect()
+}
 
-    let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("EFFECTS"));
-    f.render_stateful_widget(list, chunks[1], &mut app.list_state);
+/// The pot value for column `x` of a bar: 0.0 at its first cell, 1.0 at
+/// its last, rounded to the 0.01 the pots display. Columns outside the bar
+/// clamp to its ends.
+fn bar_value(bar: Rect, x: u16) -> f32 {
+    let span = bar.width.saturating_sub(1).max(1) as f32;
+    let value = x.saturating_sub(bar.x) as f32 / span;
+    (value.clamp(0.0, 1.0) * 100.0).round() / 100.0
+}
 
-    let effect = &EFFECTS[app.effect_idx];
-    let pots = &app.pot_values[app.effect_idx];
-    
-    let mut pot_lines: Vec<Line> = vec![
-        Line::from(Span::styled(effect.desc, Style::default().fg(Color::Gray))),
-        Line::from(""),
-    ];
+/// Whether `name -version` runs and succeeds.
+fn tool_available(name: &str) -> bool {
+    Command::new(name)
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
 
-    for i in 0..4 {
-        let selected = i == app.pot_idx;
-        let value = pots[i];
-        let name = effect.pots[i];
-        
-        let bar_width = 20;
-        let filled = (value * bar_width as f32) as usize;
-        let bar = format!("[{}{}]", "#".repeat(filled), "-".repeat(bar_width - filled));
+/// A gain as "0.80 (-1.9 dB)".
+fn volume_label(volume: f32) -> String {
+    if volume <= 0.0 {
+        return "0.00 (-inf dB)".to_string();
+    }
+    format!("{:.2} ({:+.1} dB)", volume, 20.0 * volume.log10())
+}
 
-        let style = if selected {
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-        } else {
-            Style::default().fg(Color::White)
-        };
+/// Formats a duration as `m:ss`.
+fn format_clock(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
 
-        pot_lines.push(Line::from(vec![
-            Span::styled(format!(" {:12}", name), style),
-            Span::styled(bar, if selected { Style::default().fg(Color::Green) } else { Style::default().fg(Color::Blue) }),
-            Span::styled(format!(" {:.2}", value), style),
-        ]));
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
     }
+}
 
-    let pots_widget = Paragraph::new(pot_lines)
-        .block(Block::default().borders(Borders::ALL).title(format!("POTS - {}", effect.name.to_uppercase())));
-    f.render_widget(pots_widget, chunks[2]);
+/// Actions dispatched through `run_action`, which can be recorded into
+/// macros. The render and pot-setting ones can also be repeated with '.'.
+#[derive(Clone, Debug)]
+enum Action {
+    Process,
+    Reset,
+    SmartRandomize,
+    RecallSafe,
+    RecallLastPlayed,
+    LoadPreset(usize, NamedPreset),
+    NextEffect,
+    PrevEffect,
+    NextPot,
+    IncreasePot,
+    DecreasePot,
+    /// Shift+Left/Right: continuous pots move by `FINE` instead.
+    FineIncreasePot,
+    FineDecreasePot,
+}
 
-    let controls = Paragraph::new("Up/Down: effect | Tab: pot | Left/Right: value | p: play | s: stop | r: reset | q: quit")
-        .style(Style::default().fg(Color::Gray))
-        .alignment(ratatui::layout::Alignment::Center);
-    f.render_widget(controls, chunks[3]);
+impl Action {
+    fn repeatable(&self) -> bool {
+        matches!(
+            self,
+            Self::Process
+                | Self::Reset
+                | Self::SmartRandomize
+                | Self::RecallSafe
+                | Self::RecallLastPlayed
+                | Self::LoadPreset(..)
+        )
+    }
+
+    /// The name used for this action in the config `[macros]` table.
+    /// Presets are stored as `preset:<effect>/<name>`.
+    fn name(&self) -> String {
+        match self {
+            Self::Process => "process".to_string(),
+            Self::Reset => "reset".to_string(),
+            Self::SmartRandomize => "smart_randomize".to_string(),
+            Self::RecallSafe => "recall_safe".to_string(),
+            Self::RecallLastPlayed => "recall_last_played".to_string(),
+            Self::LoadPreset(eff, preset) => format!("preset:{}/{}", EFFECTS[*eff].name, preset.name),
+            Self::NextEffect => "next_effect".to_string(),
+            Self::PrevEffect => "prev_effect".to_string(),
+            Self::NextPot => "next_pot".to_string(),
+            Self::IncreasePot => "increase_pot".to_string(),
+            Self::DecreasePot => "decrease_pot".to_string(),
+            Self::FineIncreasePot => "fine_increase_pot".to_string(),
+            Self::FineDecreasePot => "fine_decrease_pot".to_string(),
+        }
+    }
+
+    /// Parses an action name, looking presets up in the loaded banks.
+    fn parse(name: &str, banks: &Banks) -> Result<Self, String> {
+        Ok(match name {
+            "process" => Self::Process,
+            "reset" => Self::Reset,
+            "smart_randomize" => Self::SmartRandomize,
+            "recall_safe" => Self::RecallSafe,
+            "recall_last_played" => Self::RecallLastPlayed,
+            "next_effect" => Self::NextEffect,
+            "prev_effect" => Self::PrevEffect,
+            "next_pot" => Self::NextPot,
+            "increase_pot" => Self::IncreasePot,
+            "decrease_pot" => Self::DecreasePot,
+            "fine_increase_pot" => Self::FineIncreasePot,
+            "fine_decrease_pot" => Self::FineDecreasePot,
+            _ => {
+                let (effect, preset) = name
+                    .strip_prefix("preset:")
+                    .and_then(|p| p.split_once('/'))
+                    .ok_or_else(|| format!("unknown action '{}'", name))?;
+                let eff = effect_index(effect).ok_or_else(|| format!("unknown effect '{}'", effect))?;
+                let preset = banks
+                    .get(EFFECTS[eff].name)
+                    .and_then(|bank| bank.iter().find(|p| p.name == preset))
+                    .ok_or_else(|| format!("no preset '{}' for {}", preset, EFFECTS[eff].name))?;
+                Self::LoadPreset(eff, preset.clone())
+            }
+        })
+    }
+}
+
+/// Key bindings by category, for the '?' overlay.
+const HELP: &[(&str, &[(&str, &str)])] = &[
+    ("Effects", &[
+        ("Up/Down, k/j", "previous/next"),
+        ("PgUp/PgDn", "page"),
+        ("1-9", "jump to effect"),
+        ("o", "preset banks"),
+        ("t", "mapped pots mode"),
+    ]),
+    ("Pots", &[
+        ("Tab", "next pot"),
+        ("Left/Right, h/l", "adjust"),
+        ("Shift+Left/Right", "fine adjust"),
+        ("H / L", "fine adjust"),
+        ("Enter", "type a value"),
+        ("Home/End", "min/max"),
+        ("f / Esc", "focus one pot / all pots"),
+        ("r", "reset to defaults"),
+        ("A", "ramp across the clip"),
+        ("!", "safe values"),
+        ("Ctrl+K", "last played values"),
+        ("n", "smart random"),
+        ("x / X", "random/from seed"),
+        ("Mouse", "click/drag a bar"),
+    ]),
+    ("Render and play", &[
+        ("p", "process and play"),
+        ("c", "cancel render"),
+        ("s", "stop playback"),
+        ("Ctrl+L", "loop playback"),
+        ("Ctrl+E", "live re-render"),
+        ("Ctrl+B", "brickwall limiter"),
+        ("Space", "pause/resume"),
+        ("( / )", "seek -/+2s"),
+        ("Ctrl+A", "output device"),
+        ("+ / -", "volume"),
+        ("{ / }", "master dry/wet"),
+        ("z", "previous/latest"),
+        (".", "repeat last action"),
+    ]),
+    ("Compare", &[
+        ("a / b", "store A/B slot"),
+        ("/", "swap A/B"),
+        ("v", "A/B stereo split"),
+        ("d", "difference mode"),
+    ]),
+    ("Rack and chain", &[
+        ("m / M", "rack add/remove"),
+        ("[ / ]", "rack gain"),
+        ("> / <", "chain add/clear"),
+    ]),
+    ("Audio", &[
+        ("C", "mono/stereo"),
+        ("i", "import channel"),
+        ("R", "record from mic"),
+        ("e", "fades"),
+        ("B", "meter dB/linear"),
+        ("Ctrl+T", "color theme"),
+        ("Ctrl+D", "log commands"),
+    ]),
+    ("Files and sharing", &[
+        ("w", "export WAV"),
+        ("g", "spectrogram PNG"),
+        ("Ctrl+G", "waveform/spectrum"),
+        ("y", "copy command"),
+        ("Ctrl+O", "open folder"),
+        ("@ / :", "share/import patch"),
+        ("Ctrl+S / Ctrl+P", "save/cycle preset"),
+        ("J / K", "next/previous file"),
+        ("F5", "re-check files"),
+    ]),
+    ("Undo and macros", &[
+        ("u / Ctrl+R", "undo/redo pots"),
+        ("U / Ctrl+Y", "undo/redo all"),
+        ("F9", "record macro"),
+        ("F1-F4", "save/play macro"),
+    ]),
+    ("General", &[
+        ("?", "this help"),
+        ("q", "quit"),
+    ]),
+];
+
+/// Loudest playback volume, as a gain.
+const MAX_VOLUME: f32 = 1.5;
+/// How far each '+'/'-' press moves the playback volume.
+const VOLUME_STEP: f32 = 0.05;
+/// How far each '{'/'}' press moves the master wet/dry mix.
+const MASTER_MIX_STEP: f32 = 0.1;
+/// Slices a clip is cut into when a pot is ramped; each renders at one
+/// step of the ramp.
+const AUTOMATION_SEGMENTS: usize = 16;
+/// How far '(' and ')' move the playhead.
+const SEEK_SECS: f64 = 2.0;
+/// How long the pots must sit still before live mode re-renders.
+const LIVE_DEBOUNCE: Duration = Duration::from_millis(300);
+/// A loop pass that ends sooner than this (and than half the clip) means
+/// ffplay gave up rather than reached the end.
+const MIN_LOOP_PASS: Duration = Duration::from_millis(500);
+/// Pot snapshots kept per effect for 'u'/Ctrl+R.
+const POT_HISTORY_LEN: usize = 50;
+
+/// Labels of the two A/B compare slots.
+const AB_NAMES: [&str; 2] = ["A", "B"];
+
+/// Number of macro slots, replayed with F1..=F4.
+const MACRO_SLOTS: usize = 4;
+
+/// Everything the global undo restores: the selected effect and pot, every
+/// effect's pots, and the parallel rack.
+#[derive(Clone, Debug, PartialEq)]
+struct Snapshot {
+    effect_idx: usize,
+    pot_idx: usize,
+    pot_values: Vec<[f32; 4]>,
+    rack: Vec<RackSlot>,
+}
+
+enum PickerLevel {
+    Effects,
+    Presets(usize),
+}
+
+/// Overlay listing the output devices found when it was opened, with the
+/// default output first.
+struct DevicePicker {
+    devices: Vec<devices::Device>,
+    list_state: ListState,
+}
+
+/// Two-level overlay for browsing preset banks: effect, then preset name.
+struct BankPicker {
+    level: PickerLevel,
+    list_state: ListState,
+}
+
+/// What the status-line text prompt is collecting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PromptKind {
+    ImportPatch,
+    SavePreset,
+    RandomSeed,
+    RecordSeconds,
+    Automation,
+}
+
+impl PromptKind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::ImportPatch => "Import patch (effect:p1,p2,p3,p4)",
+            Self::SavePreset => "Save preset as",
+            Self::RandomSeed => "Randomize with seed",
+            Self::RecordSeconds => "Record from the microphone, seconds",
+            Self::Automation => "Ramp this pot across the clip (start,end; empty clears)",
+        }
+    }
+}
+
+struct Prompt {
+    kind: PromptKind,
+    buffer: String,
+}
+
+/// A y/n question asked before rendering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Confirm {
+    /// `output.raw` exists and hasn't been exported.
+    Overwrite,
+    /// The same, asked once when live mode goes on, since its renders
+    /// replace `output.raw` without asking.
+    LiveMode,
+    /// `convert` lacks the execute bit; 'y' sets it (Unix only).
+    MakeExecutable,
+    /// Quitting would drop pot changes on `modified` effects, or a render
+    /// that was never exported.
+    Quit { modified: usize, unexported: bool },
+}
+
+impl Confirm {
+    fn question(self, convert_path: &str) -> String {
+        match self {
+            Self::Overwrite => "Overwrite output.raw? y/n".to_string(),
+            Self::LiveMode => "Live mode overwrites output.raw on every change - turn it on? y/n".to_string(),
+            Self::MakeExecutable => {
+                format!("'{}' is not executable - make it executable (chmod +x)? y/n", convert_path)
+            }
+            Self::Quit { modified, unexported } => {
+                let mut unsaved = Vec::new();
+                if modified > 0 {
+                    unsaved.push(format!("{} effect{} modified", modified, if modified == 1 { "" } else { "s" }));
+                }
+                if unexported {
+                    unsaved.push("output not exported".to_string());
+                }
+                format!("Quit with {}? y/n", unsaved.join(", "))
+            }
+        }
+    }
+}
+
+/// A microphone recording in progress: ffmpeg writing to the scratch file
+/// `path`, which becomes the input once it finishes.
+struct Capture {
+    child: Child,
+    path: String,
+    started: Instant,
+    length: Duration,
+}
+
+/// Longest recording 'R' accepts, in seconds.
+const MAX_RECORD_SECS: u64 = 600;
+
+/// ffmpeg's input format and device for the default microphone.
+fn mic_input_args() -> [&'static str; 4] {
+    if cfg!(target_os = "macos") {
+        ["-f", "avfoundation", "-i", ":default"]
+    } else if cfg!(windows) {
+        ["-f", "dshow", "-i", "audio=default"]
+    } else {
+        ["-f", "pulse", "-i", "default"]
+    }
+}
+
+/// The file being played and when the current pass through it started.
+struct Playing {
+    path: String,
+    layout: ChannelLayout,
+    pass_started: Instant,
+    /// Length of one pass, from the file size; `None` if unreadable.
+    duration: Option<Duration>,
+}
+
+struct App {
+    config: Config,
+    /// Where `convert`, `input.raw` and `output.raw` live.
+    project_dir: PathBuf,
+    /// The convert binary, from `--convert`, `AUDIONOISE_CONVERT` or the
+    /// project directory.
+    convert_path: PathBuf,
+    /// Set by `--project`: `presets.toml` is kept in the project too,
+    /// instead of the config directory.
+    project_presets: bool,
+    /// Indexes into `EFFECTS` of the effects shown, in display order. Empty
+    /// when the config filters every effect out.
+    effects: Vec<usize>,
+    effect_idx: usize,
+    pot_idx: usize,
+    pot_values: Vec<[f32; 4]>,
+    /// Resolved `pot_map` targets as `(effect, pot)` indexes, and whether
+    /// the pots currently edit them instead of the selected effect.
+    pot_map: Option<[(usize, usize); 4]>,
+    mapped_mode: bool,
+    status: String,
+    status_ok: bool,
+    list_state: ListState,
+    /// Rows of the effects list that fit on screen, updated each frame.
+    effects_height: usize,
+    /// The '?' key binding overlay is open; the next key closes it.
+    show_help: bool,
+    /// Size and length of `input.raw`, refreshed when the input changes.
+    input_info: FileInfo,
+    /// Monitoring gain, 0.0..=MAX_VOLUME; 1.0 plays the render as is.
+    volume: f32,
+    /// Master wet/dry blend against the input, applied after processing:
+    /// 1.0 plays the render as is.
+    master_mix: f32,
+    /// Seed of the last 'x' randomize, shown in the status line and
+    /// offered again by 'X'.
+    random_seed: Option<u64>,
+    /// Where each pot was last drawn, for mouse clicks.
+    pot_hits: Vec<PotHit>,
+    /// The pot whose bar is being dragged with the mouse.
+    dragging: Option<usize>,
+    player: Option<Player>,
+    split_monitor: bool,
+    /// Play the residual (output minus input) instead of the output.
+    difference_mode: bool,
+    job: Option<RenderJob>,
+    rack: Vec<RackSlot>,
+    /// Effects applied in sequence, each to the previous one's output.
+    /// Empty for single-effect (or parallel rack) rendering.
+    chain: Vec<(usize, [f32; 4])>,
+    last_action: Option<Action>,
+    /// Actions captured since macro recording started, if recording.
+    recording: Option<Vec<Action>>,
+    macros: [Vec<Action>; MACRO_SLOTS],
+    history: History<Snapshot>,
+    /// Per-effect history of just that effect's pots, for 'u'/Ctrl+R.
+    pot_history: Vec<History<[f32; 4]>>,
+    tutorial: Option<tutorial::Step>,
+    banks: Banks,
+    bank_picker: Option<BankPicker>,
+    device_picker: Option<DevicePicker>,
+    /// Where playback goes; `None` is the system default output.
+    output_device: Option<devices::Device>,
+    /// The status-line text prompt, while one is open.
+    prompt: Option<Prompt>,
+    /// A y/n question in the status line, while one is open.
+    confirm: Option<Confirm>,
+    /// Pot values as of startup or the last preset save, per effect, for
+    /// telling what quitting would lose.
+    saved_pots: Vec<[f32; 4]>,
+    /// Set once quitting is confirmed; the main loop exits.
+    quit: bool,
+    /// `output.raw` holds a render that hasn't been exported to WAV. Set at
+    /// startup too, since an existing file may be from an earlier session.
+    dirty_output: bool,
+    /// The last successful render, previewed in the waveform panel.
+    waveform_path: Option<String>,
+    /// Its min/max envelope, read by the render worker at
+    /// `ENVELOPE_COLUMNS` and narrowed to the panel when drawn.
+    waveform: Option<Vec<(i64, i64)>>,
+    /// Show the render's spectrum in the waveform panel instead.
+    spectrum_view: bool,
+    /// 'f': the pots panel shows only the selected pot, large.
+    focused_pot: bool,
+    /// Its log-spaced band magnitudes, cached like `waveform`.
+    spectrum: Option<(u16, Vec<f32>)>,
+    /// Digits typed for the selected pot while editing its exact value.
+    pot_edit: Option<String>,
+    /// Saved preset last loaded with Ctrl+P, where cycling resumes.
+    user_preset: Option<String>,
+    playing_prev: bool,
+    dirty_since_render: bool,
+    /// Channel selection `input.raw` was imported with this session, if any.
+    imported_mono: Option<MonoSource>,
+    /// Set when `source` was chosen but not imported yet, so the next
+    /// render imports it even though an old `input.raw` exists.
+    reimport: bool,
+    /// Channel layout `input.raw` holds, once known this session.
+    input_layout: Option<ChannelLayout>,
+    /// Audio file dropped onto the terminal, imported in place of any
+    /// discovered one.
+    source: Option<String>,
+    /// An audio file found in the project directory, cached by
+    /// `check_environment` so drawing doesn't list the directory each frame.
+    discovered: Option<String>,
+    /// Files from the command line stepped through with J/K.
+    playlist: Option<Playlist>,
+    /// What renders read in place of the project's `input.raw`: an import
+    /// or recording among `temp_files`, or a raw file used where it is.
+    input_file: Option<PathBuf>,
+    /// Scratch files handed out by `temp_file`, removed when the app goes.
+    temp_files: Vec<PathBuf>,
+    rng: Rng,
+    /// The last `check_environment` warning and when it was checked; it is
+    /// re-checked while the status line still shows it.
+    env_warning: Option<String>,
+    env_checked: Instant,
+    /// Whether `ffmpeg -version` / `ffplay -version` ran, probed at startup
+    /// and again on F5 or while a missing-tool warning is showing.
+    has_ffmpeg: bool,
+    has_ffplay: bool,
+    playback_started: Option<Instant>,
+    /// Per effect: pots stored in A/B compare slots, and which slot was
+    /// last swapped in.
+    ab_slots: Vec<[Option<[f32; 4]>; 2]>,
+    ab_active: Vec<Option<usize>>,
+    /// Per effect and pot: a `(start, end)` ramp across the clip, set with
+    /// 'A'. Renders of a ramped effect go through `AUTOMATION_SEGMENTS`.
+    automation: Vec<[Option<(f32, f32)>; 4]>,
+    /// Per effect, its pots in the last render that finished and played,
+    /// whether alone, in the rack or as a chain stage.
+    last_played: Vec<Option<[f32; 4]>>,
+    /// What is currently being played, for metering and looping.
+    playing: Option<Playing>,
+    capture: Option<Capture>,
+    /// Length of the last recording, offered again by the next 'R'.
+    record_secs: u64,
+    /// Restart playback from the top each time it reaches the end.
+    loop_playback: bool,
+    /// Re-render and replay by itself once the pots settle.
+    live_mode: bool,
+    /// When the effect or pots last changed while live, and what they were
+    /// then; a render is due `LIVE_DEBOUNCE` after the change.
+    last_change: Option<Instant>,
+    live_seen: (usize, Vec<[f32; 4]>),
+    meter: PeakMeter,
+}
+
+impl App {
+    fn new(
+        config: Config,
+        project: Option<&Path>,
+        convert: Option<&Path>,
+        effects: Vec<usize>,
+        effect_idx: usize,
+        banks: Banks,
+        session: Option<Session>,
+    ) -> Self {
+        let pot_values = EFFECTS.iter().map(|e| e.defaults).collect();
+        let mut list_state = ListState::default();
+        list_state.select(effects.iter().position(|&i| i == effect_idx));
+        
+        let mut app = Self {
+            config,
+            project_dir: project_dir(project),
+            convert_path: convert_path(convert, &project_dir(project)),
+            project_presets: project.is_some(),
+            effects,
+            effect_idx,
+            pot_idx: 0,
+            pot_values,
+            pot_map: None,
+            mapped_mode: false,
+            status: String::new(),
+            status_ok: true,
+            list_state,
+            effects_height: EFFECTS.len(),
+            show_help: false,
+            input_info: FileInfo::default(),
+            volume: 1.0,
+            master_mix: 1.0,
+            random_seed: None,
+            pot_hits: Vec::new(),
+            dragging: None,
+            player: None,
+            split_monitor: false,
+            difference_mode: false,
+            job: None,
+            rack: Vec::new(),
+            chain: Vec::new(),
+            last_action: None,
+            recording: None,
+            macros: Default::default(),
+            history: History::default(),
+            pot_history: (0..EFFECTS.len()).map(|_| History::with_capacity(POT_HISTORY_LEN)).collect(),
+            tutorial: None,
+            banks,
+            bank_picker: None,
+            device_picker: None,
+            output_device: None,
+            prompt: None,
+            confirm: None,
+            saved_pots: Vec::new(),
+            quit: false,
+            dirty_output: true,
+            waveform_path: None,
+            waveform: None,
+            spectrum_view: false,
+            focused_pot: false,
+            spectrum: None,
+            pot_edit: None,
+            user_preset: None,
+            playing_prev: false,
+            dirty_since_render: true,
+            imported_mono: None,
+            reimport: false,
+            input_layout: None,
+            source: None,
+            discovered: None,
+            rng: Rng::from_time(),
+            env_warning: None,
+            env_checked: Instant::now(),
+            has_ffmpeg: false,
+            has_ffplay: false,
+            playback_started: None,
+            ab_slots: vec![[None; 2]; EFFECTS.len()],
+            automation: vec![[None; 4]; EFFECTS.len()],
+            last_played: vec![None; EFFECTS.len()],
+            ab_active: vec![None; EFFECTS.len()],
+            playing: None,
+            playlist: None,
+            input_file: None,
+            temp_files: Vec::new(),
+            capture: None,
+            record_secs: 5,
+            loop_playback: false,
+            live_mode: false,
+            last_change: None,
+            live_seen: (0, Vec::new()),
+            meter: PeakMeter::default(),
+        };
+        if let Some(session) = session {
+            app.restore_session(session);
+        }
+        app.saved_pots = app.pot_values.clone();
+        app.probe_tools();
+        app.check_environment();
+        app
+    }
+
+    /// `(convert, input.raw, output.raw)`: `convert_path` and the project
+    /// directory's files, with `input_file` standing in for `input.raw`
+    /// when set.
+    fn work_paths(&self) -> (String, String, String) {
+        let (input, output) = project_paths(&self.project_dir);
+        let input = self.input_file.as_ref().map_or(input, |path| path.to_string_lossy().into_owned());
+        (self.convert_path.to_string_lossy().into_owned(), input, output)
+    }
+
+    /// A new scratch file, removed by `remove_temp_files`.
+    fn temp_file(&mut self, name: &str) -> String {
+        let path = scratch_path(name);
+        self.temp_files.push(path.clone());
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Stops tracking scratch files a finished render has already deleted.
+    fn forget_temp_files(&mut self, paths: &[String]) {
+        self.temp_files.retain(|path| !paths.iter().any(|p| Path::new(p) == path));
+    }
+
+    /// Deletes every scratch file handed out this session.
+    fn remove_temp_files(&mut self) {
+        for path in self.temp_files.drain(..) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Makes `path` the input renders read. A scratch file it replaces is
+    /// deleted; the caller takes `input_file` first to keep it.
+    fn set_input_path(&mut self, path: Option<PathBuf>) {
+        if let Some(old) = std::mem::replace(&mut self.input_file, path) {
+            if self.temp_files.contains(&old) && self.input_file.as_ref() != Some(&old) {
+                let _ = std::fs::remove_file(old);
+            }
+        }
+    }
+
+    fn presets_path(&self) -> Option<PathBuf> {
+        if self.project_presets {
+            Some(self.project_dir.join("presets.toml"))
+        } else {
+            config::presets_path()
+        }
+    }
+
+    /// Applies a saved session. Pots are matched by effect name, and a
+    /// selected effect that no longer exists or is hidden is ignored.
+    fn restore_session(&mut self, session: Session) {
+        for (name, pots) in &session.pots {
+            if let Some(idx) = effect_index(name) {
+                self.pot_values[idx] = *pots;
+            }
+        }
+        if let Some(idx) = session.effect.as_deref().and_then(effect_index).filter(|i| self.effects.contains(i)) {
+            self.effect_idx = idx;
+            self.list_state.select(self.effects.iter().position(|&i| i == idx));
+        }
+        self.pot_idx = session.pot_idx.min(3);
+        self.loop_playback = session.loop_playback;
+        if let Some(layout) = session.layout {
+            self.config.layout = layout;
+        }
+    }
+
+    fn session(&self) -> Session {
+        Session {
+            effect: Some(EFFECTS[self.effect_idx].name.to_string()),
+            pot_idx: self.pot_idx,
+            pots: EFFECTS.iter().zip(&self.pot_values).map(|(e, pots)| (e.name.to_string(), *pots)).collect(),
+            loop_playback: self.loop_playback,
+            layout: Some(self.config.layout),
+        }
+    }
+
+    fn probe_tools(&mut self) {
+        self.has_ffmpeg = tool_available("ffmpeg");
+        self.has_ffplay = tool_available("ffplay");
+    }
+
+    /// Re-probes the tools and then re-checks the environment, for F5.
+    fn refresh_environment(&mut self) {
+        self.probe_tools();
+        self.check_environment();
+    }
+
+    fn check_environment(&mut self) {
+        self.env_checked = Instant::now();
+        self.discovered = find_audio_file(&self.project_dir);
+        self.refresh_input_info();
+        let (convert_path, input_path, _) = &self.work_paths();
+        if self.effects.is_empty() {
+            self.status = NO_EFFECTS.to_string();
+            self.status_ok = false;
+        } else if !Path::new(convert_path).exists() {
+            self.status =
+                format!("Warning: '{}' not found. Run 'make convert' first, or point --convert at it.", convert_path);
+            self.status_ok = false;
+        } else if !is_executable(Path::new(convert_path)) {
+            self.status = format!("Warning: '{}' is not executable - run chmod +x {}", convert_path, convert_path);
+            self.status_ok = false;
+        } else if !self.has_ffplay {
+            self.status = "Warning: 'ffplay' not found on PATH - install ffmpeg to enable playback".to_string();
+            self.status_ok = false;
+        } else if !Path::new(input_path).exists() {
+            if self.has_ffmpeg {
+                self.status = "No input.raw found - will try to import an audio file".to_string();
+                self.status_ok = true;
+            } else {
+                self.status = "Warning: no input.raw and 'ffmpeg' not found on PATH to import one".to_string();
+                self.status_ok = false;
+            }
+        } else {
+            self.status = "Ready - press 'p' to process, 'q' to quit".to_string();
+            self.status_ok = true;
+            self.env_warning = None;
+            return;
+        }
+        self.env_warning = Some(self.status.clone());
+    }
+
+    /// Re-runs `check_environment` periodically while its warning is still
+    /// showing, so it clears once the missing file appears.
+    fn recheck_environment(&mut self) {
+        let Some(interval) = self.config.env_recheck else {
+            return;
+        };
+        let showing = self.env_warning.as_ref().is_some_and(|w| *w == self.status);
+        if showing && self.env_checked.elapsed() >= interval {
+            if !self.has_ffmpeg || !self.has_ffplay {
+                self.probe_tools();
+            }
+            self.check_environment();
+        }
+    }
+
+    fn next_effect(&mut self) {
+        self.step_effect(1);
+    }
+
+    fn prev_effect(&mut self) {
+        let len = self.effects.len();
+        self.step_effect(len.saturating_sub(1));
+    }
+
+    /// Moves `offset` places through the shown effects, wrapping around.
+    fn step_effect(&mut self, offset: usize) {
+        let len = self.effects.len();
+        if len == 0 {
+            return;
+        }
+        let pos = self.effects.iter().position(|&i| i == self.effect_idx).unwrap_or(0);
+        self.select_effect_at((pos + offset) % len);
+    }
+
+    /// Moves a page of effects up (`forward == false`) or down, stopping
+    /// at either end of the list.
+    fn page_effects(&mut self, forward: bool) {
+        let len = self.effects.len();
+        if len == 0 {
+            return;
+        }
+        let page = self.effects_height.max(1);
+        let pos = self.effects.iter().position(|&i| i == self.effect_idx).unwrap_or(0);
+        let pos = if forward { (pos + page).min(len - 1) } else { pos.saturating_sub(page) };
+        self.select_effect_at(pos);
+    }
+
+    /// Selects the `number`th shown effect, counting from 1; numbers past
+    /// the end of the list are ignored.
+    fn jump_to_effect(&mut self, number: usize) {
+        if (1..=self.effects.len()).contains(&number) {
+            self.select_effect_at(number - 1);
+        }
+    }
+
+    fn select_effect_at(&mut self, pos: usize) {
+        self.effect_idx = self.effects[pos];
+        self.list_state.select(Some(pos));
+        self.pot_idx = 0;
+        self.dirty_since_render = true;
+        self.keep_selection_visible();
+    }
+
+    /// Scrolls the effects list so the selected row is inside the
+    /// `effects_height` rows that fit on screen.
+    fn keep_selection_visible(&mut self) {
+        let Some(selected) = self.list_state.selected() else {
+            return;
+        };
+        let height = self.effects_height.max(1);
+        let offset = self.list_state.offset().min(self.effects.len().saturating_sub(height));
+        *self.list_state.offset_mut() = if selected < offset {
+            selected
+        } else if selected >= offset + height {
+            selected + 1 - height
+        } else {
+            offset
+        };
+    }
+
+    fn next_pot(&mut self) {
+        self.pot_idx = (self.pot_idx + 1) % 4;
+    }
+
+    fn increase_pot(&mut self) {
+        self.step_pot(1.0, false);
+    }
+
+    fn decrease_pot(&mut self) {
+        self.step_pot(-1.0, false);
+    }
+
+    /// Moves the selected pot one step; `fine` swaps continuous pots to the
+    /// 0.01 step, while integer pots always move one whole number.
+    fn step_pot(&mut self, dir: f32, fine: bool) {
+        let (eff_idx, idx) = self.pot_target();
+        let step = match EFFECTS[eff_idx].steps[idx] {
+            PotStep::Continuous(_) if fine => FINE,
+            COARSE => PotStep::Continuous(self.config.pot_step),
+            step => step,
+        };
+        self.write_pot(eff_idx, idx, step.apply(self.pot_values[eff_idx][idx], dir));
+    }
+
+    /// The `(effect, pot)` the selected on-screen pot edits.
+    fn pot_target(&self) -> (usize, usize) {
+        match self.pot_map {
+            Some(map) if self.mapped_mode => map[self.pot_idx],
+            _ => (self.effect_idx, self.pot_idx),
+        }
+    }
+
+    /// Sets one pot, keeping that effect's rack slot (if any) in step so
+    /// mapped edits reach a parallel render too.
+    fn write_pot(&mut self, eff_idx: usize, idx: usize, value: f32) {
+        self.pot_values[eff_idx][idx] = value;
+        if let Some(slot) = self.rack.iter_mut().find(|s| s.effect_idx == eff_idx) {
+            slot.pots[idx] = value;
+        }
+        self.dirty_since_render = true;
+    }
+
+    fn toggle_mapped_mode(&mut self) {
+        let Some(map) = self.pot_map else {
+            self.status = "No pot_map in config - set pot_map = [\"effect.pot\", ...]".to_string();
+            self.status_ok = false;
+            return;
+        };
+        self.mapped_mode = !self.mapped_mode;
+        self.status = if self.mapped_mode {
+            let targets: Vec<String> = map
+                .iter()
+                .map(|&(e, p)| format!("{}.{}", EFFECTS[e].name, EFFECTS[e].pots[p]))
+                .collect();
+            format!("Mapped pots: {}", targets.join(", "))
+        } else {
+            format!("Pots edit {}", EFFECTS[self.effect_idx].name)
+        };
+        self.status_ok = true;
+    }
+
+    /// Routes keys while the tutorial is open. Each step only advances once
+    /// the user performs it; the key is applied to the app as usual.
+    fn handle_tutorial_key(&mut self, code: KeyCode) {
+        use tutorial::Step;
+        let Some(step) = self.tutorial else {
+            return;
+        };
+        if code == KeyCode::Esc {
+            self.finish_tutorial();
+            return;
+        }
+
+        let performed = match (step, code) {
+            (Step::Welcome | Step::Done, _) => true,
+            (Step::SelectEffect, KeyCode::Up | KeyCode::Char('k')) => {
+                self.undoable(Self::prev_effect);
+                true
+            }
+            (Step::SelectEffect, KeyCode::Down | KeyCode::Char('j')) => {
+                self.undoable(Self::next_effect);
+                true
+            }
+            (Step::AdjustPot, KeyCode::Tab) => {
+                self.next_pot();
+                false
+            }
+            (Step::AdjustPot, KeyCode::Left | KeyCode::Char('h')) => {
+                self.undoable(Self::decrease_pot);
+                true
+            }
+            (Step::AdjustPot, KeyCode::Right | KeyCode::Char('l')) => {
+                self.undoable(Self::increase_pot);
+                true
+            }
+            (Step::Render, KeyCode::Char('p') | KeyCode::Char('P')) => {
+                self.run_action(Action::Process);
+                true
+            }
+            _ => false,
+        };
+
+        if performed {
+            match step.next() {
+                Some(next) => self.tutorial = Some(next),
+                None => self.finish_tutorial(),
+            }
+        }
+    }
+
+    /// Closes the tutorial and writes a starter config so it isn't shown on
+    /// the next launch.
+    fn finish_tutorial(&mut self) {
+        self.tutorial = None;
+        if let Err(e) = config::write_starter_config() {
+            self.status = format!("Error writing config: {}", e);
+            self.status_ok = false;
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            effect_idx: self.effect_idx,
+            pot_idx: self.pot_idx,
+            pot_values: self.pot_values.clone(),
+            rack: self.rack.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.effect_idx = snapshot.effect_idx;
+        self.list_state.select(self.effects.iter().position(|&i| i == self.effect_idx));
+        self.pot_idx = snapshot.pot_idx;
+        self.pot_values = snapshot.pot_values;
+        self.rack = snapshot.rack;
+        self.dirty_since_render = true;
+    }
+
+    /// Runs `change` and records the prior state in the undo history if
+    /// anything undoable actually changed. Effects whose pots changed also
+    /// get an entry in their own pot history.
+    fn undoable(&mut self, change: impl FnOnce(&mut Self)) {
+        let before = self.snapshot();
+        change(self);
+        if self.snapshot() != before {
+            for (eff, pots) in before.pot_values.iter().enumerate() {
+                if *pots != self.pot_values[eff] {
+                    self.pot_history[eff].push(*pots);
+                }
+            }
+            self.history.push(before);
+        }
+    }
+
+    /// Steps the selected effect's pots back, leaving other effects, the
+    /// selection and the rack alone.
+    fn undo_pots(&mut self) {
+        let eff = self.effect_idx;
+        match self.pot_history[eff].undo(self.pot_values[eff]) {
+            Some(pots) => {
+                self.set_effect_pots(eff, pots);
+                self.status = format!("Undo {} pots", EFFECTS[eff].name);
+                self.status_ok = true;
+            }
+            None => {
+                self.status = format!("Nothing to undo for {}", EFFECTS[eff].name);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    fn redo_pots(&mut self) {
+        let eff = self.effect_idx;
+        match self.pot_history[eff].redo(self.pot_values[eff]) {
+            Some(pots) => {
+                self.set_effect_pots(eff, pots);
+                self.status = format!("Redo {} pots", EFFECTS[eff].name);
+                self.status_ok = true;
+            }
+            None => {
+                self.status = format!("Nothing to redo for {}", EFFECTS[eff].name);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    fn set_effect_pots(&mut self, eff: usize, pots: [f32; 4]) {
+        for (i, &value) in pots.iter().enumerate() {
+            self.write_pot(eff, i, value);
+        }
+    }
+
+    fn undo(&mut self) {
+        let current = self.snapshot();
+        match self.history.undo(current) {
+            Some(prev) => {
+                self.restore(prev);
+                self.status = format!("Undo - {} selected", EFFECTS[self.effect_idx].name);
+                self.status_ok = true;
+            }
+            None => {
+                self.status = "Nothing to undo".to_string();
+                self.status_ok = false;
+            }
+        }
+    }
+
+    fn redo(&mut self) {
+        let current = self.snapshot();
+        match self.history.redo(current) {
+            Some(next) => {
+                self.restore(next);
+                self.status = format!("Redo - {} selected", EFFECTS[self.effect_idx].name);
+                self.status_ok = true;
+            }
+            None => {
+                self.status = "Nothing to redo".to_string();
+                self.status_ok = false;
+            }
+        }
+    }
+
+    fn run_action(&mut self, action: Action) {
+        match &action {
+            Action::Process => self.process_and_play(),
+            Action::Reset => self.undoable(Self::reset_pots),
+            Action::SmartRandomize => self.undoable(Self::smart_randomize),
+            Action::RecallSafe => self.undoable(Self::recall_safe_values),
+            Action::RecallLastPlayed => self.undoable(Self::recall_last_played),
+            Action::LoadPreset(eff, preset) => self.undoable(|app| app.load_named_preset(*eff, preset)),
+            Action::NextEffect => self.undoable(Self::next_effect),
+            Action::PrevEffect => self.undoable(Self::prev_effect),
+            Action::NextPot => self.next_pot(),
+            Action::IncreasePot => self.undoable(Self::increase_pot),
+            Action::DecreasePot => self.undoable(Self::decrease_pot),
+            Action::FineIncreasePot => self.undoable(|app| app.step_pot(1.0, true)),
+            Action::FineDecreasePot => self.undoable(|app| app.step_pot(-1.0, true)),
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.push(action.clone());
+        }
+        if action.repeatable() {
+            self.last_action = Some(action);
+        }
+    }
+
+    fn start_macro_recording(&mut self) {
+        if self.recording.take().is_some() {
+            self.status = "Macro recording discarded".to_string();
+        } else {
+            self.recording = Some(Vec::new());
+            self.status = "Recording macro - F1-F4 saves it to that slot, F9 discards".to_string();
+        }
+        self.status_ok = true;
+    }
+
+    /// F1-F4: finishes a recording into `slot`, or replays that macro.
+    fn macro_key(&mut self, slot: usize) {
+        if let Some(actions) = self.recording.take() {
+            let names: Vec<String> = actions.iter().map(Action::name).collect();
+            let count = actions.len();
+            self.macros[slot] = actions;
+            match config::save_macro(&format!("f{}", slot + 1), &names) {
+                Ok(()) => {
+                    self.status = format!("Saved {} actions to macro F{}", count, slot + 1);
+                    self.status_ok = true;
+                }
+                Err(e) => {
+                    self.status = format!("Macro F{} kept for this session, not saved: {}", slot + 1, e);
+                    self.status_ok = false;
+                }
+            }
+            return;
+        }
+        if self.macros[slot].is_empty() {
+            self.status = format!("Macro F{} is empty - press F9 to record one", slot + 1);
+            self.status_ok = false;
+            return;
+        }
+        for action in self.macros[slot].clone() {
+            self.run_action(action);
+        }
+    }
+
+    fn repeat_last_action(&mut self) {
+        match self.last_action.clone() {
+            Some(action) => self.run_action(action),
+            None => {
+                self.status = "Nothing to repeat yet".to_string();
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Sets the selected pot, snapped onto its grid if it's an integer one.
+    fn set_pot(&mut self, value: f32) {
+        let (eff_idx, idx) = self.pot_target();
+        self.write_pot(eff_idx, idx, EFFECTS[eff_idx].steps[idx].snap(value.clamp(0.0, 1.0)));
+    }
+
+    /// A left click on a pot's row selects it; a click or drag on its bar
+    /// also sets its value from the column, one undo step per click.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let at = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(i) = self.pot_hits.iter().position(|hit| hit.row.contains(at)) else {
+                    return;
+                };
+                self.pot_idx = i;
+                let bar = self.pot_hits[i].bar;
+                if bar.contains(at) {
+                    self.dragging = Some(i);
+                    self.undoable(|app| app.set_pot(bar_value(bar, mouse.column)));
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(hit) = self.dragging.and_then(|i| self.pot_hits.get(i)) {
+                    let value = bar_value(hit.bar, mouse.column);
+                    self.set_pot(value);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.dragging = None,
+            _ => {}
+        }
+    }
+
+    /// Starts typing an exact value for the selected pot.
+    fn start_pot_edit(&mut self) {
+        self.pot_edit = Some(String::new());
+        self.status = "Type a value 0.0-1.0, Enter to set, Esc to cancel".to_string();
+        self.status_ok = true;
+    }
+
+    fn handle_pot_edit_key(&mut self, code: KeyCode) {
+        let Some(buffer) = self.pot_edit.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Char(c) if c.is_ascii_digit() || (c == '.' && !buffer.contains('.')) => buffer.push(c),
+            KeyCode::Char(c) => {
+                self.status = format!("'{}' isn't part of a number - type digits and '.'", c);
+                self.status_ok = false;
+            }
+            KeyCode::Backspace => {
+                buffer.pop();
+            }
+            KeyCode::Esc => {
+                self.pot_edit = None;
+                self.status = "Edit cancelled".to_string();
+                self.status_ok = true;
+            }
+            KeyCode::Enter => match buffer.parse::<f32>() {
+                Ok(value) => {
+                    self.pot_edit = None;
+                    let (eff, pot) = self.pot_target();
+                    let value = EFFECTS[eff].steps[pot].snap(value.clamp(0.0, 1.0));
+                    self.undoable(|app| app.set_pot(value));
+                    self.status = format!("{} = {}", EFFECTS[eff].pots[pot], EFFECTS[eff].steps[pot].label(value));
+                    self.status_ok = true;
+                }
+                Err(_) => {
+                    self.status = format!("'{}' is not a number", buffer);
+                    self.status_ok = false;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Stores the current effect's pots in A/B slot `slot` (0 = A, 1 = B).
+    fn store_ab_slot(&mut self, slot: usize) {
+        let eff = self.effect_idx;
+        self.ab_slots[eff][slot] = Some(self.pot_values[eff]);
+        self.ab_active[eff] = Some(slot);
+        let p = self.pot_values[eff];
+        self.status = format!(
+            "Stored {} in slot {}: [{:.2}, {:.2}, {:.2}, {:.2}]",
+            EFFECTS[eff].name, AB_NAMES[slot], p[0], p[1], p[2], p[3]
+        );
+        self.status_ok = true;
+    }
+
+    /// Loads whichever A/B slot isn't active into the live pots.
+    fn swap_ab(&mut self) {
+        let eff = self.effect_idx;
+        let [Some(_), Some(_)] = self.ab_slots[eff] else {
+            self.status = format!("Store both slots first - 'a' and 'b' save {}'s pots", EFFECTS[eff].name);
+            self.status_ok = false;
+            return;
+        };
+        let next = match self.ab_active[eff] {
+            Some(0) => 1,
+            _ => 0,
+        };
+        let pots = self.ab_slots[eff][next].unwrap_or(self.pot_values[eff]);
+        self.undoable(|app| app.set_effect_pots(eff, pots));
+        self.ab_active[eff] = Some(next);
+        self.status = format!("Slot {} active for {} - '/' swaps back", AB_NAMES[next], EFFECTS[eff].name);
+        self.status_ok = true;
+    }
+
+    fn reset_pots(&mut self) {
+        let defaults = EFFECTS[self.effect_idx].defaults;
+        self.pot_values[self.effect_idx] = defaults;
+        self.dirty_since_render = true;
+        self.status = format!("Reset {} to defaults", EFFECTS[self.effect_idx].name);
+        self.status_ok = true;
+    }
+
+    /// Instantly recalls the effect's safe pot values: the `[safe_values]`
+    /// entry from config, or the factory defaults when none is set.
+    fn recall_safe_values(&mut self) {
+        let effect = &EFFECTS[self.effect_idx];
+        let safe = self
+            .config
+            .safe_values
+            .get(effect.name)
+            .copied()
+            .unwrap_or(effect.defaults);
+        self.pot_values[self.effect_idx] = safe;
+        self.dirty_since_render = true;
+        self.status = format!(
+            "Safe values for {}: [{:.2}, {:.2}, {:.2}, {:.2}]",
+            effect.name, safe[0], safe[1], safe[2], safe[3]
+        );
+        self.status_ok = true;
+    }
+
+    /// Puts back the pots the current effect last had in a finished render.
+    fn recall_last_played(&mut self) {
+        let effect = &EFFECTS[self.effect_idx];
+        let Some(pots) = self.last_played[self.effect_idx] else {
+            self.status = format!("{} hasn't been played yet this session", effect.name);
+            self.status_ok = false;
+            return;
+        };
+        self.pot_values[self.effect_idx] = pots;
+        self.dirty_since_render = true;
+        self.status = format!(
+            "Last played {}: [{:.2}, {:.2}, {:.2}, {:.2}]",
+            effect.name, pots[0], pots[1], pots[2], pots[3]
+        );
+        self.status_ok = true;
+    }
+
+    /// Randomizes the current effect's pots within each pot's musical range,
+    /// rounded to the 0.01 precision sent to `convert`.
+    fn smart_randomize(&mut self) {
+        let effect = &EFFECTS[self.effect_idx];
+        let mut pots = [0.0; 4];
+        for ((pot, &(lo, hi)), step) in pots.iter_mut().zip(&effect.ranges).zip(effect.steps) {
+            *pot = step.snap((self.rng.range(lo, hi) * 100.0).round() / 100.0);
+        }
+        self.pot_values[self.effect_idx] = pots;
+        self.dirty_since_render = true;
+        self.status = format!(
+            "Smart random {}: [{:.2}, {:.2}, {:.2}, {:.2}]",
+            effect.name, pots[0], pots[1], pots[2], pots[3]
+        );
+        self.status_ok = true;
+    }
+
+    /// Fills the current effect's pots from a fresh seed, keeping the seed
+    /// so the result can be reproduced with 'X'.
+    fn randomize_fresh(&mut self) {
+        let seed = self.rng.next_u64();
+        self.randomize(seed);
+    }
+
+    /// Sets the current effect's pots to `random_pots` from `seed`, then
+    /// renders if `random_autoplay` is on.
+    fn randomize(&mut self, seed: u64) {
+        self.undoable(|app| {
+            let pots = random_pots(&EFFECTS[app.effect_idx], &mut Rng::new(seed));
+            app.set_effect_pots(app.effect_idx, pots);
+            app.random_seed = Some(seed);
+            app.status = format!(
+                "Random {}: [{:.2}, {:.2}, {:.2}, {:.2}] (seed {})",
+                EFFECTS[app.effect_idx].name, pots[0], pots[1], pots[2], pots[3], seed
+            );
+            app.status_ok = true;
+        });
+        if self.config.random_autoplay {
+            self.process_and_play();
+        }
+    }
+
+    /// Steps the monitoring volume. Streaming playback changes at once;
+    /// ffplay playback picks it up on the next pass.
+    fn adjust_volume(&mut self, delta: f32) {
+        self.volume = ((self.volume + delta).clamp(0.0, MAX_VOLUME) * 100.0).round() / 100.0;
+        let live = self.player.as_ref().is_some_and(|p| p.set_volume(self.volume));
+        self.status = format!(
+            "Volume {}{}{}",
+            volume_label(self.volume),
+            if self.volume > 1.0 { " - may clip" } else { "" },
+            if self.player.is_some() && !live { " (from the next play)" } else { "" }
+        );
+        self.status_ok = self.volume <= 1.0;
+    }
+
+    fn adjust_master_mix(&mut self, delta: f32) {
+        self.master_mix = ((self.master_mix + delta).clamp(0.0, 1.0) * 100.0).round() / 100.0;
+        self.status = format!(
+            "Master mix {:.0}% wet{}",
+            self.master_mix * 100.0,
+            if self.waveform_path.is_some() { " (from the next render)" } else { "" }
+        );
+        self.status_ok = true;
+    }
+
+    fn toggle_fades(&mut self) {
+        self.config.fade_enabled = !self.config.fade_enabled;
+        self.status = if self.config.fade_enabled {
+            format!("Fade in/out on ({} ms)", self.config.fade_ms)
+        } else {
+            "Fade in/out off".to_string()
+        };
+        self.status_ok = true;
+    }
+
+    /// True when the input is missing, or was imported this session with a
+    /// different channel selection or layout than the current one.
+    fn needs_import(&self, input_path: &str) -> bool {
+        !std::path::Path::new(input_path).exists()
+            || self.imported_mono.is_some_and(|m| m != self.config.mono_source)
+            || self.input_layout.is_some_and(|l| l != self.config.layout)
+            || self.reimport
+    }
+
+    fn copy_share_string(&mut self) {
+        let patch = presets::share_string(EFFECTS[self.effect_idx].name, &self.pot_values[self.effect_idx]);
+        match clipboard::copy(&patch) {
+            Ok(via) => {
+                self.status = format!("Copied patch ({}): {}", via, patch);
+                self.status_ok = true;
+            }
+            Err(e) => {
+                self.status = format!("Error copying to clipboard: {}", e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Applies a shared `effect:p1,p2,p3,p4` patch as an undoable preset
+    /// load, or reports why it was rejected.
+    fn import_share_string(&mut self, text: &str) {
+        let result = presets::parse_share_string(text).and_then(|(name, pots)| {
+            let eff = effect_index(&name)
+                .filter(|i| self.effects.contains(i))
+                .ok_or_else(|| format!("unknown effect '{}' (available: {})", name, self.effect_list()))?;
+            Ok((eff, pots))
+        });
+        match result {
+            Ok((eff, pots)) => {
+                let preset = NamedPreset { name: "shared patch".to_string(), pots };
+                self.run_action(Action::LoadPreset(eff, preset));
+            }
+            Err(e) => {
+                self.status = format!("Invalid patch: {}", e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    fn effect_list(&self) -> String {
+        self.effects.iter().map(|&i| EFFECTS[i].name).collect::<Vec<_>>().join(", ")
+    }
+
+    fn open_prompt(&mut self, kind: PromptKind) {
+        let buffer = match kind {
+            PromptKind::RandomSeed => self.random_seed.map(|seed| seed.to_string()).unwrap_or_default(),
+            PromptKind::RecordSeconds => self.record_secs.to_string(),
+            PromptKind::Automation => {
+                let (eff, pot) = self.pot_target();
+                match self.automation[eff][pot] {
+                    Some((start, end)) => format!("{:.2},{:.2}", start, end),
+                    None => format!("{:.2},", self.pot_values[eff][pot]),
+                }
+            }
+            _ => String::new(),
+        };
+        self.prompt = Some(Prompt { kind, buffer });
+    }
+
+    fn handle_prompt_key(&mut self, code: KeyCode) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Char(c) => prompt.buffer.push(c),
+            KeyCode::Backspace => {
+                prompt.buffer.pop();
+            }
+            KeyCode::Esc => {
+                self.status = match prompt.kind {
+                    PromptKind::ImportPatch => "Import cancelled",
+                    PromptKind::SavePreset => "Save cancelled",
+                    PromptKind::RandomSeed => "Randomize cancelled",
+                    PromptKind::RecordSeconds => "Recording cancelled",
+                    PromptKind::Automation => "Ramp unchanged",
+                }
+                .to_string();
+                self.status_ok = true;
+                self.prompt = None;
+            }
+            KeyCode::Enter => {
+                let Some(Prompt { kind, buffer }) = self.prompt.take() else {
+                    return;
+                };
+                match kind {
+                    PromptKind::ImportPatch => self.import_share_string(&buffer),
+                    PromptKind::RandomSeed => match buffer.trim().parse::<u64>() {
+                        Ok(seed) => self.randomize(seed),
+                        Err(_) => {
+                            self.status = format!("'{}' isn't a seed - enter a whole number", buffer.trim());
+                            self.status_ok = false;
+                        }
+                    },
+                    PromptKind::RecordSeconds => match buffer.trim().parse::<u64>() {
+                        Ok(secs) if (1..=MAX_RECORD_SECS).contains(&secs) => self.start_capture(secs),
+                        _ => {
+                            self.status = format!("Recording length must be 1-{} seconds", MAX_RECORD_SECS);
+                            self.status_ok = false;
+                        }
+                    },
+                    PromptKind::Automation => self.set_automation(&buffer),
+                    PromptKind::SavePreset => {
+                        let name = buffer.trim();
+                        if name.is_empty() {
+                            self.status = "Preset name can't be empty".to_string();
+                            self.status_ok = false;
+                            return;
+                        }
+                        match self.save_preset(name) {
+                            Ok(()) => {
+                                self.saved_pots[self.effect_idx] = self.pot_values[self.effect_idx];
+                                self.user_preset = Some(name.to_string());
+                                self.status = format!("Saved preset '{}' for {}", name, EFFECTS[self.effect_idx].name);
+                                self.status_ok = true;
+                            }
+                            Err(e) => {
+                                self.status = format!("Error saving preset: {}", e);
+                                self.status_ok = false;
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets or, for empty input, clears the selected pot's ramp from a
+    /// "start,end" pair of 0.0-1.0 values.
+    fn set_automation(&mut self, input: &str) {
+        let (eff, pot) = self.pot_target();
+        let name = EFFECTS[eff].pots[pot];
+        if input.trim().is_empty() {
+            self.automation[eff][pot] = None;
+            self.status = format!("{} ramp cleared", name);
+            self.status_ok = true;
+            return;
+        }
+        let parsed: Vec<Option<f32>> = input.split(',').map(|v| v.trim().parse().ok()).collect();
+        match parsed[..] {
+            [Some(start), Some(end)] if (0.0..=1.0).contains(&start) && (0.0..=1.0).contains(&end) => {
+                self.automation[eff][pot] = Some((start, end));
+                self.dirty_since_render = true;
+                self.status = format!("{} ramps {:.2} -> {:.2} across the clip", name, start, end);
+                self.status_ok = true;
+            }
+            _ => {
+                self.status = "A ramp is two values from 0.0 to 1.0, like 0.2,0.8".to_string();
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Stores the current effect and its pots in `presets.toml` as `name`,
+    /// replacing any preset already saved under that name.
+    fn save_preset(&self, name: &str) -> Result<(), String> {
+        let path = self.presets_path().ok_or("no config directory")?;
+        presets::save_user_preset(&path, name, EFFECTS[self.effect_idx].name, &self.pot_values[self.effect_idx])
+    }
+
+    /// Loads the preset saved as `name`, switching to its effect.
+    fn load_preset(&mut self, name: &str) {
+        let result = self.presets_path()
+            .ok_or_else(|| "no config directory".to_string())
+            .and_then(|path| presets::load_user_presets(&path))
+            .and_then(|saved| {
+                let preset = saved
+                    .into_iter()
+                    .find(|p| p.name == name)
+                    .ok_or_else(|| format!("no preset named '{}'", name))?;
+                let eff = effect_index(&preset.effect)
+                    .filter(|i| self.effects.contains(i))
+                    .ok_or_else(|| format!("unknown effect '{}'", preset.effect))?;
+                Ok((eff, preset))
+            });
+        match result {
+            Ok((eff, preset)) => {
+                self.user_preset = Some(preset.name.clone());
+                self.run_action(Action::LoadPreset(eff, NamedPreset { name: preset.name, pots: preset.pots }));
+            }
+            Err(e) => {
+                self.status = format!("Error loading preset: {}", e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Loads the next saved preset for the current effect, wrapping around.
+    fn cycle_preset(&mut self) {
+        let effect = EFFECTS[self.effect_idx].name;
+        let saved = match self.presets_path().map(|path| presets::load_user_presets(&path)) {
+            Some(Ok(saved)) => saved,
+            Some(Err(e)) => {
+                self.status = format!("Error reading presets.toml: {}", e);
+                self.status_ok = false;
+                return;
+            }
+            None => Vec::new(),
+        };
+        let names: Vec<String> = saved.into_iter().filter(|p| p.effect == effect).map(|p| p.name).collect();
+        let next = match &self.user_preset {
+            Some(current) => names.iter().find(|n| *n > current).or(names.first()),
+            None => names.first(),
+        };
+        match next.cloned() {
+            Some(name) => self.load_preset(&name),
+            None => {
+                self.status = format!("No saved presets for {} - Ctrl+S saves one", effect);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Re-reads the length and size of the current input for the info line.
+    fn refresh_input_info(&mut self) {
+        let (_, input_path, _) = &self.work_paths();
+        let rate = self.config.format.rate;
+        let channels = self.input_layout.unwrap_or(self.config.layout).channels as u16;
+        self.input_info = info::input_info(input_path, rate, channels);
+    }
+
+    /// The info line: the input's length and size, and where it came from.
+    fn input_info_line(&self) -> String {
+        let source = self.import_source().map(|s| {
+            std::path::Path::new(&s).file_name().map_or(s.clone(), |n| n.to_string_lossy().into_owned())
+        });
+        let info = &self.input_info;
+        if !info.exists || self.reimport {
+            return match source {
+                Some(source) => format!("{}Input: {} (imported on the next render)", self.playlist_prefix(), source),
+                None => "Input: none - drop an audio file or pass one on the command line".to_string(),
+            };
+        }
+        let layout = self.input_layout.unwrap_or(self.config.layout);
+        let name = match &self.input_file {
+            None => format!("input.raw{}", source.map(|s| format!(" (from {})", s)).unwrap_or_default()),
+            Some(path) if !self.temp_files.contains(path) => {
+                path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned())
+            }
+            Some(_) => source.map_or("recording".to_string(), |s| format!("{} (imported)", s)),
+        };
+        format!(
+            "{}Input: {} | {}.{:02} | {} frames {} | {:.1} MB",
+            self.playlist_prefix(),
+            name,
+            format_clock(info.duration),
+            info.duration.subsec_millis() / 10,
+            info.frames,
+            layout.name,
+            info.bytes as f64 / 1_000_000.0
+        )
+    }
+
+    /// "File 3/12 | " while a playlist is loaded.
+    fn playlist_prefix(&self) -> String {
+        self.playlist.as_ref().map(|p| format!("{} | ", p.label())).unwrap_or_default()
+    }
+
+    /// The file `input.raw` is imported from: a dropped file, else one
+    /// found in the working directory.
+    fn import_source(&self) -> Option<String> {
+        self.source.clone().or_else(|| self.discovered.clone())
+    }
+
+    /// What `process` would render from right now. The no-input banner
+    /// asks the same question, so the two can't disagree.
+    fn find_input_source(&self) -> InputSource {
+        let (_, input_path, _) = &self.work_paths();
+        if !self.needs_import(input_path) {
+            InputSource::Raw(input_path.to_string())
+        } else if let Some(path) = self.import_source() {
+            InputSource::Import(path)
+        } else {
+            InputSource::None
+        }
+    }
+
+    /// Why there's nothing to render, for `InputSource::None`.
+    fn no_input_message(&self) -> String {
+        match self.input_layout {
+            Some(layout) if layout != self.config.layout => format!(
+                "input.raw is {} and there's no source file to re-import as {}",
+                layout.name, self.config.layout.name
+            ),
+            _ => format!("No input.raw or audio file ({}) found", DISCOVER_EXTENSIONS.join("/")),
+        }
+    }
+
+    /// Handles a bracketed paste. A path to an audio file becomes the new
+    /// input: `.raw` files are copied as-is, anything else is imported.
+    fn handle_paste(&mut self, text: &str) {
+        if let Some(prompt) = self.prompt.as_mut() {
+            prompt.buffer.push_str(text.trim());
+            return;
+        }
+        if self.pot_edit.is_some() {
+            for c in text.trim().chars() {
+                self.handle_pot_edit_key(KeyCode::Char(c));
+            }
+            return;
+        }
+        // Patches have no path separators, so malformed ones still get a
+        // patch error rather than "not an audio file". A Windows path has
+        // a colon too, and may have commas.
+        let trimmed = text.trim();
+        let is_path = pasted_path(text).is_some_and(|path| is_drive_path(&path.to_string_lossy()) || path.exists());
+        if !is_path && trimmed.contains(':') && trimmed.contains(',') && !trimmed.contains('/') {
+            self.import_share_string(text);
+            return;
+        }
+        let Some(path) = pasted_path(text) else {
+            return;
+        };
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+        if !path.is_file() || !AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+            self.status = format!("Pasted text is not an audio file: {}", path.display());
+            self.status_ok = false;
+            return;
+        }
+        if self.job.is_some() {
+            self.status = "Wait for the current render to finish before loading a new input".to_string();
+            self.status_ok = false;
+            return;
+        }
+
+        let display = path.display().to_string();
+        if ext == "raw" {
+            self.set_input_path(Some(path.clone()));
+            self.source = None;
+            self.reimport = false;
+            self.imported_mono = None;
+            self.input_layout = Some(self.config.layout);
+        } else {
+            // Until the import finishes, the next render would import it.
+            self.source = Some(display.clone());
+            self.reimport = true;
+            self.start_import(&display);
+            return;
+        }
+        self.dirty_since_render = true;
+        self.status = format!("Loaded input from {} - press 'p' to process", display);
+        self.status_ok = true;
+    }
+
+    /// Imports `source` into a new scratch file on the render worker, so
+    /// the UI keeps drawing; `poll_render` makes it the input when done.
+    fn start_import(&mut self, source: &str) {
+        if !self.has_ffmpeg {
+            self.status = "Error: importing audio needs 'ffmpeg' - install it and press F5".to_string();
+            self.status_ok = false;
+            return;
+        }
+        let (convert_path, _, output_path) = self.work_paths();
+        let target = self.temp_file("input");
+        let mono = self.config.mono_source;
+        let import = import_command(source, &target, mono, self.config.layout, self.config.format);
+        let name = Path::new(source).file_name().map_or(source.into(), |n| n.to_string_lossy());
+        self.status = format!("Importing {}...", name);
+        self.status_ok = true;
+        self.job = Some(RenderJob::spawn(self.status.clone(), RenderRequest {
+            spec: PendingRender {
+                effect_name: String::new(),
+                pots: [0.0; 4],
+                rack: Vec::new(),
+                chain: Vec::new(),
+                part_paths: Vec::new(),
+                input_path: target,
+                output_path,
+                started: Instant::now(),
+            },
+            jobs: Vec::new(),
+            chained: false,
+            segments: Vec::new(),
+            convert_path,
+            layout: self.config.layout,
+            import: Some((import, mono)),
+            timeout: None,
+            post: PostProcess { fade_len: 0, fade_curve: self.config.fade_curve },
+            master_mix: 1.0,
+            difference: false,
+            split: false,
+        }));
+    }
+
+    /// Uses the file given on the command line as the source. Raw files
+    /// are read where they are; anything else is imported by the first
+    /// render.
+    fn set_input_file(&mut self, path: &std::path::Path) {
+        let display = path.display().to_string();
+        if !path.is_file() {
+            self.status = format!("Error: input file not found: {}", display);
+            self.status_ok = false;
+            return;
+        }
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+        if !AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+            self.status = format!("Error: {} is not a supported audio file ({})", display, AUDIO_EXTENSIONS.join(", "));
+            self.status_ok = false;
+            return;
+        }
+        if ext == "raw" {
+            self.set_input_path(Some(path.to_path_buf()));
+            self.source = None;
+            self.reimport = false;
+            self.imported_mono = None;
+            self.input_layout = Some(self.config.layout);
+        } else {
+            self.source = Some(display.clone());
+            self.reimport = true;
+        }
+        self.refresh_input_info();
+        self.status = format!("Input: {} - press 'p' to process", display);
+        self.status_ok = true;
+    }
+
+    fn cycle_mono_source(&mut self) {
+        self.config.mono_source = self.config.mono_source.next();
+        self.dirty_since_render = true;
+        self.status = format!(
+            "Stereo import: {} (applies on the next import)",
+            self.config.mono_source.label()
+        );
+        self.status_ok = true;
+    }
+
+    /// Switches between mono and stereo processing. The input is imported
+    /// again with the new layout on the next render.
+    fn toggle_channels(&mut self) {
+        let previous = self.config.layout;
+        self.config.layout = if previous.channels == 1 { ChannelLayout::STEREO } else { ChannelLayout::MONO };
+        // Whatever input.raw holds now was made for the old layout.
+        self.input_layout.get_or_insert(previous);
+        self.dirty_since_render = true;
+        self.status = format!("Channels: {} (input is re-imported on the next render)", self.config.layout.name);
+        self.status_ok = true;
+    }
+
+    fn toggle_split_monitor(&mut self) {
+        self.split_monitor = !self.split_monitor;
+        self.status = if self.split_monitor {
+            "A/B stereo split on - dry input left, wet output right".to_string()
+        } else {
+            "A/B stereo split off".to_string()
+        };
+        self.status_ok = true;
+    }
+
+    fn toggle_meter_scale(&mut self) {
+        self.config.meter_scale = self.config.meter_scale.toggle();
+        self.status = format!("Meter scale: {}", self.config.meter_scale.label());
+        self.status_ok = true;
+    }
+
+    fn toggle_difference_mode(&mut self) {
+        self.difference_mode = !self.difference_mode;
+        self.status = if self.difference_mode {
+            "Difference mode on - next render plays output minus input".to_string()
+        } else {
+            "Difference mode off".to_string()
+        };
+        self.status_ok = true;
+    }
+
+    /// Starts playing `path`. Returns a warning when the chosen output
+    /// device has gone away since it was picked, in which case playback
+    /// falls back to the default output.
+    fn start_player(&mut self, path: &str, layout: ChannelLayout) -> Option<String> {
+        self.stop_audio();
+        let mut warning = None;
+        if let Some(device) = self.output_device.take() {
+            if devices::list().iter().any(|d| d.name == device.name) {
+                self.output_device = Some(device);
+            } else {
+                warning = Some(format!("output device '{}' is gone - using the default", device.name));
+            }
+        }
+        let rate = self.config.format.rate.to_string();
+        let raw = RawFormat { format: self.config.format.format, rate: &rate, layout: layout.name };
+        let config = &self.config;
+        self.player = Player::spawn(
+            path,
+            &raw,
+            config.resampler,
+            config.monitor_rate,
+            config.backend,
+            self.volume,
+            self.output_device.as_ref(),
+        )
+        .ok();
+        if self.player.is_some() {
+            let now = Instant::now();
+            let rate = self.config.format.rate as u64;
+            let duration = audio::sample_count(path)
+                .ok()
+                .map(|samples| Duration::from_secs_f64(samples as f64 / (rate * layout.channels as u64) as f64));
+            self.playback_started = Some(now);
+            self.playing = Some(Playing { path: path.to_string(), layout, pass_started: now, duration });
+        }
+        warning
+    }
+
+    fn cycle_theme(&mut self) {
+        self.config.theme = self.config.theme.next();
+        match config::save_theme(self.config.theme) {
+            Ok(()) => {
+                self.status = format!("Theme: {}", self.config.theme.name);
+                self.status_ok = true;
+            }
+            Err(e) => {
+                self.status = format!("Theme: {} (not saved: {})", self.config.theme.name, e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Loop mode keeps `-autoexit` and respawns the player once a pass
+    /// ends. Turning it off lets the current pass play to the end.
+    fn toggle_loop(&mut self) {
+        self.loop_playback = !self.loop_playback;
+        self.status = if self.loop_playback {
+            "Loop playback on".to_string()
+        } else {
+            "Loop playback off - the current pass plays to the end".to_string()
+        };
+        self.status_ok = true;
+    }
+
+    fn toggle_verbose(&mut self) {
+        cmdlog::set_enabled(!cmdlog::enabled());
+        self.status = if cmdlog::enabled() {
+            format!("Verbose on - command lines go to {}", cmdlog::path())
+        } else {
+            "Verbose off".to_string()
+        };
+        self.status_ok = true;
+    }
+
+    fn toggle_spectrum_view(&mut self) {
+        self.spectrum_view = !self.spectrum_view;
+        self.status = if self.spectrum_view { "Spectrum view" } else { "Waveform view" }.to_string();
+        self.status_ok = true;
+    }
+
+    fn toggle_focus(&mut self) {
+        self.focused_pot = !self.focused_pot;
+        self.status = if self.focused_pot {
+            "Focused on one pot - Left/Right adjust, Tab switches, Esc or 'f' shows all four"
+        } else {
+            "All pots"
+        }
+        .to_string();
+        self.status_ok = true;
+    }
+
+    /// Ctrl+E. Like 'p', turning it on asks first if it would replace an
+    /// `output.raw` that hasn't been exported; after that it doesn't ask.
+    fn toggle_live_mode(&mut self) {
+        if !self.live_mode {
+            let (_, _, output_path) = &self.work_paths();
+            if needs_overwrite_confirm(Path::new(output_path).exists(), !self.dirty_output) {
+                self.confirm = Some(Confirm::LiveMode);
+                return;
+            }
+        }
+        self.set_live_mode(!self.live_mode);
+    }
+
+    fn set_live_mode(&mut self, on: bool) {
+        self.live_mode = on;
+        self.live_seen = (self.effect_idx, self.pot_values.clone());
+        // Catch up with edits made before live mode went on.
+        self.last_change = (self.live_mode && self.dirty_since_render).then(Instant::now);
+        self.status = if self.live_mode {
+            "Live mode on - pot changes re-render and replay, replacing output.raw without asking".to_string()
+        } else {
+            "Live mode off".to_string()
+        };
+        self.status_ok = true;
+    }
+
+    /// Live mode: notices the effect or pots changing, dropping any render
+    /// of the old values, and renders once they have been still for
+    /// `LIVE_DEBOUNCE`.
+    fn check_live(&mut self) {
+        if !self.live_mode {
+            return;
+        }
+        if self.live_seen.0 != self.effect_idx || self.live_seen.1 != self.pot_values {
+            self.live_seen = (self.effect_idx, self.pot_values.clone());
+            self.last_change = Some(Instant::now());
+            self.cancel_render();
+        }
+        let settled = self.last_change.is_some_and(|t| t.elapsed() >= LIVE_DEBOUNCE);
+        // Hold off while a question or editor is open, and while a
+        // recording needs the input.
+        let busy = self.prompt.is_some() || self.confirm.is_some() || self.pot_edit.is_some() || self.capture.is_some();
+        if settled && !busy {
+            self.last_change = None;
+            self.process(true);
+        }
+    }
+
+    fn toggle_limiter(&mut self) {
+        self.config.limiter = !self.config.limiter;
+        self.status = if self.config.limiter {
+            "Limiter on - renders end in a brickwall compress stage".to_string()
+        } else {
+            "Limiter off".to_string()
+        };
+        self.status_ok = true;
+    }
+
+    /// Notices the player exiting on its own: restarts the pass when
+    /// looping, without resetting the auto-stop timer, and otherwise clears
+    /// the playback clock.
+    fn check_player(&mut self) {
+        if !self.player.as_mut().is_some_and(Player::finished) {
+            return;
+        }
+        if !self.loop_playback {
+            self.stop_audio();
+            return;
+        }
+        let Some(playing) = self.playing.as_ref() else {
+            return;
+        };
+        let pass = playing.pass_started.elapsed();
+        let early = pass < playing.duration.map_or(MIN_LOOP_PASS, |d| MIN_LOOP_PASS.min(d / 2));
+        if early || self.player.as_mut().is_some_and(Player::failed) {
+            // Respawning would just fail again, every tick.
+            self.stop_audio();
+            self.status = "Error: playback ended early - stopped looping".to_string();
+            self.status_ok = false;
+            return;
+        }
+        let Some(playing) = self.playing.as_mut() else {
+            return;
+        };
+        let config = &self.config;
+        let rate = config.format.rate.to_string();
+        let raw = RawFormat { format: config.format.format, rate: &rate, layout: playing.layout.name };
+        let device = self.output_device.as_ref();
+        match Player::spawn(&playing.path, &raw, config.resampler, config.monitor_rate, config.backend, self.volume, device) {
+            Ok(player) => {
+                self.player = Some(player);
+                playing.pass_started = Instant::now();
+            }
+            Err(e) => {
+                self.stop_audio();
+                self.status = format!("Error restarting loop: {}", e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Feeds the meter. Streaming playback reports the levels it just
+    /// wrote; for ffplay they are estimated from the audio around the
+    /// playhead, going by the time since playback started.
+    fn update_meter(&mut self) {
+        let levels = |samples: Vec<i32>| (meter::peak(&samples), meter::rms(&samples));
+        if let Some(capture) = &self.capture {
+            // The level of what was just recorded, from the end of the file.
+            let window = (self.config.format.rate / 20) as usize * self.config.layout.channels;
+            let end = audio::sample_count(&capture.path).unwrap_or(0);
+            let (peak, rms) = audio::read_window(&capture.path, end.saturating_sub(window as u64), window)
+                .map(levels)
+                .unwrap_or_default();
+            self.meter.update(peak, rms);
+            return;
+        }
+        if let Some((peak, rms)) = self.player.as_ref().and_then(Player::levels) {
+            self.meter.update(peak, rms);
+            return;
+        }
+        let (peak, rms) = match &self.playing {
+            Some(Playing { path, layout, .. }) => {
+                let rate = self.config.format.rate as u64;
+                let window = (rate / 20) as usize * layout.channels;
+                let elapsed = self.playhead().unwrap_or_default();
+                let pos = (elapsed.as_secs_f64() * rate as f64) as u64 * layout.channels as u64;
+                audio::read_window(path, pos.saturating_sub(window as u64), window).map(levels).unwrap_or_default()
+            }
+            _ => (0.0, 0.0),
+        };
+        self.meter.update(peak, rms);
+    }
+
+    /// Whether the meter shows what is actually heard: streaming playback
+    /// or a recording, as opposed to ffplay's estimate.
+    fn meter_live(&self) -> bool {
+        self.capture.is_some() || self.player.as_ref().is_some_and(|p| p.levels().is_some())
+    }
+
+    /// Stops looped playback once it has run for the configured auto-stop period.
+    fn check_auto_stop(&mut self) {
+        let (Some(limit), Some(started)) = (self.config.auto_stop, self.playback_started) else {
+            return;
+        };
+        if self.loop_playback && self.player.is_some() && started.elapsed() >= limit {
+            self.stop_audio();
+            self.status = format!("Playback auto-stopped after {}s", limit.as_secs());
+            self.status_ok = true;
+        }
+    }
+
+    /// Effects that have at least one preset in the loaded banks.
+    fn bank_effects(&self) -> Vec<usize> {
+        self.effects
+            .iter()
+            .copied()
+            .filter(|&i| self.banks.contains_key(EFFECTS[i].name))
+            .collect()
+    }
+
+    fn open_bank_picker(&mut self) {
+        let effects = self.bank_effects();
+        if effects.is_empty() {
+            let dir = self.config.bank_dir().map(|d| d.display().to_string());
+            self.status = format!("No preset banks found in {}", dir.unwrap_or_else(|| "?".to_string()));
+            self.status_ok = false;
+            return;
+        }
+        let mut list_state = ListState::default();
+        list_state.select(Some(effects.iter().position(|&i| i == self.effect_idx).unwrap_or(0)));
+        self.bank_picker = Some(BankPicker { level: PickerLevel::Effects, list_state });
+    }
+
+    fn open_device_picker(&mut self) {
+        let devices = devices::list();
+        if devices.is_empty() {
+            self.status = "No output devices found (tried pactl and aplay -L)".to_string();
+            self.status_ok = false;
+            return;
+        }
+        let current = self.output_device.as_ref().and_then(|c| devices.iter().position(|d| d.name == c.name));
+        if self.output_device.is_some() && current.is_none() {
+            self.output_device = None;
+            self.status = "The chosen output device is gone - back to the default".to_string();
+            self.status_ok = false;
+        }
+        let mut list_state = ListState::default();
+        // Row 0 is the default output.
+        list_state.select(Some(current.map_or(0, |i| i + 1)));
+        self.device_picker = Some(DevicePicker { devices, list_state });
+    }
+
+    fn handle_device_picker_key(&mut self, code: KeyCode) {
+        let Some(picker) = self.device_picker.as_mut() else {
+            return;
+        };
+        let len = picker.devices.len() + 1;
+        let selected = picker.list_state.selected().unwrap_or(0);
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => picker.list_state.select(Some((selected + len - 1) % len)),
+            KeyCode::Down | KeyCode::Char('j') => picker.list_state.select(Some((selected + 1) % len)),
+            KeyCode::Enter => {
+                self.output_device = selected.checked_sub(1).and_then(|i| picker.devices.get(i)).cloned();
+                self.device_picker = None;
+                self.status = match &self.output_device {
+                    Some(device) => format!("Output device: {} - used from the next playback", device.name),
+                    None => "Output device: default".to_string(),
+                };
+                self.status_ok = true;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.device_picker = None,
+            _ => {}
+        }
+    }
+
+    fn picker_len(&self) -> usize {
+        match self.bank_picker.as_ref().map(|p| &p.level) {
+            Some(PickerLevel::Effects) => self.bank_effects().len(),
+            Some(PickerLevel::Presets(eff)) => {
+                self.banks.get(EFFECTS[*eff].name).map_or(0, Vec::len)
+            }
+            None => 0,
+        }
+    }
+
+    fn handle_picker_key(&mut self, code: KeyCode) {
+        let len = self.picker_len();
+        let Some(picker) = self.bank_picker.as_mut() else {
+            return;
+        };
+        let selected = picker.list_state.selected().unwrap_or(0);
+
+        match code {
+            KeyCode::Up | KeyCode::Char('k') if len > 0 => {
+                picker.list_state.select(Some((selected + len - 1) % len));
+            }
+            KeyCode::Down | KeyCode::Char('j') if len > 0 => {
+                picker.list_state.select(Some((selected + 1) % len));
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => match picker.level {
+                PickerLevel::Effects => {
+                    if let Some(&eff) = self.bank_effects().get(selected) {
+                        let picker = self.bank_picker.as_mut().unwrap();
+                        picker.level = PickerLevel::Presets(eff);
+                        picker.list_state.select(Some(0));
+                    }
+                }
+                PickerLevel::Presets(eff) => {
+                    let preset = self.banks.get(EFFECTS[eff].name).and_then(|b| b.get(selected)).cloned();
+                    if let Some(preset) = preset {
+                        self.bank_picker = None;
+                        self.run_action(Action::LoadPreset(eff, preset));
+                    }
+                }
+            },
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                match picker.level {
+                    PickerLevel::Effects => self.bank_picker = None,
+                    PickerLevel::Presets(eff) => {
+                        let pos = self.bank_effects().iter().position(|&i| i == eff);
+                        let picker = self.bank_picker.as_mut().unwrap();
+                        picker.level = PickerLevel::Effects;
+                        picker.list_state.select(pos.or(Some(0)));
+                    }
+                }
+            }
+            KeyCode::Char('q') => self.bank_picker = None,
+            _ => {}
+        }
+    }
+
+    fn load_named_preset(&mut self, effect_idx: usize, preset: &NamedPreset) {
+        self.effect_idx = effect_idx;
+        self.list_state.select(self.effects.iter().position(|&i| i == effect_idx));
+        self.pot_idx = 0;
+        self.pot_values[effect_idx] = preset.pots;
+        self.dirty_since_render = true;
+        self.status = format!("Loaded preset '{}' for {}", preset.name, EFFECTS[effect_idx].name);
+        self.status_ok = true;
+    }
+
+    /// The shell equivalent of what `process_and_play` would run right now,
+    /// including the import when `input.raw` doesn't exist yet.
+    fn current_command_line(&self) -> String {
+        let (convert_path, input_path, output_path) = &self.work_paths();
+        let convert = convert_command(
+            convert_path,
+            EFFECTS[self.effect_idx].name,
+            &self.pot_values[self.effect_idx],
+            self.config.layout.channels,
+        );
+        let mut line = format!(
+            "{} < {} > {}",
+            command_line(&convert),
+            shell_quote(input_path),
+            shell_quote(output_path)
+        );
+        if let InputSource::Import(source_path) = self.find_input_source() {
+            let import = import_command(&source_path, input_path, self.config.mono_source, self.config.layout, self.config.format);
+            line = format!("{} && {}", command_line(&import), line);
+        }
+        line
+    }
+
+    fn copy_command_line(&mut self) {
+        let line = self.current_command_line();
+        match clipboard::copy(&line) {
+            Ok(via) => {
+                self.status = format!("Copied ({}): {}", via, line);
+                self.status_ok = true;
+            }
+            Err(e) => {
+                self.status = format!("Error copying to clipboard: {}", e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Flips playback between the latest render and the one it replaced.
+    fn toggle_previous_render(&mut self) {
+        let (_, _, output_path) = &self.work_paths();
+        let prev_path = prev_output_path(output_path);
+        let (path, label) = if self.playing_prev {
+            (output_path.to_string(), "latest")
+        } else {
+            (prev_path, "previous")
+        };
+
+        if !std::path::Path::new(&path).exists() {
+            self.status = format!("No {} render to play yet", label);
+            self.status_ok = false;
+            return;
+        }
+
+        let warning = self.start_player(&path, self.config.layout);
+        self.playing_prev = !self.playing_prev;
+        self.status = format!("Playing {} render ({})", label, path);
+        self.status_ok = true;
+        self.note_device_warning(warning);
+    }
+
+    /// Opens the folder holding `output.raw` with the configured reveal
+    /// command, without waiting for the file manager.
+    fn reveal_output_folder(&mut self) {
+        let (_, _, output_path) = &self.work_paths();
+        let dir = std::path::Path::new(output_path)
+            .parent()
+            .and_then(|d| d.canonicalize().ok())
+            .unwrap_or_else(|| std::path::PathBuf::from("."));
+        let (program, args) = self.config.reveal_command.split_first().expect("reveal_command is never empty");
+        let spawned = Command::new(program)
+            .args(args)
+            .arg(&dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                // Reap it in the background so it doesn't linger as a zombie.
+                std::thread::spawn(move || child.wait());
+                self.status = format!("Opened {} with {}", dir.display(), program);
+                self.status_ok = true;
+            }
+            Err(e) => {
+                self.status = format!("Error running '{}': {}", program, e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    fn export_spectrogram(&mut self) {
+        let (_, _, output_path) = &self.work_paths();
+        if !std::path::Path::new(output_path).exists() {
+            self.status = "No output.raw yet - press 'p' to render first".to_string();
+            self.status_ok = false;
+            return;
+        }
+        let png_path = output_sibling(output_path, "spectrogram.png");
+        let (width, height) = self.config.spectrogram_size;
+        let layout = self.config.layout;
+        match spectrogram::export(output_path, &png_path, layout, width, height, self.config.spectrogram_colormap) {
+            Ok(()) => {
+                self.status = format!("Wrote spectrogram {} ({}x{})", png_path, width, height);
+                self.status_ok = true;
+            }
+            Err(e) => {
+                self.status = format!("Error writing spectrogram: {}", e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Writes `output.wav` next to `output.raw` for sharing.
+    fn export_wav(&mut self) {
+        let (_, _, output_path) = &self.work_paths();
+        if !std::path::Path::new(output_path).exists() {
+            self.status = "No output.raw yet - press 'p' to render first".to_string();
+            self.status_ok = false;
+            return;
+        }
+        let wav_path = output_sibling(output_path, "wav");
+        let rate = self.config.format.rate;
+        let channels = self.config.layout.channels as u16;
+        let mut dither = self.config.dither.then(|| Rng::new(self.rng.next_u64()));
+        match wav::write_wav(output_path, &wav_path, rate, channels, self.config.output_bits, dither.as_mut()) {
+            Ok(()) => {
+                self.dirty_output = false;
+                self.status = format!("Wrote {}", wav_path);
+                self.status_ok = true;
+            }
+            Err(e) => {
+                self.status = format!("Error writing WAV: {}", e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Position in the current pass: what the streaming backend has
+    /// handed to ffplay, or the time since ffplay was started.
+    fn playhead(&self) -> Option<Duration> {
+        let playing = self.playing.as_ref()?;
+        match self.player.as_ref().and_then(Player::position) {
+            Some(samples) => {
+                let per_sec = self.config.format.rate as f64 * playing.layout.channels as f64;
+                Some(Duration::from_secs_f64(samples as f64 / per_sec))
+            }
+            None => Some(playing.pass_started.elapsed()),
+        }
+    }
+
+    /// "elapsed / total" for the current pass, or just the elapsed time
+    /// when the length is unknown, marked while paused.
+    fn playback_clock(&self) -> Option<String> {
+        let playing = self.playing.as_ref()?;
+        let elapsed = format_clock(self.playhead()?);
+        let clock = match playing.duration {
+            Some(total) => format!("{} / {}", elapsed, format_clock(total)),
+            None => elapsed,
+        };
+        Some(if self.player.as_ref().is_some_and(Player::paused) {
+            format!("paused {}", clock)
+        } else {
+            clock
+        })
+    }
+
+    /// Space: pauses or resumes streaming playback.
+    fn toggle_pause(&mut self) {
+        let Some(player) = self.player.as_ref() else {
+            self.status = "Nothing playing - press 'p' to process and play".to_string();
+            self.status_ok = false;
+            return;
+        };
+        match player.toggle_pause() {
+            Some(paused) => {
+                self.status = if paused { "Playback paused" } else { "Playback resumed" }.to_string();
+                self.status_ok = true;
+            }
+            None => self.transport_unavailable(),
+        }
+    }
+
+    /// '(' and ')': moves streaming playback `secs` back or forward.
+    fn seek_playback(&mut self, secs: f64) {
+        let (Some(player), Some(playing)) = (self.player.as_ref(), self.playing.as_ref()) else {
+            self.status = "Nothing playing - press 'p' to process and play".to_string();
+            self.status_ok = false;
+            return;
+        };
+        let frames = (secs * self.config.format.rate as f64) as i64;
+        if player.seek(frames * playing.layout.channels as i64) {
+            self.status = format!("Seek {:+.0}s", secs);
+            self.status_ok = true;
+        } else {
+            self.transport_unavailable();
+        }
+    }
+
+    fn transport_unavailable(&mut self) {
+        self.status = "Pause and seek need the stream backend (--backend stream); ffplay plays straight through"
+            .to_string();
+        self.status_ok = false;
+    }
+
+    fn stop_audio(&mut self) {
+        if let Some(ref mut player) = self.player {
+            player.stop();
+        }
+        self.player = None;
+        self.playback_started = None;
+        self.playing = None;
+    }
+
+    /// 'R': asks for a length and records, or ends a running recording
+    /// early and keeps what was captured so far.
+    fn record_key(&mut self) {
+        match &mut self.capture {
+            Some(capture) => {
+                // 'q' makes ffmpeg finish the file properly.
+                if let Some(mut stdin) = capture.child.stdin.take() {
+                    let _ = io::Write::write_all(&mut stdin, b"q");
+                }
+                self.status = "Finishing the recording...".to_string();
+                self.status_ok = true;
+            }
+            None => self.open_prompt(PromptKind::RecordSeconds),
+        }
+    }
+
+    /// Records `secs` seconds from the default microphone with ffmpeg into
+    /// a temporary file in the configured rate, format and layout.
+    fn start_capture(&mut self, secs: u64) {
+        self.check_environment();
+        if !self.has_ffmpeg {
+            self.status = "Error: recording needs 'ffmpeg' - install it and press F5".to_string();
+            self.status_ok = false;
+            return;
+        }
+        if self.job.is_some() {
+            self.status = "Wait for the current render to finish before recording".to_string();
+            self.status_ok = false;
+            return;
+        }
+        self.stop_audio();
+        self.record_secs = secs;
+        let path = self.temp_file("rec");
+        let format = self.config.format;
+        let mut cmd = Command::new("ffmpeg");
+        cmd.args(["-y", "-v", "fatal"])
+            .args(mic_input_args())
+            .args(["-t", &secs.to_string(), "-ac", &self.config.layout.channels.to_string()])
+            .args(["-f", format.format, "-ar", &format.rate.to_string(), &path])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        cmdlog::record(&cmd);
+        let spawned = cmd.spawn();
+        match spawned {
+            Ok(child) => {
+                self.capture =
+                    Some(Capture { child, path, started: Instant::now(), length: Duration::from_secs(secs) });
+                self.status = format!("Recording {}s from the microphone ('R' to stop early)", secs);
+                self.status_ok = true;
+            }
+            Err(e) => {
+                self.status = format!("Error starting ffmpeg: {}", e);
+                self.status_ok = false;
+            }
+        }
+    }
+
+    /// Picks up a finished recording and makes it the input.
+    fn check_capture(&mut self) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+        let status = match capture.child.try_wait() {
+            Ok(None) => return,
+            Ok(Some(status)) => status,
+            Err(_) => {
+                self.cancel_capture();
+                return;
+            }
+        };
+        let Some(capture) = self.capture.take() else {
+            return;
+        };
+        let recorded = std::fs::metadata(&capture.path).is_ok_and(|m| m.len() > 0);
+        if !status.success() || !recorded {
+            let _ = std::fs::remove_file(&capture.path);
+            self.status = format!(
+                "Error: recording failed - is a microphone available to ffmpeg '{}'?",
+                mic_input_args().join(" ")
+            );
+            self.status_ok = false;
+            return;
+        }
+        self.set_input_path(Some(PathBuf::from(capture.path)));
+        self.source = None;
+        self.reimport = false;
+        self.imported_mono = None;
+        self.input_layout = Some(self.config.layout);
+        self.dirty_since_render = true;
+        self.refresh_input_info();
+        self.status = format!(
+            "Recorded {} - press 'p' to process",
+            format_clock(self.input_info.duration)
+        );
+        self.status_ok = true;
+    }
+
+    /// Kills a running recording and drops what it captured.
+    fn cancel_capture(&mut self) {
+        if let Some(mut capture) = self.capture.take() {
+            let _ = capture.child.kill();
+            let _ = capture.child.wait();
+            let _ = std::fs::remove_file(&capture.path);
+        }
+    }
+
+    /// The status line while recording: a countdown.
+    fn capture_status(&self) -> Option<String> {
+        let capture = self.capture.as_ref()?;
+        let left = capture.length.saturating_sub(capture.started.elapsed());
+        Some(format!("\u{25cf} REC {} left | {}", format_clock(left + Duration::from_millis(999)), self.status))
+    }
+
+    /// J/K: makes the next (`offset` 1) or previous (-1) playlist file the
+    /// input, wrapping around.
+    fn step_playlist(&mut self, offset: isize) {
+        let Some(next) = self.playlist.as_ref().map(|p| p.index_after(offset)) else {
+            self.status = "No playlist - pass several files or a directory".to_string();
+            self.status_ok = false;
+            return;
+        };
+        if self.job.is_some() || self.capture.is_some() {
+            self.status = "Wait for the current render or recording to finish before switching files".to_string();
+            self.status_ok = false;
+            return;
+        }
+        self.select_playlist_file(next);
+    }
+
+    /// Switches the input to playlist entry `idx`. The current file's
+    /// import is parked first if `input_file` holds it, and a parked import
+    /// of the new file is picked up again instead of importing it again.
+    fn select_playlist_file(&mut self, idx: usize) {
+        let holds_import = !self.reimport && self.input_file.as_ref().is_some_and(|p| self.temp_files.contains(p));
+        let (source, mono, layout) = (self.source.clone(), self.imported_mono, self.input_layout);
+        let Some(playlist) = self.playlist.as_mut() else {
+            return;
+        };
+
+        let current = playlist.current;
+        if holds_import && source == Some(playlist.entries[current].path.display().to_string()) {
+            if let Some(path) = self.input_file.take() {
+                playlist.entries[current].cached = Some(Cached { path, mono, layout });
+            }
+        }
+        playlist.current = idx;
+        let path = playlist.entries[idx].path.clone();
+        let restored = playlist.entries[idx].cached.take().filter(|c| c.path.exists());
+        let position = playlist.label();
+
+        match restored {
+            Some(cached) => {
+                self.set_input_path(Some(cached.path));
+                self.source = Some(path.display().to_string());
+                self.reimport = false;
+                self.imported_mono = cached.mono;
+                self.input_layout = cached.layout;
+                self.dirty_since_render = true;
+                self.refresh_input_info();
+            }
+            None => {
+                self.set_input_file(&path);
+                if !self.status_ok {
+                    return;
+                }
+                self.dirty_since_render = true;
+            }
+        }
+        self.status = format!("{}: {} - press 'p' to process", position, path.display());
+        self.status_ok = true;
+    }
+
+    /// Runs an action bound through the keymap; quitting is left to the
+    /// main loop.
+    fn key_action(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::NextEffect => self.run_action(Action::NextEffect),
+            KeyAction::PrevEffect => self.run_action(Action::PrevEffect),
+            KeyAction::IncPot => self.run_action(Action::IncreasePot),
+            KeyAction::DecPot => self.run_action(Action::DecreasePot),
+            KeyAction::NextPot => self.run_action(Action::NextPot),
+            KeyAction::Play => self.run_action(Action::Process),
+            KeyAction::Reset => self.run_action(Action::Reset),
+            KeyAction::Stop => {
+                self.stop_audio();
+                self.status = "Stopped playback".to_string();
+                self.status_ok = true;
+            }
+            KeyAction::Quit => {}
+        }
+    }
+
+    fn process_and_play(&mut self) {
+        self.process(false);
+    }
+
+    /// The quit key: quits, or first asks when `confirm_quit` is on and
+    /// there are tweaked pots or a render that was never exported.
+    fn request_quit(&mut self) {
+        let modified = modified_effects(&self.pot_values, &self.saved_pots);
+        let unexported = self.dirty_output && self.last_played.iter().any(Option::is_some);
+        if self.config.confirm_quit && (modified > 0 || unexported) {
+            self.confirm = Some(Confirm::Quit { modified, unexported });
+        } else {
+            self.quit = true;
+        }
+    }
+
+    /// 'y'/'n' while a `Confirm` question is showing.
+    fn handle_confirm_key(&mut self, code: KeyCode) {
+        let Some(confirm) = self.confirm else {
+            return;
+        };
+        match (code, confirm) {
+            (KeyCode::Char('y') | KeyCode::Char('Y'), Confirm::Overwrite) => {
+                self.confirm = None;
+                self.process(true);
+            }
+            (KeyCode::Char('y') | KeyCode::Char('Y'), Confirm::LiveMode) => {
+                self.confirm = None;
+                self.set_live_mode(true);
+            }
+            (KeyCode::Char('y') | KeyCode::Char('Y'), Confirm::MakeExecutable) => {
+                self.confirm = None;
+                let (convert_path, _, _) = &self.work_paths();
+                match make_executable(Path::new(convert_path)) {
+                    Ok(()) => self.process(false),
+                    Err(e) => {
+                        self.status = format!("Error: couldn't chmod {}: {}", convert_path, e);
+                        self.status_ok = false;
+                    }
+                }
+            }
+            (code, Confirm::Quit { .. })
+                if matches!(code, KeyCode::Char('y') | KeyCode::Char('Y'))
+                    || self.config.keymap.resolve(code, KeyModifiers::NONE) == Some(KeyAction::Quit) =>
+            {
+                self.confirm = None;
+                self.quit = true;
+            }
+            (KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc, _) => {
+                self.confirm = None;
+                self.status = match confirm {
+                    Confirm::Quit { .. } => "Quit cancelled".to_string(),
+                    Confirm::Overwrite => "Render cancelled - output.raw kept".to_string(),
+                    Confirm::LiveMode => "Live mode off - output.raw kept".to_string(),
+                    Confirm::MakeExecutable => {
+                        let (convert_path, _, _) = &self.work_paths();
+                        format!("Render cancelled - run chmod +x {}", convert_path)
+                    }
+                };
+                self.status_ok = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Renders and plays; unless `overwrite`, first asks before replacing
+    /// an `output.raw` that hasn't been exported.
+    fn process(&mut self, overwrite: bool) {
+        if self.job.is_some() || self.effects.is_empty() {
+            return;
+        }
+        if self.capture.is_some() {
+            self.status = "Wait for the recording to finish before processing".to_string();
+            self.status_ok = false;
+            return;
+        }
+        self.check_environment();
+        if !self.has_ffplay {
+            self.status = "Error: can't play without 'ffplay' - install ffmpeg and press F5".to_string();
+            self.status_ok = false;
+            return;
+        }
+
+        let (convert_path, input_path, output_path) = &self.work_paths();
+
+        let import_from = match self.find_input_source() {
+            InputSource::Raw(_) => None,
+            InputSource::None => {
+                self.status = format!("Error: {}", self.no_input_message());
+                self.status_ok = false;
+                return;
+            }
+            InputSource::Import(source_path) => {
+                if !self.has_ffmpeg {
+                    self.status = "Error: importing audio needs 'ffmpeg' - install it and press F5".to_string();
+                    self.status_ok = false;
+                    return;
+                }
+                Some(source_path)
+            }
+        };
+
+        if !std::path::Path::new(convert_path).exists() {
+            self.status = "Error: 'convert' not found - run 'make convert'".to_string();
+            self.status_ok = false;
+            return;
+        }
+        if !is_executable(Path::new(convert_path)) {
+            self.confirm = Some(Confirm::MakeExecutable);
+            return;
+        }
+        if self.split_monitor && self.config.layout.channels != 1 {
+            self.status = format!("A/B stereo split needs a mono render, not {}", self.config.layout.name);
+            self.status_ok = false;
+            return;
+        }
+
+        let exists = std::path::Path::new(output_path).exists();
+        if !overwrite && needs_overwrite_confirm(exists, !self.dirty_output) {
+            self.confirm = Some(Confirm::Overwrite);
+            return;
+        }
+        if exists {
+            let _ = std::fs::rename(output_path, prev_output_path(output_path));
+        }
+
+        let request = self.render_request(import_from, input_path.clone(), output_path.clone());
+        self.status = render_label(&request);
+        self.status_ok = true;
+        self.job = Some(RenderJob::spawn(self.status.clone(), request));
+    }
+
+    /// Everything the worker needs to render the current effect, rack or
+    /// chain from `input_path` (or an import of `import_from`) into
+    /// `output_path`. `--batch` runs the same request without the TUI.
+    fn render_request(&mut self, import_from: Option<String>, input_path: String, output_path: String) -> RenderRequest {
+        let effect_name = EFFECTS[self.effect_idx].name.to_string();
+        let effect_pots = self.pot_values[self.effect_idx];
+        // Imports go to a new scratch file, which becomes `input_file` once
+        // it's done; a raw file of the user's is never written over.
+        let (input_path, import) = match import_from {
+            Some(source_path) => {
+                let target = self.temp_file("input");
+                let mono = self.config.mono_source;
+                let cmd = import_command(&source_path, &target, mono, self.config.layout, self.config.format);
+                (target, Some((cmd, mono)))
+            }
+            None => (input_path, None),
+        };
+
+        let rack = self.rack.clone();
+        let chain = self.chain.clone();
+        let automated = self.automation[self.effect_idx].iter().any(Option::is_some);
+        // Chains and plain single-effect renders run as a series of stages,
+        // which may end in the limiter.
+        let stages = if !chain.is_empty() {
+            with_limiter(&chain, self.config.limiter)
+        } else if rack.is_empty() && !automated {
+            with_limiter(&[(self.effect_idx, effect_pots)], self.config.limiter)
+        } else {
+            Vec::new()
+        };
+        let jobs: Vec<(String, [f32; 4], String)> = if !stages.is_empty() {
+            stages
+                .iter()
+                .enumerate()
+                .map(|(i, &(effect, pots))| {
+                    let stage = if i + 1 == stages.len() {
+                        output_path.clone()
+                    } else {
+                        self.temp_file(&format!("stage{}", i))
+                    };
+                    (EFFECTS[effect].name.to_string(), pots, stage)
+                })
+                .collect()
+        } else if rack.is_empty() {
+            ramp_pots(&EFFECTS[self.effect_idx], effect_pots, &self.automation[self.effect_idx], AUTOMATION_SEGMENTS)
+                .into_iter()
+                .enumerate()
+                .map(|(i, pots)| (effect_name.clone(), pots, self.temp_file(&format!("seg{}", i))))
+                .collect()
+        } else {
+            rack.iter()
+                .enumerate()
+                .map(|(i, slot)| {
+                    let part = self.temp_file(&format!("par{}", i));
+                    (EFFECTS[slot.effect_idx].name.to_string(), slot.pots, part)
+                })
+                .collect()
+        };
+        // Input slices of an automated render, one per job.
+        let segments: Vec<String> = if stages.is_empty() && rack.is_empty() {
+            (0..jobs.len()).map(|i| self.temp_file(&format!("seg{}.in", i))).collect()
+        } else {
+            Vec::new()
+        };
+        let part_paths = if !stages.is_empty() {
+            jobs[..jobs.len() - 1].iter().map(|(_, _, out)| out.clone()).collect()
+        } else if rack.is_empty() {
+            jobs.iter().map(|(_, _, out)| out.clone()).chain(segments.clone()).collect()
+        } else {
+            jobs.iter().map(|(_, _, out)| out.clone()).collect()
+        };
+
+        let fade_len = if self.config.fade_enabled {
+            let rate = self.config.format.rate as u64;
+            (rate * self.config.fade_ms as u64 / 1000) as usize
+        } else {
+            0
+        };
+        let post = PostProcess { fade_len, fade_curve: self.config.fade_curve };
+
+        RenderRequest {
+            spec: PendingRender {
+                effect_name,
+                pots: effect_pots,
+                rack,
+                chain,
+                part_paths,
+                input_path,
+                output_path,
+                started: Instant::now(),
+            },
+            jobs,
+            chained: !stages.is_empty(),
+            segments,
+            convert_path: self.convert_path.to_string_lossy().into_owned(),
+            layout: self.config.layout,
+            import,
+            timeout: self.config.convert_timeout,
+            post,
+            master_mix: self.master_mix,
+            difference: self.difference_mode,
+            split: self.split_monitor,
+        }
+    }
+
+    /// Picks up the worker's result once per loop tick.
+    fn poll_render(&mut self) {
+        if let Some(job) = &mut self.job {
+            job.poll_progress();
+        }
+        let Some(result) = self.job.as_mut().and_then(RenderJob::try_result) else {
+            return;
+        };
+        self.job = None;
+        self.forget_temp_files(&result.spec.part_paths);
+        if let Some((mono, layout)) = result.imported {
+            self.set_input_path(Some(PathBuf::from(&result.spec.input_path)));
+            self.reimport = false;
+            self.imported_mono = Some(mono);
+            self.input_layout = Some(layout);
+            self.refresh_input_info();
+        }
+        if !matches!(result.outcome, Outcome::Done { .. } | Outcome::Imported) {
+            restore_prev_output(&result.spec.output_path);
+        }
+        match result.outcome {
+            Outcome::Done { play_path, play_layout, mode, envelope } => {
+                self.waveform = Some(envelope);
+                self.finish_render(result.spec, &play_path, play_layout, mode)
+            }
+            Outcome::Imported => {
+                self.dirty_since_render = true;
+                self.status = format!(
+                    "Loaded input from {} - press 'p' to process",
+                    self.source.as_deref().unwrap_or("the pasted file")
+                );
+                self.status_ok = true;
+            }
+            Outcome::Failed(e) => {
+                self.status = if cmdlog::enabled() {
+                    format!("Error: {} (commands in {})", e, cmdlog::path())
+                } else {
+                    format!("Error: {}", e)
+                };
+                self.status_ok = false;
+            }
+            Outcome::TimedOut => {
+                self.status = "Processing timed out".to_string();
+                self.status_ok = false;
+            }
+            Outcome::Cancelled => {
+                self.status = "Cancelled".to_string();
+                self.status_ok = true;
+            }
+        }
+    }
+
+    fn finish_render(&mut self, spec: PendingRender, play_path: &str, layout: ChannelLayout, mode: &str) {
+        let PendingRender { effect_name, pots, rack, chain, output_path, .. } = spec;
+        self.waveform_path = Some(output_path.to_string());
+        self.spectrum = None;
+        self.dirty_output = true;
+        // Edits made while the render was in flight leave the output stale.
+        self.dirty_since_render = if !chain.is_empty() {
+            self.chain != chain
+        } else if rack.is_empty() {
+            EFFECTS[self.effect_idx].name != effect_name || self.pot_values[self.effect_idx] != pots
+        } else {
+            self.rack != rack
+        };
+        if !chain.is_empty() {
+            for &(effect, pots) in &chain {
+                self.last_played[effect] = Some(pots);
+            }
+        } else if rack.is_empty() {
+            if let Some(effect) = effect_index(&effect_name) {
+                self.last_played[effect] = Some(pots);
+            }
+        } else {
+            for slot in &rack {
+                self.last_played[slot.effect_idx] = Some(slot.pots);
+            }
+        }
+        let warning = self.start_player(play_path, layout);
+        self.playing_prev = false;
+
+        self.status = if !chain.is_empty() {
+            format!("Playing{}: chain {}", mode, chain_label(&chain))
+        } else if rack.is_empty() {
+            format!(
+                "Playing{}: {} [{:.2}, {:.2}, {:.2}, {:.2}]",
+                mode, effect_name, pots[0], pots[1], pots[2], pots[3]
+            )
+        } else {
+            format!("Playing{}: parallel {}", mode, rack_label(&rack))
+        };
+        self.status_ok = true;
+        self.note_device_warning(warning);
+    }
+
+    fn note_device_warning(&mut self, warning: Option<String>) {
+        if let Some(warning) = warning {
+            self.status = format!("{} ({})", self.status, warning);
+            self.status_ok = false;
+        }
+    }
+
+    /// The status line while a render is in flight: its progress with a
+    /// spinner, so a long job visibly isn't stuck, then any newer message.
+    fn render_status(&self) -> Option<String> {
+        const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+        let job = self.job.as_ref()?;
+        let elapsed = job.started.elapsed();
+        let progress = match job.progress() {
+            Some(done) => format!("{} {:.0}% {}s", job.label, done * 100.0, elapsed.as_secs()),
+            None => {
+                // Nothing parseable from convert: an indeterminate spinner.
+                let frame = SPINNER[(elapsed.as_millis() / 150) as usize % SPINNER.len()];
+                format!("{} {} {}s", job.label, frame, elapsed.as_secs())
+            }
+        };
+        Some(if self.status == job.label {
+            progress
+        } else {
+            format!("{} | {}", progress, self.status)
+        })
+    }
+
+    /// Asks the in-flight render to stop; the worker kills `convert` and
+    /// removes the partial output before reporting back. A recording is
+    /// cancelled outright.
+    fn cancel_processing(&mut self) {
+        if self.capture.is_some() {
+            self.cancel_capture();
+            self.status = "Recording cancelled".to_string();
+            self.status_ok = true;
+            return;
+        }
+        let Some(job) = &self.job else {
+            self.status = "Nothing is processing".to_string();
+            self.status_ok = false;
+            return;
+        };
+        job.cancel();
+        self.status = "Cancelled".to_string();
+        self.status_ok = true;
+    }
+
+    /// Stops the render, if any, and waits for its children to be killed;
+    /// the render it was replacing goes back in place.
+    fn cancel_render(&mut self) {
+        if let Some(job) = self.job.take() {
+            if let Some(result) = job.cancel_and_wait() {
+                self.forget_temp_files(&result.spec.part_paths);
+            }
+            restore_prev_output(&self.work_paths().2);
+        }
+    }
+
+    /// Appends the current effect and pots as the chain's last stage. The
+    /// same effect can appear more than once.
+    fn chain_append_current(&mut self) {
+        self.chain.push((self.effect_idx, self.pot_values[self.effect_idx]));
+        self.dirty_since_render = true;
+        self.status = if self.rack.is_empty() {
+            format!("Chain: {}", chain_label(&self.chain))
+        } else {
+            format!("Chain: {} (replaces the parallel rack while set)", chain_label(&self.chain))
+        };
+        self.status_ok = true;
+    }
+
+    fn chain_clear(&mut self) {
+        self.chain.clear();
+        self.dirty_since_render = true;
+        self.status = "Chain cleared - single-effect rendering".to_string();
+        self.status_ok = true;
+    }
+
+    /// Adds the current effect and pots to the parallel rack, or updates its
+    /// pots if it's already there.
+    fn rack_add_current(&mut self) {
+        let pots = self.pot_values[self.effect_idx];
+        match self.rack.iter_mut().find(|s| s.effect_idx == self.effect_idx) {
+            Some(slot) => slot.pots = pots,
+            None => self.rack.push(RackSlot { effect_idx: self.effect_idx, pots, gain: 1.0 }),
+        }
+        self.dirty_since_render = true;
+        self.status = format!("Parallel rack: {}", rack_label(&self.rack));
+        self.status_ok = true;
+    }
+
+    fn rack_remove_current(&mut self) {
+        self.rack.retain(|s| s.effect_idx != self.effect_idx);
+        self.dirty_since_render = true;
+        self.status = if self.rack.is_empty() {
+            "Parallel rack empty - single-effect rendering".to_string()
+        } else {
+            format!("Parallel rack: {}", rack_label(&self.rack))
+        };
+        self.status_ok = true;
+    }
+
+    fn rack_adjust_gain(&mut self, delta: f32) {
+        let Some(slot) = self.rack.iter_mut().find(|s| s.effect_idx == self.effect_idx) else {
+            self.status = format!("{} is not in the parallel rack - press 'm' to add it", EFFECTS[self.effect_idx].name);
+            self.status_ok = false;
+            return;
+        };
+        slot.gain = ((slot.gain + delta) * 100.0).round().clamp(0.0, 200.0) / 100.0;
+        self.dirty_since_render = true;
+        self.status = format!("Parallel rack: {}", rack_label(&self.rack));
+        self.status_ok = true;
+    }
+}
+
+impl Drop for App {
+    /// Scratch files go with the app; a render or recording still writing
+    /// one is stopped first so it can't be recreated afterwards.
+    fn drop(&mut self) {
+        self.cancel_render();
+        self.cancel_capture();
+        self.remove_temp_files();
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    let project = project_dir(args.project.as_deref());
+    cmdlog::init(&project.join("audionoise.log").to_string_lossy(), args.verbose);
+
+    let mut startup_warning = None;
+    let mut config = Config::load().unwrap_or_else(|e| {
+        startup_warning = Some(format!("Config error: {}", e));
+        Config::default()
+    });
+    if let Some(timeout) = args.timeout {
+        config.convert_timeout = Some(timeout);
+    }
+    if let Some(rate) = args.rate {
+        config.format.rate = rate;
+    }
+    if let Some(format) = args.format {
+        config.format.format = format;
+    }
+    if let Some(resampler) = args.resampler {
+        config.resampler = resampler;
+    }
+    if let Some(backend) = args.backend {
+        config.backend = backend;
+    }
+    if let Some(layout) = args.layout {
+        config.layout = layout;
+    }
+
+    let (banks, bank_errors) = match config.bank_dir() {
+        Some(dir) => presets::load_banks(&dir),
+        None => (Banks::new(), Vec::new()),
+    };
+    if let Some(first) = bank_errors.first() {
+        startup_warning = Some(format!("Preset bank error: {}", first));
+    }
+
+    let effect_idx = if let Some(name) = &args.effect {
+        match effect_index(name) {
+            Some(idx) => idx,
+            None => {
+                eprintln!("Unknown effect '{}'. Available: {}", name, effect_names());
+                std::process::exit(2);
+            }
+        }
+    } else if let Some(name) = &config.default_effect {
+        effect_index(name).unwrap_or_else(|| {
+            startup_warning = Some(format!("Config: unknown default_effect '{}'", name));
+            0
+        })
+    } else {
+        0
+    };
+
+    if args.batch {
+        let output = args.output.clone().unwrap_or_else(|| PathBuf::from(project_paths(&project).1));
+        // With --stdin the audio is buffered to a temp file first, since
+        // convert's own stdin is the input file.
+        let stdin_path = scratch_path("stdin");
+        let input = if args.stdin {
+            let copied = File::create(&stdin_path).and_then(|mut file| io::copy(&mut io::stdin().lock(), &mut file));
+            if let Err(e) = copied {
+                let _ = std::fs::remove_file(&stdin_path);
+                eprintln!("Error: reading audio from stdin: {}", e);
+                std::process::exit(1);
+            }
+            stdin_path.clone()
+        } else {
+            match &args.input {
+                Some(input) => input.clone(),
+                None => {
+                    eprintln!("--batch needs an input file (--in <file>, INPUT or --stdin)");
+                    std::process::exit(2);
+                }
+            }
+        };
+        let effect = &EFFECTS[effect_idx];
+        let pots = match &args.pots {
+            Some(specs) => cli::resolve_pots(specs, &effect.defaults),
+            None => effect.defaults,
+        };
+        let result = run_batch(&args, config, effect_idx, pots, &input, &output);
+        if args.stdin {
+            let _ = std::fs::remove_file(&stdin_path);
+        }
+        match result {
+            Ok(()) => {
+                println!(
+                    "Wrote {}: {} [{:.2}, {:.2}, {:.2}, {:.2}]",
+                    output.display(),
+                    effect.name,
+                    pots[0],
+                    pots[1],
+                    pots[2],
+                    pots[3]
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let effects: Vec<usize> = match &config.effects {
+        Some(names) => {
+            if let Some(unknown) = names.iter().find(|n| effect_index(n).is_none()) {
+                startup_warning = Some(format!("Config: unknown effect '{}' in effects", unknown));
+            }
+            (0..EFFECTS.len()).filter(|&i| names.iter().any(|n| n == EFFECTS[i].name)).collect()
+        }
+        None => (0..EFFECTS.len()).collect(),
+    };
+    let effect_idx = if effects.contains(&effect_idx) {
+        effect_idx
+    } else {
+        if args.effect.is_some() {
+            startup_warning = Some(format!("Effect '{}' is hidden by the config effects list", EFFECTS[effect_idx].name));
+        }
+        effects.first().copied().unwrap_or(0)
+    };
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    // Dumb terminals, and ones that reject the alternate screen, are drawn
+    // on the normal screen. A fixed viewport avoids the cursor-position
+    // query an inline one needs, which such terminals may never answer.
+    let dumb = std::env::var("TERM").is_ok_and(|t| t == "dumb");
+    let alt_screen = !dumb && execute!(stdout, EnterAlternateScreen).is_ok();
+    let _ = execute!(stdout, EnableBracketedPaste, EnableMouseCapture);
+    // A panic would otherwise leave the terminal raw, capturing the mouse
+    // and on the alternate screen; put it back before the message prints.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let mut stdout = io::stdout();
+        let _ = execute!(stdout, DisableBracketedPaste, DisableMouseCapture, crossterm::cursor::Show);
+        if alt_screen {
+            let _ = execute!(stdout, LeaveAlternateScreen);
+        }
+        default_hook(info);
+    }));
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = if alt_screen {
+        Terminal::new(backend)?
+    } else {
+        let (cols, rows) = crossterm::terminal::size().unwrap_or(FALLBACK_SIZE);
+        let area = Rect::new(0, 0, cols, rows);
+        let mut terminal = Terminal::with_options(backend, TerminalOptions { viewport: Viewport::Fixed(area) })?;
+        terminal.clear()?;
+        terminal
+    };
+
+    let first_run = config::config_path().is_some_and(|p| !p.exists());
+    let pot_map = match &config.pot_map {
+        Some(names) => {
+            let targets: Result<Vec<_>, String> = names.iter().map(|(e, p)| pot_target(e, p)).collect();
+            match targets {
+                Ok(t) => Some([t[0], t[1], t[2], t[3]]),
+                Err(e) => {
+                    startup_warning = Some(format!("Config: pot_map: {}", e));
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let mut macros: [Vec<Action>; MACRO_SLOTS] = Default::default();
+    for (slot, names) in &config.macros {
+        let idx = slot
+            .strip_prefix('f')
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| (1..=MACRO_SLOTS).contains(n));
+        let Some(idx) = idx else {
+            startup_warning = Some(format!("Config: unknown macro slot '{}' (f1-f{})", slot, MACRO_SLOTS));
+            continue;
+        };
+        match names.iter().map(|n| Action::parse(n, &banks)).collect() {
+            Ok(actions) => macros[idx - 1] = actions,
+            Err(e) => startup_warning = Some(format!("Config: macros.{}: {}", slot, e)),
+        }
+    }
+
+    let session_path = config::session_path();
+    let session = match &session_path {
+        Some(path) if !args.no_restore => session::load(path).unwrap_or_else(|e| {
+            startup_warning = Some(format!("Session not restored: {}", e));
+            None
+        }),
+        _ => None,
+    }
+    .map(|mut session| {
+        // Explicit flags win over the saved session.
+        if args.effect.is_some() {
+            session.effect = None;
+        }
+        if args.layout.is_some() {
+            session.layout = None;
+        }
+        session
+    });
+
+    let mut app = App::new(config, args.project.as_deref(), args.convert.as_deref(), effects, effect_idx, banks, session);
+    app.pot_map = pot_map;
+    app.macros = macros;
+    if (first_run || args.tutorial) && !app.effects.is_empty() {
+        app.tutorial = Some(tutorial::Step::Welcome);
+    }
+    if let Some(specs) = &args.pots {
+        let effect_idx = app.effect_idx;
+        app.pot_values[effect_idx] = cli::resolve_pots(specs, &EFFECTS[effect_idx].defaults);
+    }
+    let inputs: Vec<PathBuf> = args.input.iter().chain(&args.queue).cloned().collect();
+    let files = playlist::expand(&inputs, AUDIO_EXTENSIONS);
+    if files.len() > 1 || inputs.iter().any(|p| p.is_dir()) {
+        if files.is_empty() {
+            app.status = format!("Error: no audio files in {}", inputs[0].display());
+            app.status_ok = false;
+        }
+        app.playlist = Playlist::new(files);
+        if app.playlist.is_some() {
+            app.select_playlist_file(0);
+        }
+    } else if let Some(input) = files.first() {
+        app.set_input_file(input);
+    }
+    if let Some(warning) = startup_warning {
+        app.status = warning;
+        app.status_ok = false;
+    }
+
+    let result = run_app(&mut terminal, &mut app);
+    if result.is_err() {
+        app.cancel_render();
+        app.cancel_capture();
+        app.stop_audio();
+    }
+
+    let session_error = session_path.and_then(|path| session::save(&path, &app.session()).err());
+
+    disable_raw_mode()?;
+    let _ = execute!(terminal.backend_mut(), DisableBracketedPaste, DisableMouseCapture);
+    if alt_screen {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    } else {
+        // Leave the last frame on screen and put the prompt below it.
+        let bottom = terminal.get_frame().area().bottom().saturating_sub(1);
+        execute!(terminal.backend_mut(), crossterm::cursor::MoveTo(0, bottom))?;
+        println!();
+    }
+    terminal.show_cursor()?;
+    if let Some(e) = session_error {
+        eprintln!("Error saving session: {}", e);
+    }
+    result?;
+    Ok(())
+}
+
+/// The main loop: draws, polls events and dispatches keys until the user
+/// quits. Errors return early so `main` still restores the terminal.
+fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> io::Result<()> {
+    loop {
+        if app.quit {
+            app.cancel_render();
+            app.cancel_capture();
+            app.stop_audio();
+            break;
+        }
+        app.poll_render();
+        app.check_player();
+        app.check_capture();
+        app.check_auto_stop();
+        app.check_live();
+        app.recheck_environment();
+        app.update_meter();
+        terminal.draw(|f| ui(f, app, app.config.theme))?;
+
+        if event::poll(app.config.poll)? {
+            let event = event::read()?;
+            if let Event::Resize(cols, rows) = event {
+                // Fixed viewports (TERM=dumb) don't follow the terminal on
+                // their own; resizing also clears, so the next frame is whole.
+                terminal.resize(Rect::new(0, 0, cols, rows))?;
+                continue;
+            }
+            if let Event::Paste(text) = &event {
+                if app.tutorial.is_none() && app.bank_picker.is_none() && app.device_picker.is_none() {
+                    app.handle_paste(text);
+                }
+            }
+            if let Event::Mouse(mouse) = event {
+                if !app.show_help
+                    && app.prompt.is_none()
+                    && app.confirm.is_none()
+                    && app.pot_edit.is_none()
+                    && app.tutorial.is_none()
+                    && app.bank_picker.is_none()
+                    && app.device_picker.is_none()
+                {
+                    app.handle_mouse(mouse);
+                }
+            }
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press && app.show_help {
+                    app.show_help = false;
+                } else if key.kind == KeyEventKind::Press && app.prompt.is_some() {
+                    app.handle_prompt_key(key.code);
+                } else if key.kind == KeyEventKind::Press && app.confirm.is_some() {
+                    app.handle_confirm_key(key.code);
+                } else if key.kind == KeyEventKind::Press && app.pot_edit.is_some() {
+                    app.handle_pot_edit_key(key.code);
+                } else if key.kind == KeyEventKind::Press && app.tutorial.is_some() {
+                    app.handle_tutorial_key(key.code);
+                } else if key.kind == KeyEventKind::Press && app.bank_picker.is_some() {
+                    app.handle_picker_key(key.code);
+                } else if key.kind == KeyEventKind::Press && app.device_picker.is_some() {
+                    app.handle_device_picker_key(key.code);
+                } else if key.kind == KeyEventKind::Press
+                    && app.effects.is_empty()
+                    && !matches!(
+                        app.config.keymap.resolve(key.code, key.modifiers),
+                        Some(KeyAction::Quit | KeyAction::Stop)
+                    )
+                {
+                    app.status = NO_EFFECTS.to_string();
+                    app.status_ok = false;
+                } else if key.kind == KeyEventKind::Press {
+                    if let Some(action) = app.config.keymap.resolve(key.code, key.modifiers) {
+                        if action == KeyAction::Quit {
+                            app.request_quit();
+                            continue;
+                        }
+                        app.key_action(action);
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => app.toggle_loop(),
+                        KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => app.toggle_live_mode(),
+                        KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => app.toggle_spectrum_view(),
+                        KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => app.toggle_limiter(),
+                        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => app.toggle_verbose(),
+                        KeyCode::Char(' ') => app.toggle_pause(),
+                        KeyCode::Char('(') => app.seek_playback(-SEEK_SECS),
+                        KeyCode::Char(')') => app.seek_playback(SEEK_SECS),
+                        KeyCode::Left if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                            app.run_action(Action::FineDecreasePot)
+                        }
+                        KeyCode::Right if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                            app.run_action(Action::FineIncreasePot)
+                        }
+                        KeyCode::Char('H') => app.run_action(Action::FineDecreasePot),
+                        KeyCode::Char('L') => app.run_action(Action::FineIncreasePot),
+                        KeyCode::F(9) => app.start_macro_recording(),
+                        KeyCode::F(n @ 1..=4) => app.macro_key(n as usize - 1),
+                        KeyCode::Char('?') => app.show_help = true,
+                        KeyCode::Char('+') | KeyCode::Char('=') => app.adjust_volume(VOLUME_STEP),
+                        KeyCode::Char('-') => app.adjust_volume(-VOLUME_STEP),
+                        KeyCode::Char('{') => app.adjust_master_mix(-MASTER_MIX_STEP),
+                        KeyCode::Char('}') => app.adjust_master_mix(MASTER_MIX_STEP),
+                        KeyCode::Char('x') => app.randomize_fresh(),
+                        KeyCode::Char('X') => app.open_prompt(PromptKind::RandomSeed),
+                        KeyCode::Char('>') => app.chain_append_current(),
+                        KeyCode::Char('<') => app.chain_clear(),
+                        KeyCode::Char(c @ '1'..='9') if key.modifiers.is_empty() => {
+                            let number = c as usize - '0' as usize;
+                            app.undoable(|app| app.jump_to_effect(number))
+                        }
+                        KeyCode::Char('J') => app.step_playlist(1),
+                        KeyCode::Char('K') => app.step_playlist(-1),
+                        KeyCode::PageUp => app.page_effects(false),
+                        KeyCode::PageDown => app.page_effects(true),
+                        KeyCode::Enter => app.start_pot_edit(),
+                        KeyCode::Char('f') => app.toggle_focus(),
+                        KeyCode::Esc if app.focused_pot => app.toggle_focus(),
+                        KeyCode::Home => app.undoable(|app| app.set_pot(0.0)),
+                        KeyCode::End => app.undoable(|app| app.set_pot(1.0)),
+                        KeyCode::Char('u') => app.undo_pots(),
+                        KeyCode::Char('U') => app.undo(),
+                        KeyCode::F(5) => app.refresh_environment(),
+                        KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => app.cycle_theme(),
+                        KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.open_device_picker()
+                        }
+                        KeyCode::Char('t') | KeyCode::Char('T') => app.toggle_mapped_mode(),
+                        KeyCode::Char('@') => app.copy_share_string(),
+                        KeyCode::Char(':') => app.open_prompt(PromptKind::ImportPatch),
+                        KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.open_prompt(PromptKind::SavePreset)
+                        }
+                        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.cycle_preset(),
+                        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo_pots(),
+                        KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo(),
+                        KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => app.reveal_output_folder(),
+                        KeyCode::Char('R') => app.record_key(),
+                        KeyCode::Char('v') | KeyCode::Char('V') => app.toggle_split_monitor(),
+                        KeyCode::Char('d') | KeyCode::Char('D') => app.toggle_difference_mode(),
+                        KeyCode::Char('a') => app.store_ab_slot(0),
+                        KeyCode::Char('A') => app.open_prompt(PromptKind::Automation),
+                        KeyCode::Char('b') => app.store_ab_slot(1),
+                        KeyCode::Char('/') => app.swap_ab(),
+                        KeyCode::Char('B') => app.toggle_meter_scale(),
+                        KeyCode::Char('o') | KeyCode::Char('O') => app.open_bank_picker(),
+                        KeyCode::Char('e') | KeyCode::Char('E') => app.toggle_fades(),
+                        KeyCode::Char('y') | KeyCode::Char('Y') => app.copy_command_line(),
+                        KeyCode::Char('z') | KeyCode::Char('Z') => app.toggle_previous_render(),
+                        KeyCode::Char('i') | KeyCode::Char('I') => app.cycle_mono_source(),
+                        KeyCode::Char('n') | KeyCode::Char('N') => app.run_action(Action::SmartRandomize),
+                        KeyCode::Char('w') | KeyCode::Char('W') => app.export_wav(),
+                        KeyCode::Char('g') | KeyCode::Char('G') => app.export_spectrogram(),
+                        KeyCode::Char('!') => app.run_action(Action::RecallSafe),
+                        KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.run_action(Action::RecallLastPlayed)
+                        }
+                        KeyCode::Char('.') => app.repeat_last_action(),
+                        KeyCode::Char('m') => app.undoable(App::rack_add_current),
+                        KeyCode::Char('M') => app.undoable(App::rack_remove_current),
+                        KeyCode::Char('[') => app.undoable(|app| app.rack_adjust_gain(-0.1)),
+                        KeyCode::Char(']') => app.undoable(|app| app.rack_adjust_gain(0.1)),
+                        KeyCode::Char('c') => app.cancel_processing(),
+                        KeyCode::Char('C') => app.toggle_channels(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rows the pots panel needs besides the effect description: the four pots,
+/// the optional delay gauge and the borders.
+const POTS_ROWS: u16 = 6;
+/// The effect description and the blank line under it.
+const DESC_ROWS: u16 = 2;
+const CONTROLS_ROWS: u16 = 2;
+/// The focused pot's panel when there's room: its name, the large readout,
+/// a three-row bar and the min/max labels.
+const FOCUS_ROWS: u16 = 2 + 1 + 3 + 3 + 1;
+/// Everything else with a fixed height: the margin, title, meter and the
+/// status and info lines.
+const CHROME_ROWS: u16 = 2 + 1 + 1 + 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LayoutMode {
+    /// Every panel at its natural height.
+    Full,
+    /// No effect description or controls line, and a scrolling effects list.
+    Compact,
+    /// Not even the compact layout fits.
+    TooSmall,
+}
+
+/// Picks the layout for a terminal `height` rows tall, with `effects` rows
+/// in the effects list and `extra_pot_rows` (the delay gauge) under the pots.
+fn layout_mode(height: u16, effects: u16, extra_pot_rows: u16) -> LayoutMode {
+    let compact = CHROME_ROWS + POTS_ROWS + extra_pot_rows;
+    if height >= compact + DESC_ROWS + CONTROLS_ROWS + effects + 2 {
+        LayoutMode::Full
+    } else if height >= compact + 3 {
+        LayoutMode::Compact
+    } else {
+        LayoutMode::TooSmall
+    }
+}
+
+fn ui(f: &mut Frame, app: &mut App, theme: &Theme) {
+    let delay_gauge = if app.effects.is_empty() {
+        None
+    } else {
+        delay_gauge_line(&EFFECTS[app.effect_idx], &app.pot_values[app.effect_idx], theme)
+    };
+    let extra_pot_rows = delay_gauge.is_some() as u16;
+    let effects_rows = app.effects.len().max(1) as u16;
+    let height = f.area().height;
+    let mode = layout_mode(height, effects_rows, extra_pot_rows);
+    if mode == LayoutMode::TooSmall {
+        let message = Paragraph::new(format!(
+            "Terminal too small ({} rows) - need at least {}",
+            height,
+            CHROME_ROWS + POTS_ROWS + extra_pot_rows + 3
+        ))
+        .style(Style::default().fg(theme.status_error))
+        .wrap(Wrap { trim: true });
+        f.render_widget(message, f.area());
+        app.pot_hits.clear();
+        return;
+    }
+    let full = mode == LayoutMode::Full;
+    let mut pots_height = POTS_ROWS + extra_pot_rows + if full { DESC_ROWS } else { 0 };
+    if app.focused_pot && !app.effects.is_empty() {
+        // Grow into the waveform's space, or the list's down to one row.
+        let room = if full {
+            height - CHROME_ROWS - CONTROLS_ROWS - (effects_rows + 2)
+        } else {
+            height - CHROME_ROWS - 3
+        };
+        pots_height = pots_height.max(FOCUS_ROWS.min(room));
+    }
+    let effects_height = if full {
+        effects_rows + 2
+    } else {
+        // Whatever the fixed panels leave, down to a single visible row;
+        // the list scrolls to keep the selection in view.
+        (effects_rows + 2).min(height - CHROME_ROWS - pots_height).max(3)
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(effects_height),
+            Constraint::Length(pots_height),
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(if full { CONTROLS_ROWS } else { 0 }),
+            Constraint::Length(2),
+        ])
+        .split(f.area());
+
+    // With nothing to render the title row becomes a banner, so the list
+    // and pots don't look ready to use.
+    let title = if app.find_input_source() == InputSource::None {
+        Paragraph::new(format!(" NO INPUT - {} - drop an audio file or pass one on the command line ", app.no_input_message()))
+            .style(Style::default().fg(theme.status_error).add_modifier(Modifier::BOLD | Modifier::REVERSED))
+    } else {
+        Paragraph::new("=== AUDIONOISE TUI ===").style(Style::default().fg(theme.title).add_modifier(Modifier::BOLD))
+    }
+    .alignment(ratatui::layout::Alignment::Center);
+    f.render_widget(title, chunks[0]);
+
+    let items: Vec<ListItem> = app
+        .effects
+        .iter()
+        .map(|&i| (i, &EFFECTS[i]))
+        .map(|(i, e)| {
+            let style = if i == app.effect_idx {
+                Style::default().fg(theme.selected).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.unselected)
+            };
+            let marker = if i == app.effect_idx { "> " } else { "  " };
+            let rack = app
+                .rack
+                .iter()
+                .find(|s| s.effect_idx == i)
+                .map(|s| format!("  [parallel x{:.2}]", s.gain))
+                .unwrap_or_default();
+            let stages: Vec<String> = app
+                .chain
+                .iter()
+                .enumerate()
+                .filter(|(_, &(effect, _))| effect == i)
+                .map(|(n, _)| (n + 1).to_string())
+                .collect();
+            let chain = if stages.is_empty() { String::new() } else { format!("  [chain #{}]", stages.join(",")) };
+            ListItem::new(format!("{}{}{}{}", marker, e.name.to_uppercase(), rack, chain)).style(style)
+        })
+        .collect();
+
+    let highlight = app.tutorial.and_then(|t| t.region());
+    let region_style = |region: Region| {
+        if highlight == Some(region) {
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        }
+    };
+
+    let mut effects_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(region_style(Region::Effects))
+        .title(format!("EFFECTS - {} - import: {}", app.config.layout.name, app.config.mono_source.label()));
+    if !app.chain.is_empty() {
+        let stages: Vec<String> = app
+            .chain
+            .iter()
+            .enumerate()
+            .map(|(n, &(effect, pots))| {
+                format!("{}. {} {:.2}/{:.2}/{:.2}/{:.2}", n + 1, EFFECTS[effect].name, pots[0], pots[1], pots[2], pots[3])
+            })
+            .collect();
+        effects_block = effects_block.title_bottom(format!("CHAIN: {}", stages.join(" > ")));
+    }
+    let list = List::new(items).block(effects_block);
+    app.effects_height = chunks[1].height.saturating_sub(2) as usize;
+    app.keep_selection_visible();
+    f.render_stateful_widget(list, chunks[1], &mut app.list_state);
+
+    if app.effects.is_empty() {
+        let message = Paragraph::new(NO_EFFECTS)
+            .style(Style::default().fg(theme.accent))
+            .block(Block::default().borders(Borders::ALL).title("POTS"));
+        f.render_widget(message, chunks[2]);
+        app.pot_hits.clear();
+    } else if app.focused_pot {
+        app.pot_hits = render_focused_pot(f, app, theme, chunks[2], region_style(Region::Pots));
+    } else {
+        app.pot_hits = render_pots(f, app, theme, chunks[2], delay_gauge, full, region_style(Region::Pots));
+    }
+
+    render_waveform_panel(f, app, theme, chunks[3]);
+    let estimated = app.playing.is_some() && !app.meter_live();
+    f.render_widget(
+        Paragraph::new(meter_line(&app.meter, app.config.meter_scale, chunks[4].width, estimated, theme)),
+        chunks[4],
+    );
+
+    let controls = Paragraph::new("Up/Down: effect | Tab: pot | Left/Right: value | p: play | s: stop | r: reset | ?: help | q: quit")
+        .style(Style::default().fg(theme.unselected))
+        .alignment(ratatui::layout::Alignment::Center);
+    f.render_widget(controls, chunks[5]);
 
     let status_style = if app.status_ok {
-        Style::default().fg(Color::Green)
+        Style::default().fg(theme.status_ok)
+    } else {
+        Style::default().fg(theme.status_error)
+    };
+    let status_style = if highlight == Some(Region::Status) {
+        status_style.add_modifier(Modifier::REVERSED)
+    } else {
+        status_style
+    };
+    let status = match (&app.prompt, app.confirm) {
+        (Some(prompt), _) => Paragraph::new(format!("{}: {}_", prompt.kind.label(), prompt.buffer))
+            .style(Style::default().fg(theme.accent)),
+        (None, Some(confirm)) => Paragraph::new(confirm.question(&app.work_paths().0)).style(Style::default().fg(theme.accent)),
+        (None, None) => match app.capture_status().or_else(|| app.render_status()) {
+            Some(progress) => Paragraph::new(progress).style(Style::default().fg(theme.accent)),
+            None => {
+                let mut line = app.status.clone();
+                if app.loop_playback {
+                    line.push_str(" [loop]");
+                }
+                if app.config.limiter {
+                    line.push_str(" [limit]");
+                }
+                if app.volume != 1.0 && !app.status.starts_with("Volume") {
+                    line = format!("{} [vol {}]", line, volume_label(app.volume));
+                }
+                if app.master_mix < 1.0 && !app.status.starts_with("Master mix") {
+                    line = format!("{} [mix {:.0}%]", line, app.master_mix * 100.0);
+                }
+                if let Some(clock) = app.playback_clock() {
+                    line = format!("{}  [{}]", line, clock);
+                }
+                Paragraph::new(line).style(status_style)
+            }
+        },
+    };
+    let [status_area, info_area] = Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(chunks[6]);
+    match app.job.as_ref().and_then(RenderJob::progress).filter(|_| app.prompt.is_none() && app.confirm.is_none()) {
+        Some(done) => {
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(theme.accent))
+                .ratio(done.clamp(0.0, 1.0))
+                .label(app.render_status().unwrap_or_default());
+            f.render_widget(gauge, status_area);
+        }
+        None => f.render_widget(status, status_area),
+    }
+    f.render_widget(
+        Paragraph::new(app.input_info_line()).style(Style::default().fg(theme.dim)),
+        info_area,
+    );
+
+    if app.bank_picker.is_some() {
+        render_bank_picker(f, app, theme);
+    }
+
+    if app.device_picker.is_some() {
+        render_device_picker(f, app, theme);
+    }
+
+    if app.show_help {
+        render_help(f, theme, &app.config.keymap);
+    }
+
+    if let Some(step) = app.tutorial {
+        // Keep the highlighted region visible by covering a different one.
+        let area = match step.region() {
+            Some(Region::Effects) => chunks[2],
+            _ => chunks[1],
+        };
+        render_tutorial(f, step, area, theme);
+    }
+}
+
+/// Where a pot was drawn, for mouse hit-testing: its whole row, and the
+/// cells inside the brackets of its bar.
+#[derive(Clone, Copy)]
+struct PotHit {
+    row: Rect,
+    bar: Rect,
+}
+
+/// Draws the pots panel and returns the on-screen rows of the pots that
+/// fit, in pot order.
+fn render_pots(
+    f: &mut Frame,
+    app: &App,
+    theme: &Theme,
+    area: Rect,
+    delay_gauge: Option<Line<'static>>,
+    show_desc: bool,
+    border_style: Style,
+) -> Vec<PotHit> {
+    let effect = &EFFECTS[app.effect_idx];
+    let (title, desc, targets) = match app.pot_map {
+        Some(map) if app.mapped_mode => ("MAPPED".to_string(), "Pots mapped from config pot_map", map),
+        _ => (effect.name.to_uppercase(), effect.desc, [0, 1, 2, 3].map(|p| (app.effect_idx, p))),
+    };
+    
+    let mut pot_lines: Vec<Line> = if show_desc {
+        let hint = if effect.name == "gate" && !app.mapped_mode { GATE_HINT } else { "" };
+        vec![
+            Line::from(Span::styled(desc, Style::default().fg(theme.unselected))),
+            Line::from(Span::styled(hint, Style::default().fg(theme.dim))),
+        ]
+    } else {
+        Vec::new()
+    };
+    let name_width = if app.mapped_mode { 20 } else { 12 };
+    let inner = area.inner(Margin::new(1, 1));
+    let hits = (0..targets.len() as u16)
+        .map(|i| inner.y + pot_lines.len() as u16 + i)
+        .take_while(|&y| y < inner.bottom())
+        .map(|y| PotHit {
+            row: Rect::new(inner.x, y, inner.width, 1),
+            bar: Rect::new(inner.x + name_width + 2, y, app.config.bar_width as u16, 1).intersection(inner),
+        })
+        .collect();
+
+    for (i, &(eff, pot)) in targets.iter().enumerate() {
+        let value = app.pot_values[eff][pot];
+        let step = EFFECTS[eff].steps[pot];
+        let name = if app.mapped_mode {
+            format!("{}.{}", EFFECTS[eff].name, EFFECTS[eff].pots[pot])
+        } else {
+            EFFECTS[eff].pots[pot].to_string()
+        };
+        let selected = i == app.pot_idx;
+        
+        let bar_width = app.config.bar_width;
+        let bar = match &app.pot_edit {
+            Some(buffer) if selected => format!("[{:<width$}]", format!("{}_", buffer), width = bar_width),
+            _ => {
+                let filled = (value * bar_width as f32) as usize;
+                format!("[{}{}]", "#".repeat(filled), "-".repeat(bar_width - filled))
+            }
+        };
+
+        let style = if selected {
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.text)
+        };
+
+        pot_lines.push(Line::from(vec![
+            Span::styled(format!(" {:width$}", name, width = name_width as usize), style),
+            Span::styled(bar, Style::default().fg(if selected { theme.bar_selected } else { theme.bar_unselected })),
+            Span::styled(format!(" {}", step.label(value)), style),
+            Span::styled(
+                match EFFECTS[eff].units[pot] {
+                    Some(_) => format!("  {}", display_pot(&EFFECTS[eff], pot, value)),
+                    None => String::new(),
+                },
+                Style::default().fg(theme.unselected),
+            ),
+            Span::styled(
+                match app.automation[eff][pot] {
+                    Some((start, end)) => format!("  ramp {:.2} -> {:.2}", start, end),
+                    None => String::new(),
+                },
+                Style::default().fg(theme.graph),
+            ),
+        ]));
+    }
+    if !app.mapped_mode {
+        pot_lines.extend(delay_gauge);
+    }
+
+    let freshness = if app.dirty_since_render {
+        Span::styled(" \u{25cf}stale ", Style::default().fg(theme.accent))
+    } else {
+        Span::styled(" \u{2713}current ", Style::default().fg(theme.status_ok))
+    };
+    let mut pots_title = vec![Span::raw(format!("POTS - {}", title))];
+    let effect = &EFFECTS[app.effect_idx];
+    let pots = &app.pot_values[app.effect_idx];
+    if !app.mapped_mode && unsaved_tweak(pots, &effect.defaults, app.last_played[app.effect_idx]) {
+        pots_title.push(Span::styled(" *", Style::default().fg(theme.dim)));
+    }
+    pots_title.push(freshness);
+    if app.live_mode {
+        pots_title.push(Span::styled(
+            " LIVE ",
+            Style::default().fg(theme.status_error).add_modifier(Modifier::BOLD | Modifier::REVERSED),
+        ));
+        pots_title.push(Span::raw(" "));
+    }
+    let slots = app.ab_slots[app.effect_idx];
+    if slots.iter().any(Option::is_some) {
+        // "A/B", with the active slot highlighted and empty ones dimmed.
+        let active = app.ab_active[app.effect_idx];
+        for (i, name) in AB_NAMES.iter().enumerate() {
+            let style = match (slots[i], active == Some(i)) {
+                (Some(_), true) => Style::default().fg(theme.graph).add_modifier(Modifier::REVERSED),
+                (Some(_), false) => Style::default().fg(theme.graph),
+                (None, _) => Style::default().fg(theme.dim),
+            };
+            pots_title.push(Span::raw(if i == 0 { " " } else { "/" }));
+            pots_title.push(Span::styled(*name, style));
+        }
+        pots_title.push(Span::raw(" "));
+    }
+    let pots_title = Line::from(pots_title);
+    let pots_widget = Paragraph::new(pot_lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(pots_title),
+        );
+    f.render_widget(pots_widget, area);
+    hits
+}
+
+/// `text` three rows tall in box-drawing digits. Anything but digits, '.'
+/// and '-' (unit labels, mostly) stays normal size on the middle row.
+fn big_text(text: &str) -> [String; 3] {
+    let mut rows = [String::new(), String::new(), String::new()];
+    for c in text.chars() {
+        let glyph: [&str; 3] = match c {
+            '0' => ["┏━┓", "┃ ┃", "┗━┛"],
+            '1' => ["  ╻", "  ┃", "  ╹"],
+            '2' => ["╺━┓", "┏━┛", "┗━╸"],
+            '3' => ["╺━┓", " ━┫", "╺━┛"],
+            '4' => ["╻ ╻", "┗━┫", "  ╹"],
+            '5' => ["┏━╸", "┗━┓", "╺━┛"],
+            '6' => ["┏━╸", "┣━┓", "┗━┛"],
+            '7' => ["╺━┓", "  ┃", "  ╹"],
+            '8' => ["┏━┓", "┣━┫", "┗━┛"],
+            '9' => ["┏━┓", "┗━┫", "╺━┛"],
+            '.' => [" ", " ", "╹"],
+            '-' => ["   ", "╺━╸", "   "],
+            _ => {
+                rows[0].push(' ');
+                rows[1].push(c);
+                rows[2].push(' ');
+                continue;
+            }
+        };
+        for (row, part) in rows.iter_mut().zip(glyph) {
+            row.push_str(part);
+            row.push(' ');
+        }
+    }
+    rows
+}
+
+/// Draws the selected pot alone across the pots panel, for 'f'. Only its
+/// entry in the returned hits is on screen, so clicks and drags land on it.
+fn render_focused_pot(f: &mut Frame, app: &App, theme: &Theme, area: Rect, border_style: Style) -> Vec<PotHit> {
+    let (eff, pot) = app.pot_target();
+    let effect = &EFFECTS[eff];
+    let value = app.pot_values[eff][pot];
+    let inner = area.inner(Margin::new(1, 1));
+    let accent = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(theme.unselected);
+
+    let readout = match &app.pot_edit {
+        Some(buffer) => format!("{}_", buffer),
+        None => display_pot(effect, pot, value),
+    };
+    let mut header = vec![
+        Span::styled(format!(" {}", effect.pots[pot]), accent),
+        Span::styled(format!("  pot {}/4", app.pot_idx + 1), dim),
+    ];
+    if effect.units[pot].is_some() {
+        header.push(Span::styled(format!("  {}", effect.steps[pot].label(value)), dim));
+    }
+    if let Some((start, end)) = app.automation[eff][pot] {
+        header.push(Span::styled(format!("  ramp {:.2} -> {:.2}", start, end), Style::default().fg(theme.graph)));
+    }
+    // Rows left over after the header and labels go to the readout if it
+    // fits with a bar row to spare, and the rest to the bar.
+    let spare = inner.height.saturating_sub(2);
+    let big = spare >= 4;
+    if !big {
+        header.push(Span::styled(format!("  {}", readout), accent));
+    }
+    let mut lines = vec![Line::from(header)];
+    if big {
+        lines.extend(big_text(&readout).map(|row| Line::from(Span::styled(format!(" {}", row), accent))));
+    }
+
+    let bar_rows = if big { spare - 3 } else { spare.max(1) };
+    let bar_width = inner.width.saturating_sub(2) as usize;
+    let filled = (value.clamp(0.0, 1.0) * bar_width as f32) as usize;
+    let bar_y = inner.y + lines.len() as u16;
+    for _ in 0..bar_rows {
+        lines.push(Line::from(vec![
+            Span::raw(" "),
+            Span::styled("\u{2588}".repeat(filled), Style::default().fg(theme.bar_selected)),
+            Span::styled("\u{2591}".repeat(bar_width - filled), Style::default().fg(theme.bar_unselected)),
+        ]));
+    }
+    let (min, max) = (display_pot(effect, pot, 0.0), display_pot(effect, pot, 1.0));
+    let gap = bar_width.saturating_sub(min.chars().count() + max.chars().count());
+    lines.push(Line::from(Span::styled(format!(" {}{}{}", min, " ".repeat(gap), max), dim)));
+
+    let title = Line::from(vec![
+        Span::raw(format!("POT - {} ", effect.name.to_uppercase())),
+        Span::styled("(f/Esc: all pots) ", Style::default().fg(theme.dim)),
+    ]);
+    let panel = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).border_style(border_style).title(title));
+    f.render_widget(panel, area);
+
+    let bar = Rect::new(inner.x + 1, bar_y, bar_width as u16, bar_rows).intersection(inner);
+    (0..4)
+        .map(|i| if i == app.pot_idx { PotHit { row: bar, bar } } else { PotHit { row: Rect::default(), bar: Rect::default() } })
+        .collect()
+}
+
+fn render_tutorial(f: &mut Frame, step: tutorial::Step, area: Rect, theme: &Theme) {
+    let mut text = vec![
+        Line::from(Span::styled(step.title(), Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+    ];
+    text.extend(step.text().lines().map(Line::from));
+    let popup = Paragraph::new(text)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent))
+                .title(format!("TUTORIAL {}/{} (Esc: skip)", step.number(), tutorial::Step::COUNT)),
+        );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// Longest delay the echo effect's buffer can hold.
+const ECHO_MAX_DELAY_MS: f32 = 1250.0;
+
+/// For the echo effect, a gauge of how much of the delay buffer the
+/// current "Delay" setting uses.
+fn delay_gauge_line(effect: &Effect, pots: &[f32; 4], theme: &Theme) -> Option<Line<'static>> {
+    if effect.name != "echo" {
+        return None;
+    }
+    let idx = effect.pots.iter().position(|&p| p == "Delay")?;
+    let used = pots[idx].clamp(0.0, 1.0);
+    let width = 20;
+    let filled = (used * width as f32) as usize;
+    Some(Line::from(vec![
+        Span::styled(format!(" {:12}", "Buffer"), Style::default().fg(theme.unselected)),
+        Span::styled(
+            format!("[{}{}]", "=".repeat(filled), " ".repeat(width - filled)),
+            Style::default().fg(theme.graph),
+        ),
+        Span::styled(
+            format!(
+                " {:.0}/{:.0} ms used, {:.0} ms free",
+                used * ECHO_MAX_DELAY_MS,
+                ECHO_MAX_DELAY_MS,
+                (1.0 - used) * ECHO_MAX_DELAY_MS
+            ),
+            Style::default().fg(theme.unselected),
+        ),
+    ]))
+}
+
+/// The min/max waveform of the last render, drawn with block characters
+/// when the terminal has room for the panel.
+fn render_waveform_panel(f: &mut Frame, app: &mut App, theme: &Theme, area: Rect) {
+    if area.height < 3 {
+        return;
+    }
+    if app.spectrum_view {
+        render_spectrum_panel(f, app, theme, area);
+        return;
+    }
+    let width = area.width.saturating_sub(2);
+    let rows = area.height - 2;
+    let envelope = app.waveform.as_deref().map(|env| waveform::rescale(env, width)).unwrap_or_default();
+
+    let lines: Vec<Line> = if envelope.is_empty() {
+        vec![Line::from(Span::styled(" No render yet - press 'p'", Style::default().fg(theme.dim)))]
+    } else {
+        (0..rows)
+            .map(|row| {
+                // The band of values this row covers, from +1.0 at the top to -1.0 at the bottom.
+                let top = 1.0 - 2.0 * row as f64 / rows as f64;
+                let bottom = 1.0 - 2.0 * (row + 1) as f64 / rows as f64;
+                let text: String = envelope
+                    .iter()
+                    .map(|&(lo, hi)| {
+                        let (lo, hi) = (lo as f64 / i32::MAX as f64, hi as f64 / i32::MAX as f64);
+                        if hi >= bottom && lo <= top { '\u{2588}' } else { ' ' }
+                    })
+                    .collect();
+                Line::from(Span::styled(text, Style::default().fg(theme.graph)))
+            })
+            .collect()
+    };
+    let panel = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("WAVEFORM - output.raw"));
+    f.render_widget(panel, area);
+}
+
+/// The spectrum of the last render as one bar per column, low to high
+/// frequency on a log scale, heights in dB from `SPECTRUM_FLOOR_DB` up to
+/// full scale.
+fn render_spectrum_panel(f: &mut Frame, app: &mut App, theme: &Theme, area: Rect) {
+    const SPECTRUM_FLOOR_DB: f32 = -90.0;
+    const EIGHTHS: [char; 9] =
+        [' ', '\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}', '\u{2585}', '\u{2586}', '\u{2587}', '\u{2588}'];
+    let width = area.width.saturating_sub(2);
+    let rows = area.height - 2;
+    if let Some(path) = &app.waveform_path {
+        if app.spectrum.as_ref().is_none_or(|(w, _)| *w != width) {
+            app.spectrum = Some((width, spectrum::read_spectrum(path, app.config.layout, width as usize)));
+        }
+    }
+    let bands = app.spectrum.as_ref().map_or(&[][..], |(_, bands)| bands.as_slice());
+
+    let lines: Vec<Line> = if bands.is_empty() {
+        vec![Line::from(Span::styled(" No render yet - press 'p'", Style::default().fg(theme.dim)))]
     } else {
-        Style::default().fg(Color::Red)
+        // Each band's height in eighths of a row.
+        let heights: Vec<usize> = bands
+            .iter()
+            .map(|&m| {
+                let db = 20.0 * m.max(1e-9).log10();
+                let t = (1.0 - db / SPECTRUM_FLOOR_DB).clamp(0.0, 1.0);
+                (t * rows as f32 * 8.0).round() as usize
+            })
+            .collect();
+        (0..rows as usize)
+            .map(|row| {
+                let base = (rows as usize - 1 - row) * 8;
+                let text: String = heights.iter().map(|&h| EIGHTHS[h.saturating_sub(base).min(8)]).collect();
+                Line::from(Span::styled(text, Style::default().fg(theme.graph)))
+            })
+            .collect()
+    };
+    let panel = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("SPECTRUM - output.raw (log frequency)"));
+    f.render_widget(panel, area);
+}
+
+/// The peak bar with a `|` marker at the held peak and the RMS bar side by
+/// side, each followed by its value in the chosen scale, then the clip
+/// indicator. While ffplay plays, the levels are only an estimate from the
+/// file and the whole meter is grayed out.
+fn meter_line(meter: &PeakMeter, scale: MeterScale, width: u16, estimated: bool, theme: &Theme) -> Line<'static> {
+    let gray = |color| if estimated { theme.dim } else { color };
+    let label = " PEAK  ";
+    let rms_label = "  RMS ";
+    let peak_readout = format!(" {:>9}", scale.format(meter.held));
+    let rms_readout = format!(" {:>9}", scale.format(meter.rms));
+    let clip = if meter.clipped() { " CLIP" } else { "     " };
+    let fixed = label.len() + rms_label.len() + peak_readout.len() + rms_readout.len() + clip.len() + 4;
+    let bar_width = ((width as usize).saturating_sub(fixed) / 2).max(1);
+
+    let bar = |level: f32, held: Option<f32>| {
+        let filled = ((scale.position(level) * bar_width as f32) as usize).min(bar_width);
+        let held = held.filter(|&h| h > 0.0).map(|h| ((scale.position(h) * bar_width as f32) as usize).min(bar_width - 1));
+        let mut spans = vec![Span::raw("[")];
+        for i in 0..bar_width {
+            spans.push(if Some(i) == held {
+                Span::styled("|", Style::default().fg(gray(theme.status_error)).add_modifier(Modifier::BOLD))
+            } else if i < filled {
+                Span::styled("#", Style::default().fg(gray(theme.status_ok)))
+            } else {
+                Span::styled("-", Style::default().fg(theme.dim))
+            });
+        }
+        spans.push(Span::raw("]"));
+        spans
+    };
+
+    let mut spans = vec![Span::styled(label, Style::default().fg(gray(theme.text)))];
+    spans.extend(bar(meter.level, Some(meter.held)));
+    spans.push(Span::styled(peak_readout, Style::default().fg(gray(theme.unselected))));
+    spans.push(Span::styled(rms_label, Style::default().fg(gray(theme.text))));
+    spans.extend(bar(meter.rms, None));
+    spans.push(Span::styled(rms_readout, Style::default().fg(gray(theme.unselected))));
+    spans.push(Span::styled(clip, Style::default().fg(theme.status_error).add_modifier(Modifier::BOLD)));
+    Line::from(spans)
+}
+
+fn render_bank_picker(f: &mut Frame, app: &mut App, theme: &Theme) {
+    let (title, items): (String, Vec<ListItem>) = match app.bank_picker.as_ref().map(|p| &p.level) {
+        Some(PickerLevel::Presets(eff)) => {
+            let effect = &EFFECTS[*eff];
+            let items = app
+                .banks
+                .get(effect.name)
+                .map(|bank| {
+                    bank.iter()
+                        .map(|p| {
+                            let pots: Vec<String> = p.pots.iter().map(|v| format!("{:.2}", v)).collect();
+                            ListItem::new(format!("{:16} {}", p.name, pots.join(" ")))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            (format!("PRESETS - {} (Esc: back)", effect.name.to_uppercase()), items)
+        }
+        _ => {
+            let items = app
+                .bank_effects()
+                .into_iter()
+                .map(|i| {
+                    let count = app.banks.get(EFFECTS[i].name).map_or(0, Vec::len);
+                    ListItem::new(format!("{:10} ({})", EFFECTS[i].name.to_uppercase(), count))
+                })
+                .collect();
+            ("PRESET BANKS (Enter: open, Esc: close)".to_string(), items)
+        }
+    };
+
+    let area = centered_rect(60, 60, f.area());
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().fg(theme.selected).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    f.render_widget(Clear, area);
+    if let Some(picker) = app.bank_picker.as_mut() {
+        f.render_stateful_widget(list, area, &mut picker.list_state);
+    }
+}
+
+fn render_device_picker(f: &mut Frame, app: &mut App, theme: &Theme) {
+    let Some(picker) = app.device_picker.as_mut() else {
+        return;
     };
-    let status = Paragraph::new(app.status.as_str()).style(status_style);
-    f.render_widget(status, chunks[4]);
+    let chosen = app.output_device.as_ref().map(|d| d.name.as_str());
+    let mark = |current: bool| if current { "*" } else { " " };
+    let items: Vec<ListItem> = std::iter::once(ListItem::new(format!("{} (default output)", mark(chosen.is_none()))))
+        .chain(picker.devices.iter().map(|d| {
+            let line = format!("{} {}", mark(chosen == Some(d.name.as_str())), d.name);
+            ListItem::new(if d.description.is_empty() { line } else { format!("{}  - {}", line, d.description) })
+        }))
+        .collect();
+
+    let area = centered_rect(70, 60, f.area());
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("OUTPUT DEVICE (Enter: use, Esc: close)"))
+        .highlight_style(Style::default().fg(theme.selected).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut picker.list_state);
+}
+
+/// The key binding overlay: `HELP` groups packed into three columns.
+fn render_help(f: &mut Frame, theme: &Theme, keymap: &keymap::Keymap) {
+    let area = centered_rect(96, 90, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.title))
+        .title("KEYS (any key to close)");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Keys moved by a `[keys]` table get their own group, so the fixed
+    // list above stays accurate for everything else.
+    let mut groups: Vec<(&str, Vec<(String, &str)>)> = HELP
+        .iter()
+        .map(|(group, keys)| (*group, keys.iter().map(|(key, what)| (key.to_string(), *what)).collect()))
+        .collect();
+    let remapped = keymap.overrides();
+    if !remapped.is_empty() {
+        groups.push(("Remapped ([keys])", remapped));
+    }
+
+    const COLUMNS: usize = 3;
+    let total: usize = groups.iter().map(|(_, keys)| keys.len() + 2).sum();
+    let per_column = total.div_ceil(COLUMNS);
+    let mut columns: Vec<Vec<Line>> = vec![Vec::new(); COLUMNS];
+    let mut col = 0;
+    for (group, keys) in groups {
+        if !columns[col].is_empty() && columns[col].len() + keys.len() + 1 > per_column && col + 1 < COLUMNS {
+            col += 1;
+        }
+        if !columns[col].is_empty() {
+            columns[col].push(Line::from(""));
+        }
+        columns[col].push(Line::from(Span::styled(
+            group,
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        )));
+        for (key, what) in keys {
+            columns[col].push(Line::from(vec![
+                Span::styled(format!(" {:17}", key), Style::default().fg(theme.selected)),
+                Span::raw(what),
+            ]));
+        }
+    }
+
+    let areas = Layout::horizontal([Constraint::Ratio(1, COLUMNS as u32); COLUMNS]).split(inner);
+    for (lines, area) in columns.into_iter().zip(areas.iter()) {
+        f.render_widget(Paragraph::new(lines), *area);
+    }
+}
+
+/// A rect centered in `area` taking the given percentages of its size.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_mode_follows_the_terminal_height() {
+        // 7 effects, no delay gauge: 12 fixed rows for the compact layout,
+        // plus the description, controls and the whole list for the full one.
+        assert_eq!(layout_mode(50, 7, 0), LayoutMode::Full);
+        assert_eq!(layout_mode(25, 7, 0), LayoutMode::Full);
+        assert_eq!(layout_mode(24, 7, 0), LayoutMode::Compact);
+        assert_eq!(layout_mode(15, 7, 0), LayoutMode::Compact);
+        assert_eq!(layout_mode(14, 7, 0), LayoutMode::TooSmall);
+        assert_eq!(layout_mode(0, 7, 0), LayoutMode::TooSmall);
+        // The echo delay gauge takes one more row in either layout.
+        assert_eq!(layout_mode(25, 7, 1), LayoutMode::Compact);
+        assert_eq!(layout_mode(15, 7, 1), LayoutMode::TooSmall);
+    }
+
+    #[test]
+    fn discovery_prefers_extensions_then_names() {
+        let dir = std::env::temp_dir().join(format!("audionoise-discover-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested.mp3")).unwrap();
+        assert_eq!(discover_audio_file(&dir), None);
+
+        for name in ["notes.txt", "input.raw", "output.wav", "take2.FLAC", "take1.ogg", "b.wav", "a.wav"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        assert_eq!(discover_audio_file(&dir), Some(dir.join("a.wav")));
+
+        std::fs::write(dir.join("BassForLinus.mp3"), b"").unwrap();
+        assert_eq!(discover_audio_file(&dir), Some(dir.join("BassForLinus.mp3")));
+
+        for name in ["BassForLinus.mp3", "a.wav", "b.wav"] {
+            std::fs::remove_file(dir.join(name)).unwrap();
+        }
+        assert_eq!(discover_audio_file(&dir), Some(dir.join("take2.FLAC")));
+        assert_eq!(discover_audio_file(&dir.join("missing")), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stream_descriptions_give_the_channel_count() {
+        let line = |desc| format!("  Stream #0:0: Audio: {}", desc);
+        assert_eq!(stream_channels(&line("mp3 (mp3float), 44100 Hz, stereo, fltp, 128 kb/s")), Some(2));
+        assert_eq!(stream_channels(&line("pcm_s16le ([1][0][0][0] / 0x0001), 48000 Hz, mono, s16, 768 kb/s")), Some(1));
+        assert_eq!(stream_channels(&line("flac, 96000 Hz, 5.1(side), s32 (24 bit)")), Some(6));
+        assert_eq!(stream_channels(&line("pcm_s24le, 48000 Hz, 3 channels, s32")), Some(3));
+        assert_eq!(stream_channels(&line("opus, 48000 Hz")), None);
+        assert_eq!(MonoSource::Left.ffmpeg_args(Some(1)), ["-ac", "1"]);
+        assert_eq!(MonoSource::Right.ffmpeg_args(Some(2)), ["-af", "pan=mono|c0=FR"]);
+    }
+
+    #[test]
+    fn pasted_paths_are_unescaped_unless_windows() {
+        let pasted = |text| pasted_path(text).unwrap().to_string_lossy().into_owned();
+        assert_eq!(pasted("'/no/such/my\\ clip.mp3'"), "/no/such/my clip.mp3");
+        assert_eq!(pasted("file:///no/such/a%20b.mp3"), "/no/such/a b.mp3");
+        assert_eq!(pasted("\"C:\\Music\\take 1, 2.mp3\""), "C:\\Music\\take 1, 2.mp3");
+        assert_eq!(pasted("D:/clips/a.wav"), "D:/clips/a.wav");
+        assert!(!is_drive_path("flanger:0.50,0.50,0.50,0.50"));
+        assert!(pasted_path("  \n ").is_none());
+    }
+
+    #[test]
+    fn every_effect_has_four_labelled_pots() {
+        for (i, effect) in EFFECTS.iter().enumerate() {
+            assert_eq!(effect.pots.len(), 4, "{}", effect.name);
+            for pot in effect.pots {
+                assert!(!pot.trim().is_empty(), "{} has an empty pot label", effect.name);
+            }
+            assert_eq!(effect.name, effect.name.to_lowercase(), "effect names are lowercase");
+            assert!(!EFFECTS[..i].iter().any(|e| e.name == effect.name), "two '{}' effects", effect.name);
+        }
+    }
+
+    #[test]
+    fn gate_and_distort_label_their_pots() {
+        let gate = &EFFECTS[effect_index("gate").unwrap()];
+        assert_eq!(gate.pots, ["Threshold", "Attack", "Release", "Range"]);
+
+        let distort = &EFFECTS[effect_index("distort").unwrap()];
+        assert_eq!(distort.pots, ["Drive", "Tone", "Level", "Mix"]);
+        assert!(distort.defaults[2] <= 0.3, "distort starts quiet");
+    }
+
+    #[test]
+    fn effect_defaults_are_in_range_and_pot_labels_unique() {
+        for effect in EFFECTS {
+            for (pot, value) in effect.pots.iter().zip(effect.defaults) {
+                assert!((0.0..=1.0).contains(&value), "{}.{} defaults to {}", effect.name, pot, value);
+            }
+            for (i, pot) in effect.pots.iter().enumerate() {
+                assert!(!effect.pots[..i].contains(pot), "{} has two '{}' pots", effect.name, pot);
+            }
+        }
+    }
+
+    #[test]
+    fn limiter_is_appended_once_and_only_when_enabled() {
+        let flanger = effect_index("flanger").unwrap();
+        let compress = effect_index("compress").unwrap();
+        let chain = [(flanger, [0.5; 4])];
+        assert_eq!(with_limiter(&chain, false), chain);
+        assert_eq!(with_limiter(&chain, true), [(flanger, [0.5; 4]), (compress, LIMITER_POTS)]);
+
+        // A compressor the user placed, anywhere in the chain, stands in.
+        let own = [(compress, [0.7; 4]), (flanger, [0.5; 4])];
+        assert_eq!(with_limiter(&own, true), own);
+
+        // Distortion is limited either way, unless a compressor follows it.
+        let distort = effect_index("distort").unwrap();
+        let driven = [(distort, [0.9; 4])];
+        assert_eq!(with_limiter(&driven, false), [(distort, [0.9; 4]), (compress, LIMITER_POTS)]);
+        assert_eq!(with_limiter(&driven, true), [(distort, [0.9; 4]), (compress, LIMITER_POTS)]);
+        let before = [(compress, [0.7; 4]), (distort, [0.9; 4])];
+        assert_eq!(with_limiter(&before, true), [(compress, [0.7; 4]), (distort, [0.9; 4]), (compress, LIMITER_POTS)]);
+        let after = [(distort, [0.9; 4]), (compress, [0.7; 4])];
+        assert_eq!(with_limiter(&after, false), after);
+    }
+
+    #[test]
+    fn only_unexported_output_needs_confirmation() {
+        assert!(needs_overwrite_confirm(true, false));
+        assert!(!needs_overwrite_confirm(true, true));
+        assert!(!needs_overwrite_confirm(false, false));
+        assert!(!needs_overwrite_confirm(false, true));
+    }
+
+    #[test]
+    fn reverb_passes_its_pots_to_convert() {
+        let reverb = effect_index("reverb").expect("reverb is in EFFECTS");
+        let cmd = convert_command("./convert", EFFECTS[reverb].name, &EFFECTS[reverb].defaults, 1);
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(args, ["reverb", "0.50", "0.40", "0.50", "0.30"]);
+    }
+
+    #[test]
+    fn display_units_map_the_pot_range() {
+        let echo = &EFFECTS[effect_index("echo").unwrap()];
+        assert_eq!(display_pot(echo, 0, 0.0), "0 ms");
+        assert_eq!(display_pot(echo, 0, 0.5), "625 ms");
+        assert_eq!(display_pot(echo, 0, 1.0), "1250 ms");
+
+        let flanger = &EFFECTS[effect_index("flanger").unwrap()];
+        assert_eq!(display_pot(flanger, 1, 0.0), "0.10 Hz");
+        assert_eq!(display_pot(flanger, 1, 0.5), "5.05 Hz");
+        assert_eq!(display_pot(flanger, 1, 1.0), "10.00 Hz");
+        assert_eq!(display_pot(flanger, 3, 0.5), "50%");
+
+        let lowpass = &EFFECTS[effect_index("lowpass").unwrap()];
+        assert_eq!(display_pot(lowpass, 0, 0.0), "20.0 Hz");
+        assert_eq!(display_pot(lowpass, 0, 1.0), "20000 Hz");
+
+        let gate = &EFFECTS[effect_index("gate").unwrap()];
+        assert_eq!(display_pot(gate, 0, 0.5), "-40.0 dB");
+        let distort = &EFFECTS[effect_index("distort").unwrap()];
+        assert_eq!(display_pot(distort, 0, 0.5), "20.0 dB");
+
+        // Pots without units keep the normal readout.
+        assert_eq!(display_pot(flanger, 0, 0.5), "0.50");
+        let phaser = &EFFECTS[effect_index("phaser").unwrap()];
+        assert_eq!(display_pot(phaser, 2, 1.0), "8");
+    }
+
+    #[test]
+    fn cutoff_maps_logarithmically() {
+        let cutoff = CUTOFF_HZ.unwrap();
+        // Every tenth of the travel is the same frequency ratio, so the
+        // middle of the pot is the geometric mean, and 1 kHz just past it.
+        let middle = cutoff.map(0.5);
+        assert!((middle - 632.5).abs() < 1.0, "{}", middle);
+        assert!(cutoff.map(0.55) < 1000.0 && cutoff.map(0.6) > 1000.0);
+        let ratio = cutoff.map(0.3) / cutoff.map(0.2);
+        assert!((ratio - cutoff.map(0.8) / cutoff.map(0.7)).abs() < 1e-3);
+        assert_eq!(cutoff.map(-1.0), 20.0);
+    }
+
+    #[test]
+    fn random_pots_are_reproducible_from_the_seed() {
+        for effect in EFFECTS {
+            let pots = random_pots(effect, &mut Rng::new(42));
+            assert_eq!(pots, random_pots(effect, &mut Rng::new(42)));
+            for (pot, step) in pots.into_iter().zip(effect.steps) {
+                assert!((0.0..=1.0).contains(&pot), "{} pot {}", effect.name, pot);
+                let grid = match step {
+                    PotStep::Continuous(_) => (pot * 100.0).round() / 100.0,
+                    PotStep::Integer(..) => step.snap(pot),
+                };
+                assert_eq!(pot, grid, "{} pot {} is off its grid", effect.name, pot);
+            }
+        }
+        assert_ne!(random_pots(&EFFECTS[0], &mut Rng::new(1)), random_pots(&EFFECTS[0], &mut Rng::new(2)));
+    }
+
+    #[test]
+    fn set_pot_snaps_integer_pots() {
+        let phaser = effect_index("phaser").unwrap();
+        let mut app = App::new(Config::default(), None, None, vec![phaser], phaser, Banks::new(), None);
+        app.pot_idx = 2;
+        app.set_pot(0.3);
+        assert_eq!(app.pot_values[phaser][2], PotStep::Integer(2, 8).snap(0.3));
+        assert_eq!(EFFECTS[phaser].steps[2].label(app.pot_values[phaser][2]), "4");
+        app.pot_idx = 0;
+        app.set_pot(0.3);
+        assert_eq!(app.pot_values[phaser][0], 0.3);
+    }
+
+    #[test]
+    fn selection_stays_inside_a_small_effects_window() {
+        let mut app = App::new(Config::default(), None, None, (0..EFFECTS.len()).collect(), 0, Banks::new(), None);
+        app.effects_height = 3;
+        let visible = |app: &App| {
+            let (offset, selected) = (app.list_state.offset(), app.list_state.selected().unwrap());
+            (offset..offset + app.effects_height).contains(&selected)
+        };
+
+        app.prev_effect();
+        assert_eq!(app.list_state.selected(), Some(EFFECTS.len() - 1));
+        assert!(visible(&app));
+
+        app.next_effect();
+        assert_eq!(app.list_state.selected(), Some(0));
+        assert!(visible(&app));
+
+        app.page_effects(true);
+        assert_eq!(app.list_state.selected(), Some(3));
+        assert!(visible(&app));
+        for _ in 0..EFFECTS.len() / 3 {
+            app.page_effects(true);
+        }
+        assert_eq!(app.list_state.selected(), Some(EFFECTS.len() - 1));
+        assert!(visible(&app));
+    }
+
+    #[test]
+    fn tweaks_are_marked_until_they_match_defaults_or_last_played() {
+        let defaults = [0.5; 4];
+        assert!(!unsaved_tweak(&defaults, &defaults, None));
+        assert!(unsaved_tweak(&[0.6, 0.5, 0.5, 0.5], &defaults, None));
+        assert!(!unsaved_tweak(&[0.6, 0.5, 0.5, 0.5], &defaults, Some([0.6, 0.5, 0.5, 0.5])));
+        assert!(unsaved_tweak(&[0.7, 0.5, 0.5, 0.5], &defaults, Some([0.6, 0.5, 0.5, 0.5])));
+        assert!(!unsaved_tweak(&defaults, &defaults, Some([0.6, 0.5, 0.5, 0.5])));
+    }
+
+    #[test]
+    fn quitting_asks_only_with_unsaved_changes() {
+        assert_eq!(modified_effects(&[[0.5; 4], [0.1; 4]], &[[0.5; 4], [0.1; 4]]), 0);
+        assert_eq!(modified_effects(&[[0.5; 4], [0.2; 4], [0.3; 4]], &[[0.5; 4], [0.1; 4], [0.4; 4]]), 2);
+
+        let mut app = App::new(Config::default(), None, None, (0..EFFECTS.len()).collect(), 0, Banks::new(), None);
+        app.request_quit();
+        assert!(app.quit && app.confirm.is_none());
+
+        app.quit = false;
+        app.pot_values[1][0] = 0.99;
+        app.pot_values[2][0] = 0.99;
+        app.request_quit();
+        assert_eq!(app.confirm, Some(Confirm::Quit { modified: 2, unexported: false }));
+        assert!(app.confirm.unwrap().question("").contains("2 effects modified"));
+        app.handle_confirm_key(KeyCode::Esc);
+        assert!(!app.quit && app.confirm.is_none());
+        app.request_quit();
+        app.handle_confirm_key(KeyCode::Char('q'));
+        assert!(app.quit);
+
+        app.quit = false;
+        app.config.confirm_quit = false;
+        app.request_quit();
+        assert!(app.quit && app.confirm.is_none());
+    }
+
+    #[test]
+    fn temp_files_are_removed_on_cleanup_and_drop() {
+        let mut app = App::new(Config::default(), None, None, (0..EFFECTS.len()).collect(), 0, Banks::new(), None);
+        let paths = [app.temp_file("input"), app.temp_file("stage0")];
+        assert_ne!(paths[0], paths[1]);
+        for path in &paths {
+            std::fs::write(path, [0u8; 8]).unwrap();
+        }
+        app.remove_temp_files();
+        assert!(paths.iter().all(|path| !Path::new(path).exists()));
+        assert!(app.temp_files.is_empty());
+
+        // An import in use as the input goes with the app too.
+        let import = app.temp_file("input");
+        std::fs::write(&import, [0u8; 8]).unwrap();
+        app.set_input_path(Some(PathBuf::from(&import)));
+        assert_eq!(app.work_paths().1, import);
+        drop(app);
+        assert!(!Path::new(&import).exists());
+    }
+
+    #[test]
+    fn finished_renders_stop_tracking_their_stage_files() {
+        let mut app = App::new(Config::default(), None, None, (0..EFFECTS.len()).collect(), 0, Banks::new(), None);
+        app.convert_path = PathBuf::from("/nonexistent/convert");
+        app.chain = vec![(0, EFFECTS[0].defaults), (1, EFFECTS[1].defaults)];
+        let output = scratch_path("chain-out").to_string_lossy().into_owned();
+        let request = app.render_request(None, "input.raw".to_string(), output);
+        assert!(!app.temp_files.is_empty());
+        app.job = Some(RenderJob::spawn(String::new(), request));
+        while app.job.is_some() {
+            app.poll_render();
+        }
+        assert!(app.temp_files.is_empty());
+    }
+
+    #[test]
+    fn a_failed_render_puts_the_previous_output_back() {
+        let dir = std::env::temp_dir().join(format!("audionoise-restore-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("output.raw").to_string_lossy().into_owned();
+        let prev = prev_output_path(&output);
+        std::fs::write(&prev, [1u8; 8]).unwrap();
+        restore_prev_output(&output);
+        assert_eq!(std::fs::read(&output).unwrap(), [1u8; 8]);
+        assert!(!Path::new(&prev).exists());
+
+        // An output that's there wasn't moved aside, so it stays.
+        std::fs::write(&prev, [2u8; 8]).unwrap();
+        restore_prev_output(&output);
+        assert_eq!(std::fs::read(&output).unwrap(), [1u8; 8]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn last_played_values_are_recalled_apart_from_reset() {
+        let mut app = App::new(Config::default(), None, None, (0..EFFECTS.len()).collect(), 0, Banks::new(), None);
+        let defaults = EFFECTS[0].defaults;
+        app.run_action(Action::RecallLastPlayed);
+        assert_eq!(app.pot_values[0], defaults);
+        assert!(!app.status_ok);
+
+        app.last_played[0] = Some([0.1, 0.2, 0.3, 0.4]);
+        app.run_action(Action::Reset);
+        assert_eq!(app.pot_values[0], defaults);
+        app.run_action(Action::RecallLastPlayed);
+        assert_eq!(app.pot_values[0], [0.1, 0.2, 0.3, 0.4]);
+        // Undo goes back to the defaults the recall replaced.
+        app.undo();
+        assert_eq!(app.pot_values[0], defaults);
+    }
 }
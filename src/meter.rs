@@ -0,0 +1,167 @@
+//! Level metering for the playback meter.
+
+/// Loop ticks the held peak stays put before it starts to fall.
+const HOLD_TICKS: u32 = 15;
+/// Per-tick fall of the held peak marker once the hold expires.
+const HELD_DECAY: f32 = 0.02;
+/// Per-tick fall of the level bars, so they drop smoothly between frames.
+const LEVEL_DECAY: f32 = 0.08;
+/// Loop ticks the clip indicator stays lit after a full-scale peak.
+const CLIP_TICKS: u32 = 30;
+
+/// Peak and RMS level bars with a peak-hold marker, all in 0.0..=1.0 of
+/// full scale, and a clip indicator.
+#[derive(Clone, Debug, Default)]
+pub struct PeakMeter {
+    pub level: f32,
+    pub rms: f32,
+    pub held: f32,
+    hold_left: u32,
+    clip_left: u32,
+}
+
+impl PeakMeter {
+    /// Feeds the peak and RMS measured since the last tick. A peak at or
+    /// past full scale lights the clip indicator.
+    pub fn update(&mut self, peak: f32, rms: f32) {
+        if peak >= 1.0 {
+            self.clip_left = CLIP_TICKS;
+        } else {
+            self.clip_left = self.clip_left.saturating_sub(1);
+        }
+        let peak = peak.clamp(0.0, 1.0);
+        self.level = peak.max(self.level - LEVEL_DECAY);
+        self.rms = rms.clamp(0.0, 1.0).max(self.rms - LEVEL_DECAY);
+
+        if peak >= self.held {
+            self.held = peak;
+            self.hold_left = HOLD_TICKS;
+        } else if self.hold_left > 0 {
+            self.hold_left -= 1;
+        } else {
+            self.held = (self.held - HELD_DECAY).max(self.level);
+        }
+    }
+
+    pub fn clipped(&self) -> bool {
+        self.clip_left > 0
+    }
+}
+
+/// Peak absolute sample value as a fraction of full scale.
+pub fn peak(samples: &[i32]) -> f32 {
+    samples
+        .iter()
+        .map(|s| s.unsigned_abs())
+        .max()
+        .map_or(0.0, |p| p as f32 / i32::MAX as f32)
+}
+
+/// Root-mean-square sample value as a fraction of full scale.
+pub fn rms(samples: &[i32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|&s| (s as f64 / i32::MAX as f64).powi(2)).sum();
+    (sum / samples.len() as f64).sqrt() as f32
+}
+
+/// Bottom of the dBFS meter scale; anything quieter reads as silence.
+const DB_FLOOR: f32 = -60.0;
+
+/// Whether levels are shown as a fraction of full scale or in dBFS.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MeterScale {
+    Linear,
+    Db,
+}
+
+impl MeterScale {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Self::Linear),
+            "db" | "dbfs" => Some(Self::Db),
+            _ => None,
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Linear => Self::Db,
+            Self::Db => Self::Linear,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Db => "dBFS",
+        }
+    }
+
+    /// Where a linear `level` sits along a meter bar, in 0.0..=1.0.
+    pub fn position(self, level: f32) -> f32 {
+        match self {
+            Self::Linear => level.clamp(0.0, 1.0),
+            Self::Db => ((samples_to_db(level) - DB_FLOOR) / -DB_FLOOR).clamp(0.0, 1.0),
+        }
+    }
+
+    /// A linear `level` as text in this scale.
+    pub fn format(self, level: f32) -> String {
+        match self {
+            Self::Linear => format!("{:.2}", level),
+            Self::Db if level <= 0.0 => "-inf dB".to_string(),
+            Self::Db => format!("{:.1} dB", samples_to_db(level)),
+        }
+    }
+}
+
+/// A linear fraction of full scale, such as a peak or RMS level, in dBFS.
+pub fn samples_to_db(level: f32) -> f32 {
+    20.0 * level.max(f32::MIN_POSITIVE).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn samples_to_db_at_known_amplitudes() {
+        assert_eq!(samples_to_db(1.0), 0.0);
+        assert!(close(samples_to_db(0.5), -6.02));
+        assert!(close(samples_to_db(0.1), -20.0));
+        assert!(close(samples_to_db(0.001), -60.0));
+        // A full-scale sine's RMS sits 3 dB under its peak.
+        assert!(close(samples_to_db(std::f32::consts::FRAC_1_SQRT_2), -3.01));
+        assert!(samples_to_db(0.0) < DB_FLOOR);
+    }
+
+    #[test]
+    fn rms_of_a_sine_and_a_square() {
+        let sine: Vec<i32> = (0..4800)
+            .map(|i| ((i as f64 * std::f64::consts::TAU / 48.0).sin() * i32::MAX as f64) as i32)
+            .collect();
+        assert!(close(rms(&sine), std::f32::consts::FRAC_1_SQRT_2));
+        let square: Vec<i32> = (0..100).map(|i| if i % 2 == 0 { i32::MAX / 2 } else { -i32::MAX / 2 }).collect();
+        assert!(close(rms(&square), 0.5));
+        assert_eq!(rms(&[]), 0.0);
+    }
+
+    #[test]
+    fn full_scale_peak_lights_the_clip_indicator() {
+        let mut meter = PeakMeter::default();
+        meter.update(0.9, 0.5);
+        assert!(!meter.clipped());
+        meter.update(1.0, 0.7);
+        assert!(meter.clipped());
+        for _ in 0..CLIP_TICKS {
+            meter.update(0.2, 0.1);
+        }
+        assert!(!meter.clipped());
+    }
+}
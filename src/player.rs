@@ -0,0 +1,332 @@
+//! Spawning and stopping the external monitoring player.
+
+use std::io::{self, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::audio::{self, SampleReader, CHUNK_SAMPLES};
+use crate::devices::Device;
+use crate::{cmdlog, dsp};
+
+/// How monitoring audio reaches the sound card.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// ffplay opens and decodes the render file itself.
+    Ffplay,
+    /// The file is read and converted to f32 in-process and streamed to
+    /// ffplay's stdin, which then only acts as the audio output.
+    Stream,
+}
+
+impl Backend {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ffplay" => Some(Self::Ffplay),
+            "stream" => Some(Self::Stream),
+            _ => None,
+        }
+    }
+}
+
+/// How playback is resampled to the monitoring rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resampler {
+    /// Let ffplay resample internally.
+    Player,
+    /// Pre-resample with ffmpeg's default swresample engine.
+    Swr,
+    /// Pre-resample with libsoxr (requires an ffmpeg built with it).
+    Soxr,
+}
+
+impl Resampler {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ffplay" | "player" => Some(Self::Player),
+            "swr" => Some(Self::Swr),
+            "soxr" => Some(Self::Soxr),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Player => "ffplay",
+            Self::Swr => "swr",
+            Self::Soxr => "soxr",
+        }
+    }
+}
+
+/// Raw stream description shared by every process in the monitor pipeline.
+pub struct RawFormat<'a> {
+    pub format: &'a str,
+    pub rate: &'a str,
+    pub layout: &'a str,
+}
+
+/// Pause and seek requests for a streaming pass, and how far it has got.
+#[derive(Default)]
+struct Transport {
+    paused: AtomicBool,
+    /// Pending seek in samples, relative to the cursor.
+    seek: AtomicI64,
+    /// Index of the next sample handed to ffplay.
+    cursor: AtomicU64,
+    /// Peak and RMS of the last chunk written, after the gain and before
+    /// clamping, as `f32` bits.
+    peak: AtomicU32,
+    rms: AtomicU32,
+}
+
+/// A running ffplay, optionally fed by an ffmpeg resampling stage or by an
+/// in-process streaming thread.
+pub struct Player {
+    child: Child,
+    feeder: Option<Child>,
+    /// Stop flag of the thread feeding ffplay's stdin (`Stream` backend).
+    streamer: Option<Arc<AtomicBool>>,
+    /// Gain applied by the streaming thread, as `f32` bits, so it can
+    /// change mid-pass. ffplay gets its volume on the command line instead.
+    gain: Option<Arc<AtomicU32>>,
+    /// Pause and seek control of the streaming thread; ffplay reading the
+    /// file itself can't do either without restarting.
+    transport: Option<Arc<Transport>>,
+}
+
+impl Player {
+    pub fn spawn(
+        path: &str,
+        raw: &RawFormat,
+        resampler: Resampler,
+        monitor_rate: Option<u32>,
+        backend: Backend,
+        volume: f32,
+        device: Option<&Device>,
+    ) -> io::Result<Self> {
+        if backend == Backend::Stream {
+            return Self::spawn_stream(path, raw, resampler, monitor_rate, volume, device);
+        }
+        if resampler == Resampler::Player {
+            let mut cmd = Command::new("ffplay");
+            cmd.args(["-v", "fatal", "-nodisp", "-autoexit",
+                      "-f", raw.format, "-ar", raw.rate,
+                      "-ch_layout", raw.layout, "-i", path])
+                .args(volume_filter(volume));
+            if let Some(device) = device {
+                device.apply(&mut cmd);
+            }
+            cmdlog::record(&cmd);
+            let child = cmd.spawn()?;
+            return Ok(Self { child, feeder: None, streamer: None, gain: None, transport: None });
+        }
+
+        let out_rate = monitor_rate.map_or_else(|| raw.rate.to_string(), |r| r.to_string());
+        let mut cmd = Command::new("ffmpeg");
+        cmd.args(["-v", "fatal",
+                  "-f", raw.format, "-ar", raw.rate, "-ch_layout", raw.layout, "-i", path,
+                  "-af", &format!("aresample=resampler={}", resampler.label()),
+                  "-ar", &out_rate, "-f", raw.format, "pipe:1"])
+            .stdout(Stdio::piped());
+        cmdlog::record(&cmd);
+        let mut feeder = cmd.spawn()?;
+        let stdin = match feeder.stdout.take() {
+            Some(out) => Stdio::from(out),
+            None => Stdio::null(),
+        };
+
+        let mut cmd = Command::new("ffplay");
+        cmd.args(["-v", "fatal", "-nodisp", "-autoexit",
+                  "-f", raw.format, "-ar", &out_rate,
+                  "-ch_layout", raw.layout, "-i", "pipe:0"])
+            .args(volume_filter(volume))
+            .stdin(stdin);
+        if let Some(device) = device {
+            device.apply(&mut cmd);
+        }
+        cmdlog::record(&cmd);
+        let child = cmd.spawn();
+        match child {
+            Ok(child) => Ok(Self { child, feeder: Some(feeder), streamer: None, gain: None, transport: None }),
+            Err(e) => {
+                let _ = feeder.kill();
+                let _ = feeder.wait();
+                Err(e)
+            }
+        }
+    }
+
+    /// Reads `path` on a thread, converting its s32le samples to f32 and
+    /// writing them to an ffplay that plays raw f32 from stdin. With swr
+    /// or soxr, ffplay's own filter graph resamples to the monitor rate.
+    fn spawn_stream(
+        path: &str,
+        raw: &RawFormat,
+        resampler: Resampler,
+        monitor_rate: Option<u32>,
+        volume: f32,
+        device: Option<&Device>,
+    ) -> io::Result<Self> {
+        let mut reader = SampleReader::open(path)?;
+        let total = audio::sample_count(path)?;
+        let mut cmd = Command::new("ffplay");
+        cmd.args(["-v", "fatal", "-nodisp", "-autoexit",
+                  "-f", "f32le", "-ar", raw.rate,
+                  "-ch_layout", raw.layout, "-i", "pipe:0"])
+            .stdin(Stdio::piped());
+        if resampler != Resampler::Player {
+            let out_rate = monitor_rate.map_or_else(|| raw.rate.to_string(), |r| r.to_string());
+            cmd.args(["-af", &format!("aresample=osr={}:resampler={}", out_rate, resampler.label())]);
+        }
+        if let Some(device) = device {
+            device.apply(&mut cmd);
+        }
+        cmdlog::record(&cmd);
+        let mut child = cmd.spawn()?;
+        let Some(stdin) = child.stdin.take() else {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::other("ffplay has no stdin"));
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let gain = Arc::new(AtomicU32::new(volume.to_bits()));
+        let transport = Arc::new(Transport::default());
+        let (flag, level, control) = (Arc::clone(&stop), Arc::clone(&gain), Arc::clone(&transport));
+        thread::spawn(move || {
+            let _ = stream_samples(&mut reader, total, stdin, &flag, &level, &control);
+        });
+        Ok(Self { child, feeder: None, streamer: Some(stop), gain: Some(gain), transport: Some(transport) })
+    }
+
+    /// Pauses or resumes a streaming pass and returns whether it is now
+    /// paused, or `None` for ffplay playback. ffplay still plays out what
+    /// it has buffered, so the pause lands a fraction of a second late.
+    pub fn toggle_pause(&self) -> Option<bool> {
+        let transport = self.transport.as_ref()?;
+        let paused = !transport.paused.load(Ordering::Relaxed);
+        transport.paused.store(paused, Ordering::Relaxed);
+        Some(paused)
+    }
+
+    pub fn paused(&self) -> bool {
+        self.transport.as_ref().is_some_and(|t| t.paused.load(Ordering::Relaxed))
+    }
+
+    /// Moves a streaming pass `samples` forward or back, clamped to the
+    /// file. Returns false for ffplay playback.
+    pub fn seek(&self, samples: i64) -> bool {
+        match &self.transport {
+            Some(transport) => {
+                transport.seek.fetch_add(samples, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Peak and RMS of what was just streamed, or `None` for ffplay
+    /// playback, whose samples can't be tapped.
+    pub fn levels(&self) -> Option<(f32, f32)> {
+        self.transport.as_ref().map(|t| {
+            (f32::from_bits(t.peak.load(Ordering::Relaxed)), f32::from_bits(t.rms.load(Ordering::Relaxed)))
+        })
+    }
+
+    /// Samples streamed so far, or `None` for ffplay playback.
+    pub fn position(&self) -> Option<u64> {
+        self.transport.as_ref().map(|t| t.cursor.load(Ordering::Relaxed))
+    }
+
+    /// Changes the volume of a streaming pass in place. Returns false for
+    /// ffplay playback, which only picks the volume up on the next pass.
+    pub fn set_volume(&self, volume: f32) -> bool {
+        match &self.gain {
+            Some(gain) => {
+                gain.store(volume.to_bits(), Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// True once ffplay has exited, i.e. the pass reached the end.
+    pub fn finished(&mut self) -> bool {
+        !matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// True if ffplay has exited with an error.
+    pub fn failed(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(Some(status)) if !status.success())
+    }
+
+    pub fn stop(&mut self) {
+        // The streaming thread isn't joined: it may be blocked writing to
+        // the pipe, and exits on the flag or once the pipe breaks.
+        if let Some(stop) = self.streamer.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+        for child in std::iter::once(&mut self.child).chain(self.feeder.as_mut()) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// ffplay's `-af volume=` filter, left off at unity gain.
+fn volume_filter(volume: f32) -> Vec<String> {
+    if volume == 1.0 {
+        Vec::new()
+    } else {
+        vec!["-af".to_string(), format!("volume={:.2}", volume)]
+    }
+}
+
+/// Copies the file to `out` as f32le, scaled by `gain` and clamped to
+/// full scale, until it ends or `stop` is set. Seeks move the read cursor
+/// within the `total` samples; while paused nothing is written. Dropping
+/// `out` at the end closes the pipe so ffplay exits on its own.
+fn stream_samples(
+    reader: &mut SampleReader,
+    total: u64,
+    mut out: ChildStdin,
+    stop: &AtomicBool,
+    gain: &AtomicU32,
+    transport: &Transport,
+) -> io::Result<()> {
+    let mut chunk = Vec::new();
+    let mut bytes = Vec::new();
+    while !stop.load(Ordering::Relaxed) {
+        let seek = transport.seek.swap(0, Ordering::Relaxed);
+        if seek != 0 {
+            let cursor = transport.cursor.load(Ordering::Relaxed) as i64;
+            let target = (cursor + seek).clamp(0, total as i64) as u64;
+            reader.seek(target)?;
+            transport.cursor.store(target, Ordering::Relaxed);
+        }
+        if transport.paused.load(Ordering::Relaxed) {
+            transport.peak.store(0f32.to_bits(), Ordering::Relaxed);
+            transport.rms.store(0f32.to_bits(), Ordering::Relaxed);
+            thread::sleep(Duration::from_millis(20));
+            continue;
+        }
+        let read = reader.read_chunk(&mut chunk, CHUNK_SAMPLES / 16)?;
+        if read == 0 {
+            break;
+        }
+        transport.cursor.fetch_add(read as u64, Ordering::Relaxed);
+        let gain = f32::from_bits(gain.load(Ordering::Relaxed));
+        let samples: Vec<f32> = dsp::to_f32(&chunk).iter().map(|s| s * gain).collect();
+        let peak = samples.iter().fold(0f32, |m, s| m.max(s.abs()));
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        transport.peak.store(peak.to_bits(), Ordering::Relaxed);
+        transport.rms.store(rms.to_bits(), Ordering::Relaxed);
+        bytes.clear();
+        bytes.extend(samples.iter().flat_map(|s| s.clamp(-1.0, 1.0).to_le_bytes()));
+        out.write_all(&bytes)?;
+    }
+    out.flush()
+}
@@ -0,0 +1,74 @@
+//! Files queued from several INPUT arguments or a directory, stepped
+//! through with J/K. The selected file is imported into a scratch file on
+//! the next render; moving away parks that import with its entry so
+//! coming back to the file doesn't run ffmpeg again.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::audio::{ChannelLayout, MonoSource};
+
+/// A parked import and the settings it was made with.
+pub struct Cached {
+    pub path: PathBuf,
+    pub mono: Option<MonoSource>,
+    pub layout: Option<ChannelLayout>,
+}
+
+pub struct Entry {
+    pub path: PathBuf,
+    pub cached: Option<Cached>,
+}
+
+pub struct Playlist {
+    pub entries: Vec<Entry>,
+    pub current: usize,
+}
+
+impl Playlist {
+    /// A playlist over `paths`, starting at the first; `None` when empty.
+    pub fn new(paths: Vec<PathBuf>) -> Option<Self> {
+        if paths.is_empty() {
+            return None;
+        }
+        Some(Self { entries: paths.into_iter().map(|path| Entry { path, cached: None }).collect(), current: 0 })
+    }
+
+    /// The entry `offset` places from the current one, wrapping around.
+    pub fn index_after(&self, offset: isize) -> usize {
+        let len = self.entries.len() as isize;
+        (self.current as isize + offset).rem_euclid(len) as usize
+    }
+
+    /// "File 3/12", for the status and info lines.
+    pub fn label(&self) -> String {
+        format!("File {}/{}", self.current + 1, self.entries.len())
+    }
+}
+
+/// Expands directories into the files directly inside them whose
+/// extension is in `extensions`, sorted by name. Other paths are kept as
+/// given.
+pub fn expand(paths: &[PathBuf], extensions: &[&str]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut found: Vec<PathBuf> = fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.is_file()
+                    && p.extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| extensions.contains(&e.to_ascii_lowercase().as_str()))
+            })
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+    files
+}
@@ -0,0 +1,120 @@
+//! Minimal PNG encoder for 8-bit RGB images. Image data is stored in
+//! uncompressed deflate blocks, which every decoder accepts.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+pub fn write_rgb(path: &str, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    let row = width as usize * 3;
+    if rgb.len() != row * height as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "pixel buffer size mismatch"));
+    }
+
+    let mut raw = Vec::with_capacity((row + 1) * height as usize);
+    for line in rgb.chunks(row) {
+        raw.push(0); // filter type: none
+        raw.extend_from_slice(line);
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit, truecolor, no interlace
+
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_chunk(&mut out, b"IHDR", &ihdr)?;
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(&mut out, b"IEND", &[])?;
+    out.flush()
+}
+
+fn write_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    out.write_all(&crc.to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none() as u8;
+        let len = block.len() as u16;
+        out.push(last);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b"IEND".iter()), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(&[]), 1);
+    }
+
+    #[test]
+    fn stored_blocks_split_at_64k() {
+        let data = vec![7u8; 0x1_0000];
+        let z = zlib_stored(&data);
+        assert_eq!(z[..2], [0x78, 0x01]);
+        // A full non-final block, then a final one holding the last byte.
+        assert_eq!(z[2..7], [0, 0xFF, 0xFF, 0, 0]);
+        assert_eq!(z[7 + 0xFFFF..7 + 0xFFFF + 5], [1, 1, 0, 0xFE, 0xFF]);
+        assert_eq!(z.len(), 2 + 5 + 0xFFFF + 5 + 1 + 4);
+        assert_eq!(z[z.len() - 4..], adler32(&data).to_be_bytes());
+        assert_eq!(zlib_stored(&[])[2..7], [1, 0, 0, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn one_pixel_image_layout() {
+        let path = std::env::temp_dir().join(format!("audionoise-png-{}.png", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        write_rgb(&path, 1, 1, &[10, 20, 30]).unwrap();
+        let png = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+        assert_eq!(png[8..16], *b"\0\0\0\x0dIHDR");
+        assert_eq!(png[16..29], [0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+        assert_eq!(png[29..33], crc32(png[12..29].iter()).to_be_bytes());
+        // IDAT: zlib header, one final stored block of the filter byte and pixel.
+        assert_eq!(png[33..41], *b"\0\0\0\x0fIDAT");
+        assert_eq!(png[41..56], [0x78, 0x01, 1, 4, 0, 0xFB, 0xFF, 0, 10, 20, 30, 0, 0x68, 0, 0x3D]);
+        assert_eq!(png[png.len() - 12..], *b"\0\0\0\0IEND\xAE\x42\x60\x82");
+        assert!(write_rgb(&path, 2, 1, &[0; 3]).is_err());
+    }
+}
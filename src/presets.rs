@@ -0,0 +1,163 @@
+//! Preset banks: a directory holding one sub-directory per effect, each
+//! containing `<preset name>.toml` files with a `pots = [a, b, c, d]` entry.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::toml_lite;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamedPreset {
+    pub name: String,
+    pub pots: [f32; 4],
+}
+
+pub type Banks = HashMap<String, Vec<NamedPreset>>;
+
+/// Parses the `pots` array of a preset document, clamping each value into
+/// 0.0..=1.0 in case the file was edited by hand.
+pub fn parse_pots(value: &toml_lite::Value) -> Result<[f32; 4], String> {
+    let items = value.as_array().ok_or("pots must be an array")?;
+    if items.len() != 4 {
+        return Err(format!("pots must have 4 values, found {}", items.len()));
+    }
+    let mut pots = [0.0; 4];
+    for (slot, item) in pots.iter_mut().zip(items) {
+        let v = item.as_f64().ok_or("pots must be numbers")? as f32;
+        *slot = if v.is_nan() { 0.0 } else { v.clamp(0.0, 1.0) };
+    }
+    Ok(pots)
+}
+
+/// Loads every bank under `dir`, keyed by lowercase effect name. Files that
+/// fail to parse are skipped and reported in the returned error list.
+pub fn load_banks(dir: &Path) -> (Banks, Vec<String>) {
+    let mut banks = Banks::new();
+    let mut errors = Vec::new();
+
+    let Ok(effect_dirs) = fs::read_dir(dir) else {
+        return (banks, errors);
+    };
+
+    for entry in effect_dirs.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let Some(effect) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Ok(files) = fs::read_dir(&path) else {
+            continue;
+        };
+
+        let mut presets = Vec::new();
+        for file in files.flatten() {
+            let file = file.path();
+            if file.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            let Some(name) = file.file_stem().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            match load_preset_file(&file) {
+                Ok(pots) => presets.push(NamedPreset { name: name.to_string(), pots }),
+                Err(e) => errors.push(format!("{}: {}", file.display(), e)),
+            }
+        }
+
+        if !presets.is_empty() {
+            presets.sort_by(|a, b| a.name.cmp(&b.name));
+            banks.insert(effect.to_lowercase(), presets);
+        }
+    }
+
+    (banks, errors)
+}
+
+/// A preset saved from the UI into `presets.toml`, where each one is a
+/// table named after the preset holding `effect` and `pots`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserPreset {
+    pub name: String,
+    pub effect: String,
+    pub pots: [f32; 4],
+}
+
+/// Reads the saved presets, sorted by name. A missing file is empty.
+pub fn load_user_presets(path: &Path) -> Result<Vec<UserPreset>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let doc = toml_lite::parse(&text)?;
+    let mut presets = Vec::new();
+    for (name, table) in doc.tables.iter().filter(|(name, _)| !name.is_empty()) {
+        let effect = table
+            .get("effect")
+            .and_then(toml_lite::Value::as_str)
+            .ok_or_else(|| format!("[{}]: missing 'effect'", name))?;
+        let pots = table
+            .get("pots")
+            .ok_or_else(|| format!("[{}]: missing 'pots'", name))
+            .and_then(|v| parse_pots(v).map_err(|e| format!("[{}]: {}", name, e)))?;
+        presets.push(UserPreset { name: name.clone(), effect: effect.to_lowercase(), pots });
+    }
+    Ok(presets)
+}
+
+/// Adds or replaces the preset `name` in `presets.toml`.
+pub fn save_user_preset(path: &Path, name: &str, effect: &str, pots: &[f32; 4]) -> Result<(), String> {
+    let mut doc = match fs::read_to_string(path) {
+        Ok(text) => toml_lite::parse(&text)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml_lite::Document::default(),
+        Err(e) => return Err(e.to_string()),
+    };
+    let table = doc.table_mut(name);
+    table.clear();
+    table.insert("effect".to_string(), toml_lite::Value::Str(effect.to_string()));
+    let pots = pots.iter().map(|&p| toml_lite::Value::Num((p as f64 * 100.0).round() / 100.0)).collect();
+    table.insert("pots".to_string(), toml_lite::Value::Array(pots));
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, toml_lite::to_string(&doc)).map_err(|e| e.to_string())
+}
+
+fn load_preset_file(path: &Path) -> Result<[f32; 4], String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let doc = toml_lite::parse(&text)?;
+    parse_pots(doc.get("pots").ok_or("missing 'pots'")?)
+}
+
+/// Encodes an effect and its pots as a one-line patch for sharing, e.g.
+/// `echo:0.30,0.30,0.30,0.30`.
+pub fn share_string(effect: &str, pots: &[f32; 4]) -> String {
+    let pots: Vec<String> = pots.iter().map(|p| format!("{:.2}", p)).collect();
+    format!("{}:{}", effect, pots.join(","))
+}
+
+/// Parses a patch from `share_string`, returning the lowercase effect name
+/// and its pots. The effect name itself is checked by the caller.
+pub fn parse_share_string(text: &str) -> Result<(String, [f32; 4]), String> {
+    let (effect, values) = text
+        .trim()
+        .split_once(':')
+        .ok_or("expected effect:p1,p2,p3,p4")?;
+    let values: Vec<&str> = values.split(',').map(str::trim).collect();
+    if values.len() != 4 {
+        return Err(format!("expected 4 pot values, found {}", values.len()));
+    }
+    let mut pots = [0.0; 4];
+    for (slot, v) in pots.iter_mut().zip(&values) {
+        let value: f32 = v.parse().map_err(|_| format!("'{}' is not a number", v))?;
+        if !(0.0..=1.0).contains(&value) {
+            return Err(format!("pot value {} is outside 0.0..=1.0", v));
+        }
+        *slot = value;
+    }
+    Ok((effect.trim().to_lowercase(), pots))
+}
@@ -0,0 +1,653 @@
+//! Background render jobs. Importing, running `convert`, mixing and
+//! post-processing all happen on a worker thread that reports back over a
+//! channel, so the UI keeps drawing while a long file renders.
+
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, ChildStderr, Command, Stdio};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::audio::{self, ChannelLayout, FadeCurve, MonoSource};
+use crate::cmdlog;
+use crate::convert::{convert_spawn_error, format_convert_error, spawn_convert};
+use crate::dsp;
+use crate::waveform;
+
+/// One effect in the parallel rack, rendered from the dry input and mixed
+/// into the output at `gain`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RackSlot {
+    pub effect_idx: usize,
+    pub pots: [f32; 4],
+    pub gain: f32,
+}
+
+/// What a render job was started with: one `convert` run for a
+/// single-effect render, one per rack slot for a parallel render, one per
+/// stage for a chain.
+pub struct PendingRender {
+    pub effect_name: String,
+    pub pots: [f32; 4],
+    /// Rack snapshot for a parallel render.
+    pub rack: Vec<RackSlot>,
+    /// Chain snapshot for a chained render.
+    pub chain: Vec<(usize, [f32; 4])>,
+    /// Per-slot output files of a parallel render, or the intermediate
+    /// stage files of a chain.
+    pub part_paths: Vec<String>,
+    pub input_path: String,
+    pub output_path: String,
+    pub started: Instant,
+}
+
+/// A file kept next to a render: `output_path` with its extension swapped
+/// for `extension`, so `output.raw` becomes e.g. `output.mix.raw`.
+pub fn output_sibling(output_path: &str, extension: &str) -> String {
+    Path::new(output_path).with_extension(extension).to_string_lossy().into_owned()
+}
+
+/// How often the worker checks its children and the cancel flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Fades applied to `output.raw` once it is rendered. The file keeps its
+/// full bit depth; it is only reduced, and dithered, on WAV export.
+pub struct PostProcess {
+    /// Fade length in frames; 0 disables fades.
+    pub fade_len: usize,
+    pub fade_curve: FadeCurve,
+}
+
+impl PostProcess {
+    fn is_noop(&self) -> bool {
+        self.fade_len == 0
+    }
+}
+
+/// Everything the worker needs, snapshotted when the render starts.
+pub struct RenderRequest {
+    pub spec: PendingRender,
+    /// One `(effect, pots, output file)` per `convert` run.
+    pub jobs: Vec<(String, [f32; 4], String)>,
+    /// Run `jobs` one after another, each reading the previous job's
+    /// output, instead of side by side from the input.
+    pub chained: bool,
+    /// For a ramped render, the slice of the input each job reads, in
+    /// order; their outputs are joined into `output.raw`. Empty otherwise.
+    pub segments: Vec<String>,
+    pub convert_path: String,
+    pub layout: ChannelLayout,
+    /// The ffmpeg import to run first, and the channel selection it uses.
+    pub import: Option<(Command, MonoSource)>,
+    pub timeout: Option<Duration>,
+    pub post: PostProcess,
+    /// Master wet/dry blend of the monitored signal against the input;
+    /// below 1.0 playback uses `output.mix.raw` instead of `output.raw`.
+    pub master_mix: f32,
+    pub difference: bool,
+    pub split: bool,
+}
+
+pub enum Outcome {
+    /// Rendered; play `play_path` with `play_layout`. `mode` describes any
+    /// difference/split monitoring for the status line, and `envelope` is
+    /// the output's waveform, read here so the UI thread doesn't have to.
+    Done { play_path: String, play_layout: ChannelLayout, mode: &'static str, envelope: Vec<(i64, i64)> },
+    /// Imported the input, and there were no `convert` runs to follow.
+    Imported,
+    Failed(String),
+    TimedOut,
+    Cancelled,
+}
+
+pub struct ProcessResult {
+    pub spec: PendingRender,
+    /// Set when the job imported its input (into `spec.input_path`), with
+    /// the layout it used.
+    pub imported: Option<(MonoSource, ChannelLayout)>,
+    pub outcome: Outcome,
+}
+
+/// A render running on the worker thread.
+pub struct RenderJob {
+    rx: Receiver<ProcessResult>,
+    /// `(job, percent)` from the progress lines `convert` writes to stderr.
+    progress_rx: Receiver<(usize, u8)>,
+    /// Latest percentage per `convert` run; `None` until one reports.
+    progress: Vec<Option<u8>>,
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    /// What's being rendered, for the status line.
+    pub label: String,
+    pub started: Instant,
+}
+
+impl RenderJob {
+    pub fn spawn(label: String, request: RenderRequest) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let progress = vec![None; request.jobs.len()];
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancel);
+        let handle = thread::spawn(move || {
+            let _ = tx.send(run(request, &flag, progress_tx));
+        });
+        Self { rx, progress_rx, progress, cancel, handle: Some(handle), label, started: Instant::now() }
+    }
+
+    /// Runs `request` on the calling thread, for `--batch`.
+    pub fn run_blocking(request: RenderRequest) -> ProcessResult {
+        let (progress_tx, _) = mpsc::channel();
+        run(request, &AtomicBool::new(false), progress_tx)
+    }
+
+    /// Picks up progress reported since the last call.
+    pub fn poll_progress(&mut self) {
+        while let Ok((job, percent)) = self.progress_rx.try_recv() {
+            if let Some(slot) = self.progress.get_mut(job) {
+                *slot = Some(percent);
+            }
+        }
+    }
+
+    /// Overall completion from 0.0 to 1.0, averaged over the `convert`
+    /// runs; `None` if none of them has reported anything parseable.
+    pub fn progress(&self) -> Option<f64> {
+        if self.progress.iter().all(Option::is_none) {
+            return None;
+        }
+        let total: u32 = self.progress.iter().map(|p| p.unwrap_or(0) as u32).sum();
+        Some(total as f64 / (100 * self.progress.len()) as f64)
+    }
+
+    /// The job's result once the worker has finished.
+    pub fn try_result(&mut self) -> Option<ProcessResult> {
+        match self.rx.try_recv() {
+            Ok(result) => {
+                if let Some(handle) = self.handle.take() {
+                    let _ = handle.join();
+                }
+                Some(result)
+            }
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+        }
+    }
+
+    /// Asks the worker to kill its children and stop; the `Cancelled`
+    /// result still arrives through `try_result`.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Cancels and blocks until the worker has cleaned up, returning its
+    /// result.
+    pub fn cancel_and_wait(mut self) -> Option<ProcessResult> {
+        self.cancel();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        self.rx.try_recv().ok()
+    }
+}
+
+fn run(request: RenderRequest, cancel: &AtomicBool, progress: Sender<(usize, u8)>) -> ProcessResult {
+    let RenderRequest { spec, jobs, chained, segments, convert_path, layout, import, timeout, post, master_mix, difference, split } =
+        request;
+    let mut imported = None;
+    if let Some((mut cmd, mono)) = import {
+        cmdlog::record(&cmd);
+        let outcome = match cmd.spawn().map(|child| wait_all(vec![child], cancel, None, spec.started)) {
+            Ok(Wait::Finished) => None,
+            Ok(Wait::Cancelled) => Some(Outcome::Cancelled),
+            _ => Some(Outcome::Failed("Failed to import the input audio".to_string())),
+        };
+        if let Some(outcome) = outcome {
+            return ProcessResult { spec, imported, outcome };
+        }
+        imported = Some((mono, layout));
+    }
+    let finish = |spec, outcome| ProcessResult { spec, imported, outcome };
+    if jobs.is_empty() {
+        return finish(spec, Outcome::Imported);
+    }
+
+    let runner = Runner {
+        convert_path: &convert_path,
+        layout,
+        cancel,
+        timeout,
+        started: spec.started,
+        progress,
+        stderr: RefCell::new(Vec::new()),
+    };
+    let waits = if !segments.is_empty() {
+        audio::split_file(&spec.input_path, layout.channels, &segments)
+            .and_then(|()| runner.run_segments(&jobs, &segments))
+    } else if chained {
+        runner.run_chain(&jobs, &spec.input_path)
+    } else {
+        runner.run_parallel(&jobs, &spec.input_path)
+    };
+    match waits {
+        Ok(Wait::Finished) => {}
+        Ok(Wait::Failed { index, code }) => {
+            remove_parts(&spec);
+            remove_output(&spec);
+            let message = format_convert_error(code, &runner.stderr_of(index));
+            return finish(spec, Outcome::Failed(message));
+        }
+        Ok(Wait::TimedOut) => {
+            remove_parts(&spec);
+            remove_output(&spec);
+            return finish(spec, Outcome::TimedOut);
+        }
+        Ok(Wait::Cancelled) => {
+            remove_parts(&spec);
+            remove_output(&spec);
+            return finish(spec, Outcome::Cancelled);
+        }
+        Err(e) => {
+            remove_parts(&spec);
+            remove_output(&spec);
+            return finish(spec, Outcome::Failed(convert_spawn_error(&convert_path, &e)));
+        }
+    }
+    if chained {
+        remove_parts(&spec);
+    }
+    if !segments.is_empty() {
+        let outputs: Vec<String> = jobs.iter().map(|(_, _, out)| out.clone()).collect();
+        let joined = audio::concat_files(&outputs, &spec.output_path);
+        remove_parts(&spec);
+        if let Err(e) = joined {
+            remove_output(&spec);
+            return finish(spec, Outcome::Failed(format!("Error joining ramped segments: {}", e)));
+        }
+    } else if !chained && !spec.part_paths.is_empty() {
+        let mixed = mix_parallel(&spec);
+        remove_parts(&spec);
+        if let Err(e) = mixed {
+            remove_output(&spec);
+            return finish(spec, Outcome::Failed(format!("Error mixing parallel renders: {}", e)));
+        }
+    }
+    if !post.is_noop() {
+        if let Err(e) = post_process(&spec.output_path, layout.channels, &post) {
+            remove_output(&spec);
+            return finish(spec, Outcome::Failed(format!("Error post-processing output: {}", e)));
+        }
+    }
+    if cancel.load(Ordering::Relaxed) {
+        remove_output(&spec);
+        return finish(spec, Outcome::Cancelled);
+    }
+
+    let wet_path = if master_mix < 1.0 {
+        match write_blend_file(&spec.input_path, &spec.output_path, master_mix) {
+            Ok(path) => path,
+            Err(e) => return finish(spec, Outcome::Failed(format!("Error blending master mix: {}", e))),
+        }
+    } else {
+        spec.output_path.to_string()
+    };
+    let wet_path = if difference {
+        match write_difference_file(&spec.input_path, &wet_path, &spec.output_path) {
+            Ok(path) => path,
+            Err(e) => return finish(spec, Outcome::Failed(format!("Error computing difference: {}", e))),
+        }
+    } else {
+        wet_path
+    };
+
+    let envelope = waveform::render_waveform(&spec.output_path, waveform::ENVELOPE_COLUMNS);
+    let outcome = if split {
+        match write_split_file(&spec.input_path, &wet_path, &spec.output_path) {
+            Ok(split_path) => Outcome::Done {
+                play_path: split_path,
+                play_layout: ChannelLayout::STEREO,
+                mode: if difference { " (A/B split, difference)" } else { " (A/B stereo split)" },
+                envelope,
+            },
+            Err(e) => Outcome::Failed(format!("Error building A/B split: {}", e)),
+        }
+    } else {
+        Outcome::Done {
+            play_path: wet_path,
+            play_layout: layout,
+            mode: if difference { " (difference)" } else { "" },
+            envelope,
+        }
+    };
+    finish(spec, outcome)
+}
+
+/// What every `convert` run of a render shares.
+struct Runner<'a> {
+    convert_path: &'a str,
+    layout: ChannelLayout,
+    cancel: &'a AtomicBool,
+    timeout: Option<Duration>,
+    started: Instant,
+    progress: Sender<(usize, u8)>,
+    /// Per job, the thread reading its stderr, which returns everything
+    /// that wasn't progress.
+    stderr: RefCell<Vec<(usize, JoinHandle<String>)>>,
+}
+
+impl Runner<'_> {
+    /// What job `idx` wrote to stderr besides progress. Only call this once
+    /// the job has exited, or it blocks until it does.
+    fn stderr_of(&self, idx: usize) -> String {
+        let mut readers = self.stderr.borrow_mut();
+        match readers.iter().position(|&(i, _)| i == idx) {
+            Some(pos) => readers.swap_remove(pos).1.join().unwrap_or_default(),
+            None => String::new(),
+        }
+    }
+
+    /// Starts job number `idx`, with a thread forwarding its progress.
+    fn spawn(&self, idx: usize, (name, pots, out): &(String, [f32; 4], String), input_path: &str) -> io::Result<Child> {
+        let mut child =
+            spawn_convert(self.convert_path, name, pots, self.layout.channels, input_path, out, Stdio::piped())?;
+        if let Some(stderr) = child.stderr.take() {
+            let progress = self.progress.clone();
+            let reader = thread::spawn(move || forward_progress(stderr, idx, progress));
+            self.stderr.borrow_mut().push((idx, reader));
+        }
+        Ok(child)
+    }
+
+    /// Starts every job on the dry input at once and waits for them all.
+    fn run_parallel(&self, jobs: &[(String, [f32; 4], String)], input_path: &str) -> io::Result<Wait> {
+        let mut children = Vec::with_capacity(jobs.len());
+        for (idx, job) in jobs.iter().enumerate() {
+            match self.spawn(idx, job, input_path) {
+                Ok(child) => children.push(child),
+                Err(e) => {
+                    kill_all(&mut children);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(wait_all(children, self.cancel, self.timeout, self.started))
+    }
+
+    /// Runs the jobs in order, feeding each one the previous job's output.
+    /// The timeout covers the whole chain.
+    fn run_chain(&self, jobs: &[(String, [f32; 4], String)], input_path: &str) -> io::Result<Wait> {
+        let mut stage_input = input_path;
+        for (idx, job) in jobs.iter().enumerate() {
+            let child = self.spawn(idx, job, stage_input)?;
+            match wait_all(vec![child], self.cancel, self.timeout, self.started) {
+                Wait::Finished => {
+                    // A finished stage counts as done even if it never said so.
+                    let _ = self.progress.send((idx, 100));
+                    stage_input = &job.2;
+                }
+                // wait_all only saw this one child; report the stage.
+                Wait::Failed { code, .. } => return Ok(Wait::Failed { index: idx, code }),
+                other => return Ok(other),
+            }
+        }
+        Ok(Wait::Finished)
+    }
+
+    /// Runs job `i` on `inputs[i]`, one after another, like a chain whose
+    /// stages don't feed each other.
+    fn run_segments(&self, jobs: &[(String, [f32; 4], String)], inputs: &[String]) -> io::Result<Wait> {
+        for (idx, (job, input)) in jobs.iter().zip(inputs).enumerate() {
+            let child = self.spawn(idx, job, input)?;
+            match wait_all(vec![child], self.cancel, self.timeout, self.started) {
+                Wait::Finished => {
+                    let _ = self.progress.send((idx, 100));
+                }
+                Wait::Failed { code, .. } => return Ok(Wait::Failed { index: idx, code }),
+                other => return Ok(other),
+            }
+        }
+        Ok(Wait::Finished)
+    }
+}
+
+/// Most stderr text kept per job for the error message.
+const STDERR_LIMIT: usize = 4096;
+
+/// Reads `convert`'s stderr until it closes, sending every progress line
+/// ("50%", "progress: 50.0%") on. Lines may end in `\r` or `\n`. Anything
+/// else is collected and returned, for the error message if it fails.
+fn forward_progress(mut stderr: ChildStderr, idx: usize, progress: Sender<(usize, u8)>) -> String {
+    let mut other = String::new();
+    let take = |line: &[u8], other: &mut String| {
+        let text = String::from_utf8_lossy(line);
+        match parse_progress(&text) {
+            Some(percent) => {
+                let _ = progress.send((idx, percent));
+            }
+            None if other.len() < STDERR_LIMIT => {
+                other.push_str(&text);
+                other.push('\n');
+            }
+            None => {}
+        }
+    };
+    let mut line = Vec::new();
+    let mut buf = [0u8; 256];
+    while let Ok(n @ 1..) = stderr.read(&mut buf) {
+        for &byte in &buf[..n] {
+            if byte != b'\r' && byte != b'\n' {
+                line.push(byte);
+                continue;
+            }
+            take(&line, &mut other);
+            line.clear();
+        }
+    }
+    // A last line without a newline.
+    if !line.is_empty() {
+        take(&line, &mut other);
+    }
+    other
+}
+
+/// The percentage in a progress line: its last word, ending in `%`.
+fn parse_progress(line: &str) -> Option<u8> {
+    let number = line.split_whitespace().last()?.strip_suffix('%')?;
+    let percent: f32 = number.parse().ok()?;
+    (0.0..=100.0).contains(&percent).then_some(percent as u8)
+}
+
+enum Wait {
+    Finished,
+    /// Child number `index` exited unsuccessfully with `code` (`None` when
+    /// killed by a signal or its status couldn't be read).
+    Failed { index: usize, code: Option<i32> },
+    TimedOut,
+    Cancelled,
+}
+
+/// Polls `children` until all exit, one fails, the timeout passes or the
+/// job is cancelled. Anything still running is killed on the way out.
+fn wait_all(mut children: Vec<Child>, cancel: &AtomicBool, timeout: Option<Duration>, started: Instant) -> Wait {
+    loop {
+        let mut running = false;
+        let mut failed = None;
+        for (index, child) in children.iter_mut().enumerate() {
+            match child.try_wait() {
+                Ok(None) => running = true,
+                Ok(Some(status)) if !status.success() => {
+                    failed = failed.or(Some(Wait::Failed { index, code: status.code() }))
+                }
+                Ok(Some(_)) => {}
+                Err(_) => failed = failed.or(Some(Wait::Failed { index, code: None })),
+            }
+        }
+
+        let result = if let Some(failed) = failed {
+            failed
+        } else if !running {
+            return Wait::Finished;
+        } else if cancel.load(Ordering::Relaxed) {
+            Wait::Cancelled
+        } else if timeout.is_some_and(|limit| started.elapsed() >= limit) {
+            Wait::TimedOut
+        } else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        kill_all(&mut children);
+        return result;
+    }
+}
+
+fn kill_all(children: &mut [Child]) {
+    for child in children {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Removes the per-slot files of an aborted parallel render, or the
+/// intermediate stages of a chain.
+fn remove_parts(spec: &PendingRender) {
+    for part in &spec.part_paths {
+        let _ = std::fs::remove_file(part);
+    }
+}
+
+/// Drops the output of a cancelled render so a later play can't pick up
+/// truncated audio.
+fn remove_output(spec: &PendingRender) {
+    let _ = std::fs::remove_file(&spec.output_path);
+}
+
+/// Applies fades to a rendered file in place, working in f32 and
+/// converting back to 32-bit samples undithered. The file is streamed a
+/// chunk at a time.
+fn post_process(path: &str, channels: usize, post: &PostProcess) -> io::Result<()> {
+    let total_frames = audio::sample_count(path)? / channels as u64;
+    audio::map_file(path, channels, |first_frame, chunk| {
+        let mut samples = dsp::to_f32(chunk);
+        audio::apply_fades(&mut samples, channels, first_frame, total_frames, post.fade_len, post.fade_curve);
+        audio::quantize(&samples, 32, None)
+    })
+}
+
+/// Sums the per-slot renders of a parallel job into the output file.
+fn mix_parallel(pending: &PendingRender) -> io::Result<()> {
+    let parts: Vec<(&str, f32)> = pending
+        .part_paths
+        .iter()
+        .zip(&pending.rack)
+        .map(|(path, slot)| (path.as_str(), slot.gain))
+        .collect();
+    audio::write_mix(&parts, &pending.output_path)
+}
+
+/// Writes `output.mix.raw`, the render blended with the input at the
+/// master mix, and returns its path.
+fn write_blend_file(input_path: &str, output_path: &str, mix: f32) -> io::Result<String> {
+    let mix_path = output_sibling(output_path, "mix.raw");
+    audio::write_blend(input_path, output_path, &mix_path, mix)?;
+    Ok(mix_path)
+}
+
+/// Writes `output.diff.raw`, what the monitored signal adds to the input, and
+/// returns its path.
+fn write_difference_file(input_path: &str, wet_path: &str, output_path: &str) -> io::Result<String> {
+    let diff_path = output_sibling(output_path, "diff.raw");
+    audio::write_difference(wet_path, input_path, &diff_path)?;
+    Ok(diff_path)
+}
+
+/// Writes a stereo file with the dry input on the left channel and the
+/// wet signal on the right, returning its path.
+fn write_split_file(input_path: &str, wet_path: &str, output_path: &str) -> io::Result<String> {
+    let split_path = output_sibling(output_path, "split.raw");
+    audio::write_interleaved_stereo(input_path, wet_path, &split_path)?;
+    Ok(split_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `script` with `sh` and passes its stderr through
+    /// `forward_progress`, returning the percentages sent and the rest.
+    fn forward(script: &str) -> (Vec<u8>, String) {
+        let mut child = Command::new("sh").args(["-c", script]).stderr(Stdio::piped()).spawn().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let other = forward_progress(child.stderr.take().unwrap(), 3, tx);
+        child.wait().unwrap();
+        let sent = rx.try_iter().map(|(idx, percent)| {
+            assert_eq!(idx, 3);
+            percent
+        });
+        (sent.collect(), other)
+    }
+
+    #[test]
+    fn progress_is_the_last_word_ending_in_percent() {
+        assert_eq!(parse_progress("50%"), Some(50));
+        assert_eq!(parse_progress("progress: 99.9%"), Some(99));
+        assert_eq!(parse_progress("  0%  "), Some(0));
+        for garbage in ["", "%", "50", "50% done", "150%", "-1%", "NaN%", "x%"] {
+            assert_eq!(parse_progress(garbage), None, "{:?}", garbage);
+        }
+    }
+
+    #[test]
+    fn carriage_return_updates_are_each_forwarded() {
+        let (sent, other) = forward("printf '10%%\\r20%%\\rprogress: 30.5%%\\n' >&2");
+        assert_eq!(sent, [10, 20, 30]);
+        assert_eq!(other, "");
+    }
+
+    #[test]
+    fn lines_split_across_reads_are_joined() {
+        let (sent, other) = forward("printf 'progress: 4' >&2; sleep 0.1; printf '2%%\\nbad inp' >&2; sleep 0.1; printf 'ut' >&2");
+        assert_eq!(sent, [42]);
+        assert_eq!(other, "bad input\n");
+    }
+
+    #[test]
+    fn garbage_is_kept_for_the_error_message() {
+        let (sent, other) = forward("printf 'warning: clipped\\n\\377\\376\\n\\n75%%' >&2");
+        assert_eq!(sent, [75]);
+        assert_eq!(other, "warning: clipped\n\u{fffd}\u{fffd}\n\n");
+    }
+
+    #[test]
+    fn a_failed_job_is_reported_with_its_stderr() {
+        let sh = |script: &str| Command::new("sh").args(["-c", script]).stderr(Stdio::null()).spawn().unwrap();
+        let children = vec![sh("sleep 0.05"), sh("echo x >&2; exit 3")];
+        let wait = wait_all(children, &AtomicBool::new(false), None, Instant::now());
+        assert!(matches!(wait, Wait::Failed { index: 1, code: Some(3) }));
+
+        // `sh` stands in for convert, running the script named as the effect.
+        let dir = std::env::temp_dir().join(format!("audionoise-render-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        std::fs::write(path("ok.sh"), "cat").unwrap();
+        std::fs::write(path("fail.sh"), "echo x >&2; exit 3").unwrap();
+        std::fs::write(path("in.raw"), [0u8; 8]).unwrap();
+        let (progress, _) = mpsc::channel();
+        let runner = Runner {
+            convert_path: "sh",
+            layout: ChannelLayout::MONO,
+            cancel: &AtomicBool::new(false),
+            timeout: None,
+            started: Instant::now(),
+            progress,
+            stderr: RefCell::new(Vec::new()),
+        };
+        let jobs = [(path("ok.sh"), [0.5; 4], path("ok.raw")), (path("fail.sh"), [0.5; 4], path("fail.raw"))];
+        let wait = runner.run_parallel(&jobs, &path("in.raw")).unwrap();
+        assert!(matches!(wait, Wait::Failed { index: 1, code: Some(3) }));
+        assert_eq!(runner.stderr_of(1), "x\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+//! The session saved on quit and restored on the next launch: every
+//! effect's pots, the selection, and the loop and channel toggles. It is
+//! kept in `session.toml` next to the config file.
+
+use std::fs;
+use std::path::Path;
+
+use crate::audio::ChannelLayout;
+use crate::presets;
+use crate::toml_lite::{self, Value};
+
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    /// Name of the selected effect.
+    pub effect: Option<String>,
+    pub pot_idx: usize,
+    /// Pot values by effect name, so a file written before effects were
+    /// added or removed still restores the ones that match.
+    pub pots: Vec<(String, [f32; 4])>,
+    pub loop_playback: bool,
+    pub layout: Option<ChannelLayout>,
+}
+
+/// Reads the saved session; `None` if there isn't one yet. Entries that
+/// don't parse are skipped rather than failing the whole file.
+pub fn load(path: &Path) -> Result<Option<Session>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let doc = toml_lite::parse(&text)?;
+    let pots = doc
+        .tables
+        .get("pots")
+        .map(|table| {
+            table
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_lowercase(), presets::parse_pots(value).ok()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Some(Session {
+        effect: doc.get("effect").and_then(Value::as_str).map(str::to_lowercase),
+        pot_idx: doc.get("pot").and_then(Value::as_f64).map_or(0, |p| (p as usize).min(3)),
+        pots,
+        loop_playback: doc.get("loop").and_then(Value::as_bool).unwrap_or(false),
+        layout: doc.get("layout").and_then(Value::as_str).and_then(ChannelLayout::parse),
+    }))
+}
+
+pub fn save(path: &Path, session: &Session) -> Result<(), String> {
+    let mut doc = toml_lite::Document::default();
+    let root = doc.table_mut("");
+    if let Some(effect) = &session.effect {
+        root.insert("effect".to_string(), Value::Str(effect.clone()));
+    }
+    root.insert("pot".to_string(), Value::Num(session.pot_idx as f64));
+    root.insert("loop".to_string(), Value::Bool(session.loop_playback));
+    if let Some(layout) = session.layout {
+        root.insert("layout".to_string(), Value::Str(layout.name.to_string()));
+    }
+    let table = doc.table_mut("pots");
+    for (name, pots) in &session.pots {
+        let pots = pots.iter().map(|&p| Value::Num((p as f64 * 100.0).round() / 100.0)).collect();
+        table.insert(name.clone(), Value::Array(pots));
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, toml_lite::to_string(&doc)).map_err(|e| e.to_string())
+}
@@ -0,0 +1,133 @@
+//! STFT spectrogram of a raw sample file, exported as a PNG.
+
+use std::io;
+
+use crate::audio::{self, ChannelLayout};
+use crate::{dsp, png};
+
+/// Bottom of the displayed dynamic range; quieter bins render as black.
+const FLOOR_DB: f32 = -100.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMap {
+    Gray,
+    Heat,
+}
+
+impl ColorMap {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "gray" | "grey" => Some(Self::Gray),
+            "heat" => Some(Self::Heat),
+            _ => None,
+        }
+    }
+
+    /// Maps an intensity in 0.0..=1.0 to RGB.
+    fn rgb(self, t: f32) -> [u8; 3] {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Gray => {
+                let v = (t * 255.0) as u8;
+                [v, v, v]
+            }
+            Self::Heat => {
+                // black -> red -> yellow -> white
+                let r = (t * 3.0).min(1.0);
+                let g = (t * 3.0 - 1.0).clamp(0.0, 1.0);
+                let b = (t * 3.0 - 2.0).clamp(0.0, 1.0);
+                [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]
+            }
+        }
+    }
+}
+
+/// Computes a `width` x `height` spectrogram of an s32le file (downmixed to
+/// mono) and writes it to `png_path`. Columns are evenly spaced STFT frames read one at a
+/// time, so memory use doesn't depend on the file length. Rows run from
+/// 0 Hz at the bottom to Nyquist at the top.
+pub fn export(
+    raw_path: &str,
+    png_path: &str,
+    layout: ChannelLayout,
+    width: u32,
+    height: u32,
+    cmap: ColorMap,
+) -> io::Result<()> {
+    let channels = layout.channels;
+    let total = std::fs::metadata(raw_path)?.len() / layout.bytes_per_frame() as u64;
+    let fft_size = (height as usize * 2).next_power_of_two().max(64);
+    let window = dsp::hann(fft_size);
+    let bins = fft_size / 2;
+
+    let mut rgb = vec![0u8; width as usize * height as usize * 3];
+    for x in 0..width as usize {
+        let start = if width > 1 {
+            total.saturating_sub(fft_size as u64) * x as u64 / (width as u64 - 1)
+        } else {
+            0
+        };
+        let samples = audio::read_window(raw_path, start * channels as u64, fft_size * channels)?;
+        let mut frame: Vec<f32> = dsp::to_f32(&samples)
+            .chunks(channels)
+            .map(|c| c.iter().sum::<f32>() / channels as f32)
+            .collect();
+        frame.resize(fft_size, 0.0);
+        let mags = dsp::magnitudes(&frame, &window);
+
+        for y in 0..height as usize {
+            let bin = ((height as usize - 1 - y) * bins / height as usize).min(bins - 1);
+            let db = 20.0 * mags[bin].max(1e-9).log10();
+            let color = cmap.rgb(1.0 - db / FLOOR_DB);
+            let offset = (y * width as usize + x) * 3;
+            rgb[offset..offset + 3].copy_from_slice(&color);
+        }
+    }
+
+    png::write_rgb(png_path, width, height, &rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_tone_lights_its_own_row() {
+        let dir = std::env::temp_dir();
+        let raw = dir.join(format!("audionoise-spectrogram-{}.raw", std::process::id()));
+        let png = raw.with_extension("png");
+        let (raw, png) = (raw.to_string_lossy().into_owned(), png.to_string_lossy().into_owned());
+        // A quarter of the rate: bin 16 of the 64-point frames an 8-row image uses.
+        let tone: Vec<u8> = [0, i32::MAX / 2, 0, -i32::MAX / 2]
+            .iter()
+            .cycle()
+            .take(256)
+            .flat_map(|s: &i32| s.to_le_bytes())
+            .collect();
+        std::fs::write(&raw, tone).unwrap();
+        export(&raw, &png, ChannelLayout::MONO, 2, 8, ColorMap::Gray).unwrap();
+        let bytes = std::fs::read(&png).unwrap();
+        std::fs::remove_file(&raw).unwrap();
+        std::fs::remove_file(&png).unwrap();
+
+        assert_eq!(bytes[16..24], [0, 0, 0, 2, 0, 0, 0, 8]);
+        // Rows of the stored IDAT block: a filter byte, then two RGB pixels.
+        let pixels = &bytes[41 + 7..];
+        let row = |y: usize| &pixels[y * 7 + 1..y * 7 + 7];
+        for y in 0..8 {
+            // Row y shows bin (7 - y) * 4, so bin 16 is row 3.
+            let lit = row(y).iter().all(|&v| v > 200);
+            assert_eq!(lit, y == 3, "row {}: {:?}", y, row(y));
+        }
+    }
+
+    #[test]
+    fn color_maps_span_black_to_white() {
+        for cmap in [ColorMap::Gray, ColorMap::Heat] {
+            assert_eq!(cmap.rgb(0.0), [0, 0, 0]);
+            assert_eq!(cmap.rgb(1.0), [255, 255, 255]);
+        }
+        assert_eq!(ColorMap::parse("grey"), Some(ColorMap::Gray));
+        assert_eq!(ColorMap::parse("rainbow"), None);
+    }
+}
@@ -0,0 +1,115 @@
+//! Averaged magnitude spectrum of a rendered file for the spectrum view.
+
+use crate::audio::{self, ChannelLayout};
+use crate::dsp;
+
+/// FFT length: about 85 ms at 48 kHz, fine enough to resolve the lowest
+/// log-spaced bands.
+const FFT_SIZE: usize = 4096;
+/// Frames read from across the file and averaged.
+const FRAMES: u64 = 16;
+
+/// The magnitude spectrum of mono `samples` in `bins` log-spaced bands,
+/// from the lowest FFT bin up to Nyquist. `samples` is cut into FFT-sized
+/// frames (the last one zero-padded) whose Hann-windowed magnitudes are
+/// averaged; each band holds the loudest FFT bin it covers, so a
+/// full-scale sine reads near 1.0.
+pub fn spectrum(samples: &[f32], bins: usize) -> Vec<f32> {
+    let half = FFT_SIZE / 2;
+    let window = dsp::hann(FFT_SIZE);
+    let mut average = vec![0.0; half];
+    let mut frames = 0;
+    for chunk in samples.chunks(FFT_SIZE) {
+        let mut frame = chunk.to_vec();
+        frame.resize(FFT_SIZE, 0.0);
+        for (sum, mag) in average.iter_mut().zip(dsp::magnitudes(&frame, &window)) {
+            *sum += mag;
+        }
+        frames += 1;
+    }
+    if frames > 0 {
+        average.iter_mut().for_each(|m| *m /= frames as f32);
+    }
+
+    let edges = band_edges(bins);
+    edges.windows(2).map(|w| average[w[0]..w[1]].iter().fold(0.0, |a: f32, &m| a.max(m))).collect()
+}
+
+/// The FFT bin where each band starts, plus the end of the last one:
+/// geometric steps from bin 1 (bin 0 is DC) to Nyquist, at least one FFT
+/// bin wide. Far fewer bands than FFT bins are expected, so widening the
+/// bottom ones still leaves the top one ending at Nyquist.
+fn band_edges(bins: usize) -> Vec<usize> {
+    let half = FFT_SIZE / 2;
+    let mut edges = Vec::with_capacity(bins + 1);
+    for band in 0..=bins {
+        let edge = (half as f64).powf(band as f64 / bins.max(1) as f64) as usize;
+        edges.push(edge.clamp(1, half));
+    }
+    for band in 0..bins {
+        edges[band + 1] = edges[band + 1].max(edges[band] + 1);
+    }
+    edges
+}
+
+/// The spectrum of an s32le file, downmixed to mono, from `FRAMES` frames
+/// spread evenly across it. A missing or empty file gives no bands.
+pub fn read_spectrum(path: &str, layout: ChannelLayout, bins: usize) -> Vec<f32> {
+    let channels = layout.channels;
+    let total = match std::fs::metadata(path) {
+        Ok(m) if m.len() > 0 => m.len() / layout.bytes_per_frame() as u64,
+        _ => return Vec::new(),
+    };
+    let mut samples = Vec::with_capacity(FFT_SIZE * FRAMES as usize);
+    for frame in 0..FRAMES {
+        let start = total.saturating_sub(FFT_SIZE as u64) * frame / (FRAMES - 1);
+        let Ok(raw) = audio::read_window(path, start * channels as u64, FFT_SIZE * channels) else {
+            return Vec::new();
+        };
+        let mut mono: Vec<f32> = dsp::to_f32(&raw)
+            .chunks(channels)
+            .map(|c| c.iter().sum::<f32>() / channels as f32)
+            .collect();
+        // Keep frames aligned for `spectrum` even when the file is short.
+        mono.resize(FFT_SIZE, 0.0);
+        samples.extend(mono);
+        if total <= FFT_SIZE as u64 {
+            break;
+        }
+    }
+    spectrum(&samples, bins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn sine_energy_lands_in_its_band() {
+        // Exactly 64 cycles per FFT frame: all the energy is in FFT bin 64.
+        let samples: Vec<f32> =
+            (0..FFT_SIZE * 4).map(|i| (2.0 * PI * 64.0 * i as f32 / FFT_SIZE as f32).sin()).collect();
+        let bands = spectrum(&samples, 40);
+        assert_eq!(bands.len(), 40);
+
+        let edges = band_edges(40);
+        let expected = (0..40).find(|&b| (edges[b]..edges[b + 1]).contains(&64)).unwrap();
+        let loudest = (0..40).max_by(|&a, &b| bands[a].total_cmp(&bands[b])).unwrap();
+        assert_eq!(loudest, expected);
+        assert!((bands[expected] - 1.0).abs() < 0.05, "{}", bands[expected]);
+        // Far from the tone the Hann window leaves next to nothing.
+        assert!(bands[0] < 1e-3 && bands[39] < 1e-3);
+    }
+
+    #[test]
+    fn bands_cover_the_spectrum_in_order() {
+        for bins in [1, 8, 40, 300, 1000] {
+            let edges = band_edges(bins);
+            assert_eq!(edges.len(), bins + 1);
+            assert!(edges.windows(2).all(|w| w[0] < w[1]), "{:?}", edges);
+            assert!(edges[0] >= 1 && edges[bins] <= FFT_SIZE / 2);
+        }
+        assert_eq!(spectrum(&[], 8), vec![0.0; 8]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Color themes. Every widget in `ui` takes its colors from the active
+//! `Theme` instead of naming them, so a theme swap restyles everything.
+
+use ratatui::style::Color;
+
+#[derive(Debug, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub title: Color,
+    /// The selected effect, preset and key names in the help.
+    pub selected: Color,
+    /// Unselected list items, descriptions and hints.
+    pub unselected: Color,
+    /// Names and values of the unselected pots.
+    pub text: Color,
+    pub bar_selected: Color,
+    pub bar_unselected: Color,
+    pub status_ok: Color,
+    pub status_error: Color,
+    /// Prompts, progress, the selected pot, headings and highlights.
+    pub accent: Color,
+    /// Empty meter cells, the info line and other background detail.
+    pub dim: Color,
+    /// The waveform, the delay gauge and the A/B slots.
+    pub graph: Color,
+}
+
+const fn solarized(hex: u32) -> Color {
+    Color::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "default",
+        title: Color::Cyan,
+        selected: Color::Green,
+        unselected: Color::Gray,
+        text: Color::White,
+        bar_selected: Color::Green,
+        bar_unselected: Color::Blue,
+        status_ok: Color::Green,
+        status_error: Color::Red,
+        accent: Color::Yellow,
+        dim: Color::DarkGray,
+        graph: Color::Cyan,
+    },
+    // The terminal's own foreground throughout; selection still shows
+    // through the bold text and the markers.
+    Theme {
+        name: "monochrome",
+        title: Color::Reset,
+        selected: Color::Reset,
+        unselected: Color::Reset,
+        text: Color::Reset,
+        bar_selected: Color::Reset,
+        bar_unselected: Color::Reset,
+        status_ok: Color::Reset,
+        status_error: Color::Reset,
+        accent: Color::Reset,
+        dim: Color::Reset,
+        graph: Color::Reset,
+    },
+    Theme {
+        name: "high-contrast",
+        title: Color::White,
+        selected: Color::LightYellow,
+        unselected: Color::White,
+        text: Color::White,
+        bar_selected: Color::LightYellow,
+        bar_unselected: Color::White,
+        status_ok: Color::LightGreen,
+        status_error: Color::LightRed,
+        accent: Color::LightYellow,
+        dim: Color::Gray,
+        graph: Color::LightCyan,
+    },
+    // Readable on both the light and the dark solarized backgrounds.
+    Theme {
+        name: "solarized",
+        title: solarized(0x268bd2),
+        selected: solarized(0x859900),
+        unselected: solarized(0x839496),
+        text: solarized(0x657b83),
+        bar_selected: solarized(0x859900),
+        bar_unselected: solarized(0x2aa198),
+        status_ok: solarized(0x859900),
+        status_error: solarized(0xdc322f),
+        accent: solarized(0xb58900),
+        dim: solarized(0x93a1a1),
+        graph: solarized(0x6c71c4),
+    },
+];
+
+impl Theme {
+    pub fn parse(name: &str) -> Option<&'static Theme> {
+        THEMES.iter().find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn names() -> String {
+        THEMES.iter().map(|t| t.name).collect::<Vec<_>>().join(", ")
+    }
+
+    /// The theme after this one, wrapping around.
+    pub fn next(&self) -> &'static Theme {
+        let i = THEMES.iter().position(|t| t == self).unwrap_or(0);
+        &THEMES[(i + 1) % THEMES.len()]
+    }
+}
@@ -0,0 +1,243 @@
+//! Minimal reader/writer for the small subset of TOML used by our config
+//! and preset files: `[table]` headers, `key = value` pairs, strings,
+//! numbers, booleans and flat arrays.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Str(String),
+    Num(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Num(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+/// A parsed document: top-level keys live under the empty table name.
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    pub tables: BTreeMap<String, Table>,
+}
+
+impl Document {
+    pub fn root(&self) -> Option<&Table> {
+        self.tables.get("")
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.root().and_then(|t| t.get(key))
+    }
+
+    pub fn table_mut(&mut self, name: &str) -> &mut Table {
+        self.tables.entry(name.to_string()).or_default()
+    }
+}
+
+pub fn parse(text: &str) -> Result<Document, String> {
+    let mut doc = Document::default();
+    let mut current = String::new();
+
+    for (n, raw) in text.lines().enumerate() {
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let lineno = n + 1;
+
+        if let Some(rest) = line.strip_prefix('[') {
+            let name = rest
+                .strip_suffix(']')
+                .ok_or_else(|| format!("line {}: unterminated table header", lineno))?
+                .trim();
+            current = unquote_key(name);
+            doc.tables.entry(current.clone()).or_default();
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected 'key = value'", lineno))?;
+        let key = unquote_key(key.trim());
+        if key.is_empty() {
+            return Err(format!("line {}: empty key", lineno));
+        }
+        let value = parse_value(value.trim()).map_err(|e| format!("line {}: {}", lineno, e))?;
+        doc.tables.entry(current.clone()).or_default().insert(key, value);
+    }
+
+    Ok(doc)
+}
+
+/// Writes a document back out: root keys first, then one `[table]` each.
+/// Comments and key order from the original text are not preserved.
+pub fn to_string(doc: &Document) -> String {
+    let mut out = String::new();
+    if let Some(root) = doc.root() {
+        write_table(&mut out, root);
+    }
+    for (name, table) in doc.tables.iter().filter(|(name, _)| !name.is_empty()) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "[{}]", quote_key(name));
+        write_table(&mut out, table);
+    }
+    out
+}
+
+fn write_table(out: &mut String, table: &Table) {
+    for (key, value) in table {
+        let _ = writeln!(out, "{} = {}", quote_key(key), format_value(value));
+    }
+}
+
+fn quote_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        key.to_string()
+    } else {
+        format_value(&Value::Str(key.to_string()))
+    }
+}
+
+fn unquote_key(key: &str) -> String {
+    match parse_string(key) {
+        Ok(s) => s,
+        Err(_) => key.to_string(),
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == '#' {
+            return &line[..i];
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if text.starts_with('"') {
+        return parse_string(text).map(Value::Str);
+    }
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner
+            .strip_suffix(']')
+            .ok_or_else(|| "unterminated array".to_string())?;
+        let mut items = Vec::new();
+        for part in split_array(inner) {
+            let part = part.trim();
+            if !part.is_empty() {
+                items.push(parse_value(part)?);
+            }
+        }
+        return Ok(Value::Array(items));
+    }
+    match text {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+    text.replace('_', "")
+        .parse::<f64>()
+        .map(Value::Num)
+        .map_err(|_| format!("invalid value '{}'", text))
+}
+
+fn split_array(inner: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    let mut prev = '\0';
+    for (i, c) in inner.char_indices() {
+        match c {
+            '"' if prev != '\\' => in_string = !in_string,
+            ',' if !in_string => {
+                parts.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    parts.push(&inner[start..]);
+    parts
+}
+
+fn parse_string(text: &str) -> Result<String, String> {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .ok_or_else(|| format!("invalid string {}", text))?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(other) => out.push(other),
+                None => return Err("dangling escape".to_string()),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    Ok(out)
+}
+
+/// Formats a value as TOML, for callers that edit files line by line.
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::Str(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+        Value::Num(n) => format!("{}", n),
+        Value::Bool(b) => b.to_string(),
+        Value::Array(items) => {
+            let parts: Vec<String> = items.iter().map(format_value).collect();
+            format!("[{}]", parts.join(", "))
+        }
+    }
+}
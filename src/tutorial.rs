@@ -0,0 +1,71 @@
+//! First-run walkthrough: a short sequence of overlays that each wait for
+//! the user to perform the step they describe.
+
+/// UI region a tutorial step points at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Region {
+    Effects,
+    Pots,
+    Status,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    Welcome,
+    SelectEffect,
+    AdjustPot,
+    Render,
+    Done,
+}
+
+impl Step {
+    pub fn next(self) -> Option<Self> {
+        match self {
+            Self::Welcome => Some(Self::SelectEffect),
+            Self::SelectEffect => Some(Self::AdjustPot),
+            Self::AdjustPot => Some(Self::Render),
+            Self::Render => Some(Self::Done),
+            Self::Done => None,
+        }
+    }
+
+    pub fn number(self) -> usize {
+        self as usize + 1
+    }
+
+    pub const COUNT: usize = 5;
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Welcome => "Welcome to AudioNoise",
+            Self::SelectEffect => "Pick an effect",
+            Self::AdjustPot => "Turn a pot",
+            Self::Render => "Render and listen",
+            Self::Done => "You're set",
+        }
+    }
+
+    pub fn text(self) -> &'static str {
+        match self {
+            Self::Welcome => "This short tour covers the basics: choosing an effect, \
+                adjusting its pots and rendering audio.\n\nPress any key to start, or Esc to skip.",
+            Self::SelectEffect => "The EFFECTS list is highlighted. Press Up/Down (or k/j) \
+                to move to another effect.",
+            Self::AdjustPot => "Each effect has four pots. Tab picks a pot; now press \
+                Left/Right (or h/l) to change its value.",
+            Self::Render => "Press 'p' to process input.raw through the effect and play \
+                the result. Progress and errors appear in the status line.",
+            Self::Done => "That's the core loop. The controls line lists every other key.\n\n\
+                Press any key to close.",
+        }
+    }
+
+    pub fn region(self) -> Option<Region> {
+        match self {
+            Self::SelectEffect => Some(Region::Effects),
+            Self::AdjustPot => Some(Region::Pots),
+            Self::Render => Some(Region::Status),
+            Self::Welcome | Self::Done => None,
+        }
+    }
+}
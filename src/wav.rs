@@ -0,0 +1,127 @@
+//! WAV export: a canonical 44-byte RIFF/WAVE header in front of PCM
+//! samples, at the render's 32 bits or reduced to 16 or 24.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+
+use crate::audio::{self, SampleReader, BYTES_PER_SAMPLE, CHUNK_SAMPLES};
+use crate::dsp;
+use crate::rng::Rng;
+
+const HEADER_LEN: u32 = 44;
+const BITS_PER_SAMPLE: u16 = (BYTES_PER_SAMPLE * 8) as u16;
+
+/// Copies the headerless s32le file at `raw_path` into a WAV file at
+/// `wav_path` with `bits` per sample, streaming the samples rather than
+/// loading them. At 32 bits they are copied as they are. Fewer bits is the
+/// only place bit depth is reduced: the samples go through f32 and are
+/// requantized, with TPDF `dither` if given, while the raw file is left
+/// alone.
+pub fn write_wav(
+    raw_path: &str,
+    wav_path: &str,
+    sample_rate: u32,
+    channels: u16,
+    bits: u32,
+    mut dither: Option<&mut Rng>,
+) -> io::Result<()> {
+    let bits = bits.clamp(8, BITS_PER_SAMPLE as u32) as u16;
+    let bytes = (bits / 8) as usize;
+    let samples = audio::sample_count(raw_path)?;
+    let data_len = u32::try_from(samples * bytes as u64)
+        .ok()
+        .filter(|len| len.checked_add(HEADER_LEN - 8).is_some())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "too much audio for a WAV file (4 GiB)"))?;
+
+    let mut out = BufWriter::new(File::create(wav_path)?);
+    out.write_all(&header(data_len, sample_rate, channels, bits))?;
+    if bits == BITS_PER_SAMPLE {
+        io::copy(&mut BufReader::new(File::open(raw_path)?), &mut out)?;
+    } else {
+        let mut reader = SampleReader::open(raw_path)?;
+        let mut chunk = Vec::new();
+        while reader.read_chunk(&mut chunk, CHUNK_SAMPLES)? > 0 {
+            for s in audio::quantize(&dsp::to_f32(&chunk), bits as u32, dither.as_deref_mut()) {
+                out.write_all(&s.to_le_bytes()[..bytes])?;
+            }
+        }
+    }
+    out.flush()
+}
+
+fn header(data_len: u32, sample_rate: u32, channels: u16, bits: u16) -> [u8; HEADER_LEN as usize] {
+    let block_align = channels * (bits / 8);
+    let byte_rate = sample_rate * block_align as u32;
+
+    let mut h = [0u8; HEADER_LEN as usize];
+    h[0..4].copy_from_slice(b"RIFF");
+    h[4..8].copy_from_slice(&(data_len + HEADER_LEN - 8).to_le_bytes());
+    h[8..12].copy_from_slice(b"WAVE");
+    h[12..16].copy_from_slice(b"fmt ");
+    h[16..20].copy_from_slice(&16u32.to_le_bytes());
+    h[20..22].copy_from_slice(&1u16.to_le_bytes()); // integer PCM
+    h[22..24].copy_from_slice(&channels.to_le_bytes());
+    h[24..28].copy_from_slice(&sample_rate.to_le_bytes());
+    h[28..32].copy_from_slice(&byte_rate.to_le_bytes());
+    h[32..34].copy_from_slice(&block_align.to_le_bytes());
+    h[34..36].copy_from_slice(&bits.to_le_bytes());
+    h[36..40].copy_from_slice(b"data");
+    h[40..44].copy_from_slice(&data_len.to_le_bytes());
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("audionoise-wav-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn header_for_known_sample_count() {
+        // 10 stereo frames of 32-bit samples = 80 bytes of data.
+        let h = header(80, 48000, 2, 32);
+        assert_eq!(&h[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(h[4..8].try_into().unwrap()), 80 + 36);
+        assert_eq!(&h[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(h[16..20].try_into().unwrap()), 16);
+        assert_eq!(u16::from_le_bytes(h[20..22].try_into().unwrap()), 1);
+        assert_eq!(u16::from_le_bytes(h[22..24].try_into().unwrap()), 2);
+        assert_eq!(u32::from_le_bytes(h[24..28].try_into().unwrap()), 48000);
+        assert_eq!(u32::from_le_bytes(h[28..32].try_into().unwrap()), 48000 * 8);
+        assert_eq!(u16::from_le_bytes(h[32..34].try_into().unwrap()), 8);
+        assert_eq!(u16::from_le_bytes(h[34..36].try_into().unwrap()), 32);
+        assert_eq!(&h[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(h[40..44].try_into().unwrap()), 80);
+    }
+
+    #[test]
+    fn wav_is_header_then_samples() {
+        let (raw_path, wav_path) = (temp_path("in.raw"), temp_path("out.wav"));
+        let samples: Vec<u8> = [1i32, -2, 3].iter().flat_map(|s| s.to_le_bytes()).collect();
+        std::fs::write(&raw_path, &samples).unwrap();
+
+        write_wav(&raw_path, &wav_path, 48000, 1, 32, Some(&mut Rng::new(1))).unwrap();
+        let wav = std::fs::read(&wav_path).unwrap();
+        assert_eq!(&wav[..44], &header(12, 48000, 1, 32));
+        assert_eq!(&wav[44..], &samples[..]);
+
+        // 16 bits: the header says so and each sample keeps its top half.
+        let samples: Vec<u8> = [0x4000_0000i32, -0x4000_0000].iter().flat_map(|s| s.to_le_bytes()).collect();
+        std::fs::write(&raw_path, &samples).unwrap();
+        write_wav(&raw_path, &wav_path, 48000, 1, 16, None).unwrap();
+        let wav = std::fs::read(&wav_path).unwrap();
+        assert_eq!(&wav[..44], &header(4, 48000, 1, 16));
+        assert_eq!(u16::from_le_bytes(wav[32..34].try_into().unwrap()), 2);
+        assert_eq!(&wav[44..], &[0x00, 0x40, 0x00, 0xC0]);
+        // The raw file itself is untouched.
+        assert_eq!(std::fs::read(&raw_path).unwrap(), samples);
+
+        let _ = std::fs::remove_file(raw_path);
+        let _ = std::fs::remove_file(wav_path);
+    }
+}
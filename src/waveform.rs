@@ -0,0 +1,105 @@
+//! Min/max envelope of a rendered file for the waveform preview.
+
+use crate::audio::{self, SampleReader, CHUNK_SAMPLES};
+
+/// Columns in the envelope read once per render, enough for any terminal;
+/// drawing narrows it with `rescale`.
+pub const ENVELOPE_COLUMNS: u16 = 4096;
+
+/// Splits the samples of `path` into at most `width` equal columns and
+/// returns each column's `(min, max)`. Files shorter than `width` get one
+/// column per sample; a missing or empty file gives no columns.
+pub fn render_waveform(path: &str, width: u16) -> Vec<(i64, i64)> {
+    let total = match audio::sample_count(path) {
+        Ok(n) if n > 0 && width > 0 => n,
+        _ => return Vec::new(),
+    };
+    let Ok(mut reader) = SampleReader::open(path) else {
+        return Vec::new();
+    };
+    let columns = total.min(width as u64);
+    let mut envelope: Vec<(i64, i64)> = Vec::with_capacity(columns as usize);
+    let mut chunk = Vec::new();
+    let mut index = 0u64;
+    while reader.read_chunk(&mut chunk, CHUNK_SAMPLES).unwrap_or(0) > 0 {
+        for &sample in &chunk {
+            let column = (index * columns / total) as usize;
+            let sample = sample as i64;
+            match envelope.get_mut(column) {
+                Some((lo, hi)) => {
+                    *lo = (*lo).min(sample);
+                    *hi = (*hi).max(sample);
+                }
+                None => envelope.push((sample, sample)),
+            }
+            index += 1;
+        }
+    }
+    envelope
+}
+
+/// Merges `envelope` down to at most `width` columns for drawing. One that
+/// already fits is returned as it is.
+pub fn rescale(envelope: &[(i64, i64)], width: u16) -> Vec<(i64, i64)> {
+    let (len, width) = (envelope.len(), width as usize);
+    if len <= width {
+        return envelope.to_vec();
+    }
+    (0..width)
+        .map(|column| {
+            let group = &envelope[column * len / width..(column + 1) * len / width];
+            let lo = group.iter().map(|&(lo, _)| lo).min().unwrap_or(0);
+            let hi = group.iter().map(|&(_, hi)| hi).max().unwrap_or(0);
+            (lo, hi)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::SampleWriter;
+
+    fn write_temp(name: &str, samples: &[i32]) -> String {
+        let path = std::env::temp_dir()
+            .join(format!("audionoise-waveform-{}-{}.raw", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned();
+        let mut writer = SampleWriter::create(&path).unwrap();
+        writer.write(samples).unwrap();
+        writer.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn missing_file_has_no_columns() {
+        assert!(render_waveform("/nonexistent/output.raw", 80).is_empty());
+    }
+
+    #[test]
+    fn short_file_gets_one_column_per_sample() {
+        let path = write_temp("short", &[5, -3, 7]);
+        assert_eq!(render_waveform(&path, 80), vec![(5, 5), (-3, -3), (7, 7)]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn columns_hold_min_and_max() {
+        let path = write_temp("envelope", &[1, -4, 2, 8, i32::MIN, i32::MAX, 0, 0]);
+        assert_eq!(
+            render_waveform(&path, 4),
+            vec![(-4, 1), (2, 8), (i32::MIN as i64, i32::MAX as i64), (0, 0)]
+        );
+        assert_eq!(render_waveform(&path, 0), vec![]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn rescaling_merges_columns_but_never_widens() {
+        let envelope = [(-1, 1), (-4, 2), (0, 8), (-2, 3), (5, 6)];
+        assert_eq!(rescale(&envelope, 2), vec![(-4, 2), (-2, 8)]);
+        assert_eq!(rescale(&envelope, 5), envelope);
+        assert_eq!(rescale(&envelope, 80), envelope);
+        assert!(rescale(&envelope, 0).is_empty());
+    }
+}